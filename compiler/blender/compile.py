@@ -27,9 +27,14 @@ class Settings:
 
 def write_u32(b: bytearray, v: any):
     b.extend(v.to_bytes(4, byteorder="big", signed=False))
+def write_u16(b: bytearray, v: any):
+    if v > 65535: raise Exception("Value is bigger than 65535")
+    b.extend(v.to_bytes(2, byteorder="big", signed=False))
 def write_u8(b: bytearray, v: any):
     if v > 255: raise Exception("Value is bigger than 255")
     b.extend(v.to_bytes(1, byteorder="big", signed=False))
+def write_f32(b: bytearray, v: any):
+    b.extend(struct.pack(">f", v))
 def write_str(b: bytearray, v: any):
     b.extend(str.encode(str(v))+b"#")
 def write_vec3(b: bytearray, v: any):
@@ -69,7 +74,7 @@ def set_last_frame():
 
 def export_frames(b: BufferedWriter, settings: Settings):
     rotation = settings.rotation.to_matrix().to_4x4()
-    write_u8(b, len(bpy.context.selected_pose_bones)) # Bones count
+    write_u16(b, len(bpy.context.selected_pose_bones)) # Bones count
     frames = bpy.context.scene.frame_end
     write_u32(b, frames) # Frames count
     for frame in range(frames):
@@ -84,6 +89,22 @@ def export_frames(b: BufferedWriter, settings: Settings):
             # bone pose in model space
             write_mat4x4_decomposed(b, bone.matrix)
 
+def export_events(b: BufferedWriter, path: Path):
+    events = json.load(open(path)) if path.exists() else []
+    write_u32(b, len(events)) # Events count
+    for event in sorted(events, key=lambda e: e["frame"]):
+        write_str(b, event["name"])
+        write_f32(b, event["frame"])
+
+def export_joints(b: BufferedWriter, path: Path):
+    humanoid = json.load(open(path)) if path.exists() else {}
+    humanoid = { bone: name for name, bone in humanoid.items() }
+    for bone in bpy.context.selected_pose_bones:
+        write_str(b, bone.name)
+        write_str(b, humanoid.get(bone.name, ""))
+        # bone rest pose in model space
+        write_mat4x4_decomposed(b, bone.bone.matrix_local)
+
 for path in Path("assets").glob("**/*.fbx"):
     start = time.time()
     
@@ -98,6 +119,8 @@ for path in Path("assets").glob("**/*.fbx"):
     bpy.ops.object.mode_set(mode="POSE")
     set_last_frame()
     export_frames(b, settings)
+    export_events(b, path.with_suffix(".events.json"))
+    export_joints(b, path.parent.joinpath(f"{path.stem}.humanoid.json"))
     
     open(path.with_suffix(".bin"), "wb+")\
         .write(zstd.ZSTD_compress(bytes(b), settings.compression_level))
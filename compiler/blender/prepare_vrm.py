@@ -1,4 +1,4 @@
-import bpy
+import bpy, json
 from pathlib import Path
 
 names = [
@@ -15,6 +15,13 @@ def clear_scene():
     for obj in bpy.context.scene.objects: obj.select_set(True)
     bpy.ops.object.delete()
 
+def export_humanoid(armature, path: Path):
+    humanoid = {}
+    for human_bone in armature.data.vrm_addon_extension.vrm0.humanoid.human_bones:
+        if human_bone.node.bone_name:
+            humanoid[human_bone.bone] = human_bone.node.bone_name
+    json.dump(humanoid, open(path, "w"), indent=4)
+
 def export(path: Path, us = False):
     bpy.ops.export_scene.fbx(
         filepath=str(path),
@@ -39,6 +46,7 @@ for path in Path("assets").glob("**/*.vrm"):
         bpy.ops.object.mode_set(mode='EDIT')
 
         armature = ob
+        export_humanoid(armature, path.parent.joinpath(f"{path.stem}.humanoid.json"))
         
         for name in names:
             ob.data.edit_bones.remove(ob.data.edit_bones[name])
@@ -0,0 +1,50 @@
+use std::{path::Path, io::BufReader, fs::File};
+use bincode::{Decode, Encode};
+
+use crate::{Settings, Asset};
+
+/// Grid of terrain heights, compiled from the luminance of grayscale images with 16 bits of precision
+#[derive(Encode, Decode, Clone, Default, Debug)]
+pub struct Heightmap {
+    pub width: u32,
+    pub depth: u32,
+    /// Heights of the lowest and highest sample values
+    pub min_height: f32,
+    pub max_height: f32,
+    /// Rows along z of `width` samples along x
+    pub samples: Vec<u16>
+}
+impl Heightmap {
+    /// Quantizes rows along z of `width` heights along x, between the lowest and highest of them
+    pub fn from_heights(width: u32, depth: u32, heights: &[f32]) -> Self {
+        assert_eq!(heights.len(), (width * depth) as usize, "Heightmap of {width}x{depth} samples");
+        let min_height = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let max_height = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let range = (max_height - min_height).max(f32::EPSILON);
+        Self {
+            width,
+            depth,
+            min_height,
+            max_height,
+            samples: heights.iter().map(|v| ((v - min_height) / range * u16::MAX as f32).round() as u16).collect()
+        }
+    }
+    /// Height of the sample at `x` and `z`, clamped to the edges
+    pub fn height(&self, x: u32, z: u32) -> f32 {
+        let sample = self.samples[(x.min(self.width - 1) + z.min(self.depth - 1) * self.width) as usize];
+        self.min_height + sample as f32 / u16::MAX as f32 * (self.max_height - self.min_height)
+    }
+}
+impl Asset for Heightmap {
+    fn compile(path: &Path, settings: &Settings) -> Self {
+        let reader = BufReader::new(File::open(path).unwrap());
+        let img = image::load(reader, image::ImageFormat::from_path(path).unwrap()).unwrap().into_luma16();
+        Self {
+            width: img.width(),
+            depth: img.height(),
+            min_height: 0.,
+            max_height: settings.heightmap_height,
+            samples: img.into_raw()
+        }
+    }
+}
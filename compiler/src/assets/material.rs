@@ -0,0 +1,102 @@
+use bincode::{Decode, Encode};
+use gltf::image::{Data, Format};
+
+use crate::{Image, Pixels};
+
+/// glTF metallic-roughness material, textures are embedded so a mesh carries everything it needs to render
+#[derive(Encode, Decode)]
+pub struct Material {
+    pub name: String,
+    pub base_color: [f32;4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32;3],
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_cutoff: Option<f32>,
+    /// glTF `BLEND` alpha mode, the material is drawn with the transparent surfaces
+    pub alpha_blend: bool,
+    pub double_sided: bool,
+    pub base_color_texture: Option<Image>,
+    /// Roughness in the green channel and metallic in the blue one
+    pub metallic_roughness_texture: Option<Image>,
+    pub normal_texture: Option<Image>,
+    pub occlusion_texture: Option<Image>,
+    pub emissive_texture: Option<Image>
+}
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color: [1.;4],
+            metallic: 1.,
+            roughness: 1.,
+            emissive: [0.;3],
+            normal_scale: 1.,
+            occlusion_strength: 1.,
+            alpha_cutoff: None,
+            alpha_blend: false,
+            double_sided: false,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None
+        }
+    }
+}
+impl Material {
+    pub fn from_gltf(material: &gltf::Material, images: &[Data], textures: bool) -> Self {
+        let pbr = material.pbr_metallic_roughness();
+        let image = |texture: gltf::Texture| if textures {
+            Some(Image::from_gltf(&images[texture.source().index()]))
+        } else {
+            None
+        };
+        Self {
+            name: material.name().unwrap_or_default().to_string(),
+            base_color: pbr.base_color_factor(),
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            emissive: material.emissive_factor(),
+            normal_scale: material.normal_texture().map(|v| v.scale()).unwrap_or(1.),
+            occlusion_strength: material.occlusion_texture().map(|v| v.strength()).unwrap_or(1.),
+            alpha_cutoff: match material.alpha_mode() {
+                gltf::material::AlphaMode::Mask => Some(material.alpha_cutoff().unwrap_or(0.5)),
+                _ => None
+            },
+            alpha_blend: material.alpha_mode() == gltf::material::AlphaMode::Blend,
+            double_sided: material.double_sided(),
+            base_color_texture: pbr.base_color_texture().and_then(|v| image(v.texture())),
+            metallic_roughness_texture: pbr.metallic_roughness_texture().and_then(|v| image(v.texture())),
+            normal_texture: material.normal_texture().and_then(|v| image(v.texture())),
+            occlusion_texture: material.occlusion_texture().and_then(|v| image(v.texture())),
+            emissive_texture: material.emissive_texture().and_then(|v| image(v.texture()))
+        }
+    }
+}
+
+/// Range of the mesh indices drawn with one material
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct Submesh {
+    pub material: Option<u32>,
+    pub start: u32,
+    pub len: u32
+}
+
+impl Image {
+    pub fn from_gltf(data: &Data) -> Self {
+        let pixels = match data.format {
+            Format::R8G8B8A8 => data.pixels.clone(),
+            Format::R8G8B8 => data.pixels.chunks(3).flat_map(|v| [v[0], v[1], v[2], 255]).collect(),
+            Format::R8G8 => data.pixels.chunks(2).flat_map(|v| [v[0], v[1], 0, 255]).collect(),
+            Format::R8 => data.pixels.iter().flat_map(|v| [*v, *v, *v, 255]).collect(),
+            format => panic!("Unsupported glTF image format: {format:?}")
+        };
+        Self {
+            width: data.width,
+            height: data.height,
+            pixels: Pixels::ARGB(pixels)
+        }
+    }
+}
@@ -1,14 +1,17 @@
-use std::path::Path;
+use std::{path::{Path, PathBuf}, collections::HashMap};
 use gltf::Node;
-use math::{Mat4x4, Transform, Vec3};
+use math::{Mat4x4, Transform, Vec3, SimpleTransform, Quaternion, Mat3x3};
 use bincode::{Decode, Encode};
 
-use crate::{Settings, Asset};
+use crate::{Settings, Asset, Material, Submesh};
 
 #[derive(Clone, Encode, Decode)]
 pub struct SkeletonJoint {
-    pub parents: Vec<u8>,
-    pub ibm: Mat4x4
+    pub name: String,
+    pub humanoid: Option<String>,
+    pub parents: Vec<u16>,
+    pub ibm: Mat4x4,
+    pub rest: SimpleTransform
 }
 
 #[derive(Clone, Encode, Decode)]
@@ -16,19 +19,21 @@ pub struct Skeleton {
     pub joints: Vec<SkeletonJoint>
 }
 
-#[derive(Encode, Decode)]
+#[derive(Default, Encode, Decode)]
 pub struct Mesh {
     pub skeleton: Option<Skeleton>,
     pub positions: Vec<[f32;3]>,
     pub uvs: Vec<[f32;2]>,
     pub normals: Vec<[f32;3]>,
-    pub joints: Vec<[u8;4]>,
+    pub joints: Vec<[u16;4]>,
     pub weights: Vec<[f32;4]>,
-    pub indices: Vec<u32>
+    pub indices: Vec<u32>,
+    pub materials: Vec<Material>,
+    pub submeshes: Vec<Submesh>
 }
 impl Asset for Mesh {
     fn compile(path: &Path, settings: &Settings) -> Self {
-        let (gltf, buffers, _) = gltf::import(path).unwrap();
+        let (gltf, buffers, images) = gltf::import(path).unwrap();
 
         let skeleton = if settings.skeleton {
             let skin = gltf.skins().next().unwrap();
@@ -36,12 +41,17 @@ impl Asset for Mesh {
             let ibms = skin_reader.read_inverse_bind_matrices().unwrap()
                 .map(|v|Mat4x4::from(v))
                 .collect::<Vec<_>>();
+            let humanoid = read_humanoid(path);
             let joints: Vec<Node> = skin.joints().collect();
             let joints = joints.iter()
                 .zip(ibms.clone())
                 .map(|(joint, ibm)| {
+                    let name = joint.name().unwrap_or_default().to_string();
                     SkeletonJoint {
+                        humanoid: humanoid.get(&name).cloned(),
+                        name,
                         parents: get_gltf_node_parents_id(&joints, &joint),
+                        rest: get_rest_pose(ibm),
                         ibm
                     }
                 })
@@ -84,9 +94,7 @@ impl Asset for Mesh {
         let joints = if settings.joints {
             readers.iter()
                 .map(|reader|
-                    reader.read_joints(0).unwrap().into_u16().map(|v|{
-                        [v[0] as u8, v[1] as u8, v[2] as u8, v[3] as u8]
-                    })
+                    reader.read_joints(0).unwrap().into_u16()
                 )
                 .flatten()
                 .collect()
@@ -100,18 +108,31 @@ impl Asset for Mesh {
         } else { vec![] };
 
         let mut index_reader_offset = 0;
+        let mut submeshes = Vec::with_capacity(primitives.len());
         let indices = readers.iter()
             .zip(readers_sizes)
-            .map(|(reader, reader_size)| {
+            .zip(primitives.iter())
+            .map(|((reader, reader_size), primitive)| {
                 let res = reader.read_indices().unwrap().into_u32()
                     .map(|i| i + index_reader_offset)
                     .collect::<Vec<_>>();
+                submeshes.push(Submesh {
+                    material: primitive.material().index().filter(|_| settings.materials).map(|v| v as u32),
+                    start: submeshes.last().map(|v: &Submesh| v.start + v.len).unwrap_or_default(),
+                    len: res.len() as u32
+                });
                 index_reader_offset += reader_size;
                 res
             })
             .flatten()
             .collect();
 
+        let materials = if settings.materials {
+            gltf.materials()
+                .map(|material| Material::from_gltf(&material, &images, settings.material_textures))
+                .collect()
+        } else { vec![] };
+
         Self {
             skeleton,
             positions,
@@ -119,7 +140,9 @@ impl Asset for Mesh {
             weights,
             uvs,
             normals,
-            indices
+            indices,
+            materials,
+            submeshes
         }
     }
 }
@@ -136,17 +159,45 @@ impl Mesh {
     }
 }
 
-fn get_gltf_node_parent_id(joints: &Vec<gltf::Node>, j: &gltf::Node) -> Option<u8> {
+/// Path of the `<stem>.humanoid.json` sidecar written by `prepare_vrm.py` next to an asset
+pub fn humanoid_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!("{}.humanoid.json", path.file_stem().unwrap().to_str().unwrap()))
+}
+
+/// Reads the humanoid sidecar of an asset, mapping its joint names to humanoid bones
+pub fn read_humanoid(path: &Path) -> HashMap<String, String> {
+    match std::fs::read(humanoid_path(path)) {
+        Ok(bytes) => serde_json::from_slice::<HashMap<String, String>>(&bytes).unwrap()
+            .into_iter()
+            .map(|(humanoid, joint)| (joint, humanoid))
+            .collect(),
+        Err(_) => HashMap::new()
+    }
+}
+
+fn get_rest_pose(ibm: Mat4x4) -> SimpleTransform {
+    let bind = ibm.inverted().unwrap();
+    SimpleTransform::new(
+        bind.w.truncate(),
+        Quaternion::from(Mat3x3::new(
+            bind.x.truncate().normalized(),
+            bind.y.truncate().normalized(),
+            bind.z.truncate().normalized()
+        )).normalised()
+    )
+}
+
+fn get_gltf_node_parent_id(joints: &Vec<gltf::Node>, j: &gltf::Node) -> Option<u16> {
     for (parent_id, joint) in joints.iter().enumerate() {
         for child in joint.children() {
             if child.index() == j.index() {
-                return Some(parent_id as u8)
+                return Some(parent_id as u16)
             }
         }
     }
     None
 }
-fn get_gltf_node_parents_id(joints: &Vec<gltf::Node>, j: &gltf::Node) -> Vec<u8> {
+fn get_gltf_node_parents_id(joints: &Vec<gltf::Node>, j: &gltf::Node) -> Vec<u16> {
     let mut res = Vec::new();
     let mut id = get_gltf_node_parent_id(joints, j);
     while id.is_some() {
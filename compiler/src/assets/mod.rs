@@ -1,2 +1,4 @@
 mod mesh;       pub use mesh::*;
-mod image;      pub use image::*;
\ No newline at end of file
+mod image;      pub use image::*;
+mod material;   pub use material::*;
+mod heightmap;  pub use heightmap::*;
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::{time::Instant, path::{PathBuf, Path}, sync::{Arc, mpsc::{channel, Sender}, Mutex}, io::{Write, Cursor}};
-use compiler::{Settings, Mesh, Asset, Image};
+use compiler::{Settings, Mesh, Asset, Image, Heightmap};
 
 pub fn compile(main_path: PathBuf) {
     std::fs::create_dir_all(&main_path).unwrap();
@@ -46,6 +46,7 @@ fn compile_file(path: PathBuf, settings: Settings) {
     let start = Instant::now();
     let bytes = match path.extension().unwrap().to_str().unwrap() {
         "gltf" | "glb" => Mesh::compile(&path, &settings).bytes(),
+        "jpg" | "jpeg" | "png" if settings.heightmap => Heightmap::compile(&path, &settings).bytes(),
         "jpg" | "jpeg" | "png" => Image::compile(&path, &settings).bytes(),
         "ttf" => std::fs::read(&path).unwrap(),
         _ => return
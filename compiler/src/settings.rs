@@ -2,15 +2,38 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-#[derive(Default, Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Settings {
     pub compression_level: i32,
     pub uvs: bool,
     pub normals: bool,
     pub joints: bool,
     pub skeleton: bool,
+    /// Embeds the glTF materials, `material_textures` also embeds their images
+    pub materials: bool,
+    pub material_textures: bool,
     pub image_opacity: bool,
-    pub image_scale: f32
+    pub image_scale: f32,
+    /// Compiles the images as `Heightmap`s, their white texels `heightmap_height` high
+    pub heightmap: bool,
+    pub heightmap_height: f32
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            compression_level: 0,
+            uvs: false,
+            normals: false,
+            joints: false,
+            skeleton: false,
+            materials: false,
+            material_textures: false,
+            image_opacity: false,
+            image_scale: 1.,
+            heightmap: false,
+            heightmap_height: 64.
+        }
+    }
 }
 impl Settings {
     pub fn merge(&mut self, path: &Path) {
@@ -1,6 +1,6 @@
-use std::sync::{Mutex, atomic::AtomicBool};
+use std::{path::PathBuf, sync::{Arc, Mutex, OnceLock, atomic::{AtomicBool, AtomicU32, Ordering}}};
 use math::MVec2;
-use wgpu::{Instance, Surface, Adapter, Device, Queue, SurfaceConfiguration, TextureUsages, CommandEncoder};
+use wgpu::{Instance, Surface, Adapter, Device, Queue, SurfaceConfiguration, CommandEncoder, Sampler};
 use winit::{
     event::{Event, WindowEvent, ElementState, KeyboardInput},
     event_loop::{ControlFlow, EventLoop}, window::Window, dpi::PhysicalSize
@@ -8,64 +8,140 @@ use winit::{
 
 use crate::{
     utils::{initialization::*, pressed_keys::PressedKeys},
-    CameraBuffer, Logger, Time, DepthTexture, OutputTexture, Script, ScriptEvent, ScriptInstance
+    CameraBuffer, Camera, Fog, GameClock, Logger, AdapterOptions, fatal, GraphicsSettings, WindowMode, settings::apply_window, Time, DynamicResolution, GpuTimer, OutputTexture, TexturePool, Script, ScriptEvent, ScriptInstance,
+    SkinningPool, SKINNING_POOL_CAPACITY, ComputeSkinning, HDR_FORMAT, DEPTH_FORMAT, ShaderModules, RenderPipelines
 };
 
 pub struct Engine {
-    pub window: Window,
+    /// `None` for headless engines, see `Engine::headless`
+    pub window: Option<Window>,
     pub instance: Instance,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
+    /// Size and format of the surface, or of the offscreen output of headless engines
     pub surface_config: Mutex<SurfaceConfiguration>,
     pub exit: AtomicBool,
     pub pressed_keys: PressedKeys,
     pub camera_buffer: CameraBuffer,
+    pub(crate) camera: Mutex<Camera>,
+    pub(crate) fog: Mutex<Fog>,
+    pub(crate) game_clock: Mutex<GameClock>,
+    pub skinning_pool: Arc<SkinningPool>,
+    pub(crate) compute_skinning: OnceLock<ComputeSkinning>,
+    pub(crate) material_sampler: OnceLock<Sampler>,
     pub time: Time,
-    pub depth_texture: Mutex<DepthTexture>,
     pub output_texture: Mutex<OutputTexture>,
+    pub(crate) texture_pool: Mutex<TexturePool>,
+    pub(crate) shader_modules: Mutex<ShaderModules>,
+    pub(crate) render_pipelines: Mutex<RenderPipelines>,
+    pub(crate) graphics_settings: Mutex<GraphicsSettings>,
+    /// Where `graphics_settings` are saved, headless engines do not keep them
+    pub(crate) graphics_settings_path: Option<PathBuf>,
+    sample_count: AtomicU32,
+    pub(crate) settings_changed: AtomicBool,
+    pub(crate) dynamic_resolution: Mutex<DynamicResolution>,
+    pub(crate) gpu_timer: Mutex<Option<GpuTimer>>,
     pub cursor_movement: MVec2,
     current_scene: Mutex<Option<ScriptInstance<()>>>
 }
 impl Engine {
     pub fn new() -> (EventLoop<()>, &'static Self) {
+        Self::with_options(AdapterOptions::default())
+    }
+    /// Engine with a window and the `GraphicsSettings` saved in the working directory,
+    /// when no adapter fits `options` a dialog lists the adapters found and the process exits
+    pub fn with_options(options: AdapterOptions) -> (EventLoop<()>, &'static Self) {
         Logger::new();
+        let settings_path = GraphicsSettings::path();
+        let settings = GraphicsSettings::load(&settings_path);
         let event_loop = EventLoop::new();
-        let window = new_window(&event_loop);
-        let instance = new_instance();
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        let adapter = new_adapter(&instance, &surface);
-        let (device, queue) = new_device(&adapter);
-        let surface_config = configure_surface(window.inner_size(), &device, &adapter, &surface);
+        let window = new_window(&event_loop, settings.resolution);
+        if settings.window_mode != WindowMode::Windowed {
+            apply_window(&window, &settings)
+        }
+        let instance = new_instance(options.all_backends());
+        let surface = unsafe { instance.create_surface(&window) }.unwrap_or_else(|e| fatal(&e.to_string()));
+        let adapter = options.select(&instance, Some(&surface)).unwrap_or_else(|e| fatal(&e));
+        let (device, queue) = new_device(&adapter, &options).unwrap_or_else(|e| fatal(&e));
+        let surface_config = configure_surface(window.inner_size(), settings.present_mode, &device, &adapter, &surface);
+        let mut s = Self::with_device(Some(window), instance, Some(surface), adapter, device, queue, surface_config);
+        s.time.set_max_fps(settings.max_fps);
+        s.graphics_settings_path = Some(settings_path);
+        let msaa = settings.msaa;
+        *s.graphics_settings.get_mut().unwrap() = settings;
+        let s: &'static Self = Box::leak(Box::new(s));
+        s.set_sample_count(msaa);
+        (event_loop, s)
+    }
+    /// Engine without a window that renders into its offscreen `output_texture`, read back with `Engine::read_output`
+    /// and driven by `Engine::frame`, `AdapterOptions::software` picks an adapter such as lavapipe or llvmpipe
+    pub fn headless(width: u32, height: u32, options: AdapterOptions) -> Result<&'static Self, String> {
+        let instance = new_instance(options.all_backends());
+        let adapter = options.select(&instance, None)?;
+        let (device, queue) = new_device(&adapter, &options)?;
+        let surface_config = headless_config(width, height);
+        let mut s = Self::with_device(None, instance, None, adapter, device, queue, surface_config);
+        *s.graphics_settings.get_mut().unwrap() = GraphicsSettings {
+            resolution: (width, height),
+            msaa: 1,
+            ..Default::default()
+        };
+        Ok(Box::leak(Box::new(s)))
+    }
+    fn with_device(
+        window: Option<Window>,
+        instance: Instance,
+        surface: Option<Surface>,
+        adapter: Adapter,
+        device: Device,
+        queue: Queue,
+        surface_config: SurfaceConfiguration
+    ) -> Self {
         let camera_buffer = CameraBuffer::new(&device);
-        let depth_texture = DepthTexture::new(&device, surface_config.width, surface_config.height, TextureUsages::RENDER_ATTACHMENT).into();
+        let skinning_pool = SkinningPool::new(&device, SKINNING_POOL_CAPACITY).into();
         let output_texture = OutputTexture::new(&device, surface_config.width, surface_config.height, surface_config.format).into();
-        let s = Self {
+        let gpu_timer = GpuTimer::new(&device, &queue).into();
+        Self {
             window,
-            instance: instance,
+            instance,
             device,
             queue,
             surface,
             surface_config: surface_config.into(),
-            adapter: adapter,
+            adapter,
             exit: Default::default(),
             pressed_keys: Default::default(),
             camera_buffer,
+            camera: Default::default(),
+            fog: Default::default(),
+            game_clock: Default::default(),
+            skinning_pool,
+            compute_skinning: Default::default(),
+            material_sampler: Default::default(),
             time: Time::new(),
-            depth_texture,
             output_texture,
+            texture_pool: Default::default(),
+            shader_modules: Default::default(),
+            render_pipelines: Default::default(),
+            graphics_settings: Default::default(),
+            graphics_settings_path: None,
+            sample_count: AtomicU32::new(1),
+            settings_changed: Default::default(),
+            dynamic_resolution: Default::default(),
+            gpu_timer,
             cursor_movement: Default::default(),
             current_scene: Default::default()
-        };
-        (event_loop, Box::leak(Box::new(s)))
+        }
     }
     pub fn start(&'static self, event_loop: EventLoop<()>) -> ! {
-        self.window.set_visible(true);
+        let window = self.window.as_ref().expect("Headless engines have no event loop, they are driven by Engine::frame");
+        window.set_visible(true);
         let mut window_resized = None;
         let mut window_focus = None;
         event_loop.run(move |event, _, control_flow| {
-            if self.exit.load(std::sync::atomic::Ordering::Relaxed) {
+            if self.exit.load(Ordering::Relaxed) {
                 self.close_threads();
                 return *control_flow = ControlFlow::Exit
             }
@@ -87,30 +163,8 @@ impl Engine {
                 },
                 Event::WindowEvent { event: WindowEvent::Focused(focus), .. } => window_focus = Some(focus),
                 Event::MainEventsCleared => {
-                    self.time.update();
-
-                    if let Some(new_size) = window_resized.take() {
-                        self.emit_event(ScriptEvent::Nothing);
-                        self.resize(new_size);
-                        self.emit_events(vec![
-                            ScriptEvent::WindowResized,
-                            ScriptEvent::Nothing
-                        ]);
-                    }
-
-                    let mut update_events = vec![ ScriptEvent::Update ];
-                    if let Some(focus) = window_focus {
-                        if focus { update_events.push(ScriptEvent::WindowFocus) }
-                        else { update_events.push(ScriptEvent::WindowBlur) }
-                    }
-                    self.emit_events(update_events);
-                    
-                    self.emit_events(vec![
-                        ScriptEvent::Render,
-                        ScriptEvent::Nothing
-                    ]);
-
-                    self.window.request_redraw();
+                    self.step(window_resized.take(), window_focus);
+                    window.request_redraw();
                     self.cursor_movement.set(0.);
                 },
                 Event::RedrawRequested(_) => {}
@@ -118,19 +172,57 @@ impl Engine {
             }
         })
     }
+    /// Updates and renders the scripts once, headless engines call it for every frame they draw.
+    /// Returns once the scripts finished rendering and the GPU finished their work
+    pub fn frame(&self) {
+        self.step(None, None);
+        // `step` sends `Render` last, the scripts only take another event after rendering
+        self.emit_event(ScriptEvent::Nothing);
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+    fn step(&self, window_resized: Option<PhysicalSize<u32>>, window_focus: Option<bool>) {
+        self.time.update();
+        self.game_clock.lock().unwrap().advance(self.time.delta() as f64);
+
+        if self.settings_changed.swap(false, Ordering::Relaxed) {
+            self.emit_event(ScriptEvent::Nothing);
+            self.emit_events(vec![
+                ScriptEvent::SettingsChanged,
+                ScriptEvent::Nothing
+            ]);
+        }
+
+        if let Some(new_size) = window_resized {
+            self.emit_event(ScriptEvent::Nothing);
+            self.resize(new_size);
+            self.emit_events(vec![
+                ScriptEvent::WindowResized,
+                ScriptEvent::Nothing
+            ]);
+        }
+
+        let mut update_events = vec![ ScriptEvent::Update ];
+        if let Some(focus) = window_focus {
+            if focus { update_events.push(ScriptEvent::WindowFocus) }
+            else { update_events.push(ScriptEvent::WindowBlur) }
+        }
+        self.emit_events(update_events);
+
+        self.emit_events(vec![
+            ScriptEvent::Render,
+            ScriptEvent::Nothing
+        ]);
+    }
     pub fn resize(&self, new_size: PhysicalSize<u32>) {
         if new_size.width == 0 || new_size.height == 0 { return }
         self.instance.poll_all(true);
         let mut surface_config = self.surface_config.lock().unwrap();
         surface_config.width = new_size.width;
         surface_config.height = new_size.height;
-        self.surface.configure(&self.device, &surface_config);
-        *self.depth_texture.lock().unwrap() = DepthTexture::new(
-            &self.device,
-            surface_config.width,
-            surface_config.height,
-            TextureUsages::RENDER_ATTACHMENT
-        );
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &surface_config);
+        }
+        self.texture_pool.lock().unwrap().clear();
         *self.output_texture.lock().unwrap() = OutputTexture::new(
             &self.device,
             surface_config.width,
@@ -138,15 +230,37 @@ impl Engine {
             surface_config.format
         );
     }
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count.load(Ordering::Relaxed)
+    }
+    /// Multisample anti aliasing of the scene targets and of the `shader!` pipelines,
+    /// scripts recreate their shaders when `Script::settings_changed` is called
+    pub fn set_sample_count(&self, samples: u32) {
+        let supported = [HDR_FORMAT, DEPTH_FORMAT].into_iter()
+            .all(|format| self.adapter.get_texture_format_features(format).flags.sample_count_supported(samples));
+        if !supported {
+            return warn!("{samples} samples per pixel are not supported by this adapter")
+        }
+        if self.sample_count.swap(samples, Ordering::Relaxed) != samples {
+            self.texture_pool.lock().unwrap().clear();
+            self.settings_changed.store(true, Ordering::Relaxed)
+        }
+        self.graphics_settings.lock().unwrap().msaa = samples
+    }
     pub fn exit(&self) {
-        self.exit.store(true, std::sync::atomic::Ordering::Relaxed)
+        self.exit.store(true, Ordering::Relaxed)
     }
     pub fn encoder(&self) -> CommandEncoder {
         self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default())
     }
+    /// Copies `output_texture` to the surface, headless engines only submit the `encoder`
     pub fn present_output_texture(&self, mut encoder: CommandEncoder) {
+        let Some(surface) = &self.surface else {
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return
+        };
         let output_texture = self.output_texture.lock().unwrap();
-        let surface_texture = match self.surface.get_current_texture() {
+        let surface_texture = match surface.get_current_texture() {
             Ok(v) => v,
             Err(e) => return error!("{e}")
         };
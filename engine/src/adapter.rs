@@ -0,0 +1,99 @@
+use wgpu::{Adapter, AdapterInfo, Backends, DeviceType, Features, Instance, Surface};
+
+/// How an engine picks its backend, adapter and device features.
+/// `WGPU_BACKEND` and `WGPU_ADAPTER_NAME` override the defaults, as in the wgpu examples
+#[derive(Clone, Debug)]
+pub struct AdapterOptions {
+    /// Tried in order, the first backend with a suitable adapter is used
+    pub backends: Vec<Backends>,
+    /// Adapters whose name contains it, ignoring case, are preferred
+    pub name: Option<String>,
+    /// Preferred adapter types, in order, adapters of other types come last
+    pub device_types: Vec<DeviceType>,
+    /// Only software adapters such as lavapipe, llvmpipe or WARP
+    pub software: bool,
+    /// Adapters without them are skipped
+    pub required_features: Features,
+    /// Enabled when the adapter has them
+    pub optional_features: Features
+}
+impl Default for AdapterOptions {
+    fn default() -> Self {
+        Self {
+            backends: match wgpu::util::backend_bits_from_env() {
+                Some(backends) => vec![backends],
+                None => vec![Backends::VULKAN, Backends::DX12, Backends::METAL, Backends::GL]
+            },
+            name: std::env::var("WGPU_ADAPTER_NAME").ok(),
+            device_types: vec![DeviceType::DiscreteGpu, DeviceType::IntegratedGpu, DeviceType::VirtualGpu, DeviceType::Cpu],
+            software: false,
+            required_features: Features::empty(),
+            optional_features: Features::all().difference(Features::MAPPABLE_PRIMARY_BUFFERS)
+        }
+    }
+}
+impl AdapterOptions {
+    pub fn software() -> Self {
+        Self {
+            software: true,
+            ..Default::default()
+        }
+    }
+    /// Every backend the engine may try
+    pub fn all_backends(&self) -> Backends {
+        self.backends.iter().fold(Backends::empty(), |all, v| all | *v)
+    }
+    /// Why an adapter can not be used, `None` when it can
+    pub fn reject(&self, info: &AdapterInfo, features: Features, surface_supported: bool) -> Option<String> {
+        if !surface_supported {
+            Some("cannot present to the window".into())
+        } else if self.software && info.device_type != DeviceType::Cpu {
+            Some("not a software adapter".into())
+        } else if !features.contains(self.required_features) {
+            Some(format!("missing {:?}", self.required_features.difference(features)))
+        } else {
+            None
+        }
+    }
+    /// Lower ranks are preferred: a matching name, then the backend order, then the device type order
+    pub fn rank(&self, info: &AdapterInfo) -> (bool, usize, usize) {
+        let backend = self.backends.iter()
+            .position(|v| v.contains(Backends::from(info.backend)))
+            .unwrap_or(self.backends.len());
+        let named = self.name.as_ref().is_none_or(|name| info.name.to_lowercase().contains(&name.to_lowercase()));
+        let device_type = self.device_types.iter()
+            .position(|v| *v == info.device_type)
+            .unwrap_or(self.device_types.len());
+        (!named, backend, device_type)
+    }
+    /// Features requested from the device of `adapter`
+    pub fn features(&self, adapter: &Adapter) -> Features {
+        self.required_features | (adapter.features() & self.optional_features)
+    }
+    /// Best suitable adapter, or a report of every adapter found and why it was skipped
+    pub fn select(&self, instance: &Instance, surface: Option<&Surface>) -> Result<Adapter, String> {
+        let mut report = Vec::new();
+        let mut best: Option<((bool, usize, usize), Adapter)> = None;
+        for adapter in instance.enumerate_adapters(self.all_backends()) {
+            let info = adapter.get_info();
+            let supported = surface.is_none_or(|surface| adapter.is_surface_supported(surface));
+            let reason = self.reject(&info, adapter.features(), supported);
+            let rank = self.rank(&info);
+            info!("{:?}: {} ({}, {}), {:?} ({}), {}",
+                info.device_type,
+                info.name, info.vendor, info.device,
+                info.backend, info.driver_info,
+                reason.as_deref().unwrap_or("suitable")
+            );
+            report.push(format!("{} ({:?}, {:?}): {}", info.name, info.backend, info.device_type, reason.as_deref().unwrap_or("suitable")));
+            if reason.is_none() && best.as_ref().is_none_or(|(best, _)| rank < *best) {
+                best = Some((rank, adapter))
+            }
+        }
+        match best {
+            Some((_, adapter)) => Ok(adapter),
+            None if report.is_empty() => Err(format!("No graphics adapter was found for {:?}", self.all_backends())),
+            None => Err(format!("No suitable graphics adapter was found:\n{}", report.join("\n")))
+        }
+    }
+}
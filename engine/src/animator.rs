@@ -1,53 +1,60 @@
 use std::sync::Arc;
 use compiler::Skeleton;
 use math::{SimpleTransform, Vec3};
+use crossbeam_channel::{Sender, Receiver, TrySendError};
 use wgpu::{Buffer, util::DeviceExt};
-use crate::{Mesh, Vertex, Engine, Animation, AnimationFrame};
-
-pub const MAX_JOINTS: usize = 96;  // 16, 17, ..., 31, 32, 48, 64, 96, 128, 256, 512, 1024, 2048, 4096
+use crate::{Mesh, Vertex, Engine, Animation, AnimationFrame, AnimationEvent, IkConstraint, SkinningAllocation};
 
 #[repr(C)]
-#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct AnimatorBindingFrame {
-    pub joints: [[[f32;4];4]; MAX_JOINTS]
-}
-impl Default for AnimatorBindingFrame {
-    fn default() -> Self {
-        Self {
-            joints: [Default::default(); MAX_JOINTS]
-        }
-    }
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AnimatorBinding {
+    pub offset: u32,
+    pub joints: u32,
+    pub _padding: [u32;2]
 }
 
 pub struct Animator {
     pub skeleton: Arc<Skeleton>,
     pub transform: SimpleTransform,
     pub buffer: Arc<Buffer>,
+    pub allocation: Arc<SkinningAllocation>,
     pub speed: f32,
     pub frame: AnimationFrame,
     pub animation: Animation,
-    pub time: f32
+    pub time: f32,
+    pub ik: Vec<IkConstraint>,
+    events: Vec<AnimationEvent>,
+    subscribers: Vec<Sender<AnimationEvent>>
 }
 impl Animator {
-    pub fn new<V: Vertex>(e: &Engine, mesh: &Mesh<V>, animation: Animation) -> Self {
-        Self {
-            skeleton: mesh.skeleton.as_ref().unwrap().clone(),
+    /// `None` when the skinning pool has no room left for the joints of the skeleton
+    pub fn new<V: Vertex>(e: &Engine, mesh: &Mesh<V>, animation: Animation) -> Option<Self> {
+        let skeleton = mesh.skeleton.as_ref().unwrap().clone();
+        assert_joints(&skeleton, &animation);
+        let allocation = e.skinning_pool.allocate(skeleton.joints.len() as u32)?;
+        Some(Self {
+            skeleton,
             transform: Default::default(),
             buffer: e.device.create_buffer_init(
                 &wgpu::util::BufferInitDescriptor {
                     label: None,
-                    contents: bytemuck::bytes_of(&AnimatorBindingFrame::default()),
-                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+                    contents: bytemuck::bytes_of(&AnimatorBinding {
+                        offset: allocation.offset,
+                        joints: allocation.joints,
+                        ..Default::default()
+                    }),
+                    usage: wgpu::BufferUsages::UNIFORM
                 }
             ).into(),
+            allocation: allocation.into(),
             speed: 30.,
             frame: animation.frames.first().unwrap().clone(),
             animation,
-            time: 0.
-        }
-    }
-    fn finished(&self) -> bool {
-        self.time as usize >= self.animation.frames.len()
+            time: 0.,
+            ik: Vec::new(),
+            events: Vec::new(),
+            subscribers: Vec::new()
+        })
     }
     fn is_last_frame(&self) -> bool {
         self.time as usize >= self.animation.frames.len().saturating_sub(1)
@@ -69,11 +76,12 @@ impl Animator {
     }
     pub fn set_animation(&mut self, animation: Animation) {
         if animation.id != self.animation.id {
+            assert_joints(&self.skeleton, &animation);
             self.reset_animation();
             self.animation = animation
         }
     }
-    fn get_binding_frame(&mut self, delta_time: f32) -> AnimatorBindingFrame {
+    fn get_binding_frame(&mut self, delta_time: f32) -> Vec<[[f32;4];4]> {
         let mut cur_frame = self.current_frame().clone();
         let next_frame = self.next_frame();
         
@@ -81,29 +89,63 @@ impl Animator {
         self.frame.lerp(&cur_frame, delta_time * 10.);
 
         let transform = self.transform * self.frame.root;
+
+        let solved;
+        let frame = if self.ik.is_empty() {
+            &self.frame
+        } else {
+            let mut frame = self.frame.clone();
+            for constraint in self.ik.iter() {
+                constraint.solve(&self.skeleton, &mut frame, transform)
+            }
+            solved = frame;
+            &solved
+        };
         
-        let mut binding_frame = AnimatorBindingFrame::default();
-        for i in 0..cur_frame.joints.len() {
-            binding_frame.joints[i] = (transform * (self.frame.joints[i] * self.skeleton.joints[i].ibm)).into()
-        }
-        binding_frame
+        frame.joints.iter()
+            .zip(self.skeleton.joints.iter())
+            .map(|(joint, skeleton_joint)| (transform * (*joint * skeleton_joint.ibm)).into())
+            .collect()
     }
     fn update_time(&mut self, delta_time: f32) {
-        self.time += delta_time * self.speed;
-        if self.finished() {
-            self.reset_animation()
+        let amount = delta_time * self.speed;
+        self.events = self.animation.events_between(self.time, amount).into_iter().cloned().collect();
+        self.time = (self.time + amount).rem_euclid(self.animation.frames.len() as f32);
+        if !self.events.is_empty() {
+            self.subscribers.retain(|subscriber| {
+                self.events.iter().all(|event| !matches!(subscriber.try_send(event.clone()), Err(TrySendError::Disconnected(_))))
+            })
         }
     }
+    /// Events crossed during the last `update`
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+    /// Receives every event crossed from now on, can be moved to other scripts
+    pub fn subscribe(&mut self) -> Receiver<AnimationEvent> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.subscribers.push(tx);
+        rx
+    }
     pub fn update(&mut self, e: &Engine) {
         let delta_time = e.time.delta();
         let binding_frame = self.get_binding_frame(delta_time);
         self.update_time(delta_time);
-        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&binding_frame))
+        self.allocation.write(e, &binding_frame)
     }
 }
 
+fn assert_joints(skeleton: &Skeleton, animation: &Animation) {
+    let joints = animation.frames.first().map(|frame| frame.joints.len()).unwrap_or_default();
+    assert!(
+        joints == skeleton.joints.len(),
+        "Animation has {joints} joints but the skeleton has {}, use a `Retarget` to map it",
+        skeleton.joints.len()
+    )
+}
+
 impl Engine {
-    pub fn animator<V: Vertex>(&self, mesh: &Mesh<V>, animation: Animation) -> Animator {
+    pub fn animator<V: Vertex>(&self, mesh: &Mesh<V>, animation: Animation) -> Option<Animator> {
         Animator::new(self, mesh, animation)
     }
 }
\ No newline at end of file
@@ -24,29 +24,93 @@ impl AnimationFrame {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub frame: f32
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationJoint {
+    pub name: String,
+    pub humanoid: Option<String>,
+    pub rest: SimpleTransform
+}
+
 #[derive(Clone)]
 pub struct Animation {
     pub id: Id,
     pub frames: Arc<Vec<AnimationFrame>>,
+    pub events: Arc<Vec<AnimationEvent>>,
+    pub joints: Arc<Vec<AnimationJoint>>,
     pub keep_root_translation_axis: Vec3
 }
+impl Animation {
+    pub fn new(frames: Vec<AnimationFrame>, events: Vec<AnimationEvent>, joints: Vec<AnimationJoint>) -> Self {
+        Self {
+            id: ID.next(),
+            frames: frames.into(),
+            events: events.into(),
+            joints: joints.into(),
+            keep_root_translation_axis: Default::default()
+        }
+    }
+    /// Events crossed when playback advances `amount` frames from `from`, wrapping around the clip as many times as needed
+    pub fn events_between(&self, from: f32, amount: f32) -> Vec<&AnimationEvent> {
+        let length = self.frames.len() as f32;
+        let mut res = Vec::new();
+        if self.events.is_empty() || length == 0. || amount <= 0. { return res }
+        let mut start = from.rem_euclid(length);
+        let mut remaining = amount;
+        while remaining > 0. {
+            let end = (start + remaining).min(length);
+            res.extend(self.events.iter().filter(|event| event.frame >= start && event.frame < end));
+            remaining -= end - start;
+            start = 0.
+        }
+        res
+    }
+}
 impl Engine {
     pub fn load_animation(&self, path: impl AsRef<Path>) -> Animation {
         let mut r = Reader::new(path.as_ref());
         assert!(r.read_u8() == b'A');
 
-        let joints = r.read_u8() as usize;
+        let joints = r.read_u16() as usize;
         let frames = r.read_u32() as usize;
 
-        Animation {
-            id: ID.next(),
-            frames: (0..frames).map(|_| {
-                AnimationFrame {
-                    root: r.read_transform(),
-                    joints: (0..joints).map(|_| r.read_transform() ).collect()
+        let frames = (0..frames).map(|_| {
+            AnimationFrame {
+                root: r.read_transform(),
+                joints: (0..joints).map(|_| r.read_transform() ).collect()
+            }
+        }).collect::<Vec<_>>();
+
+        let events = if r.is_finished() { vec![] } else {
+            (0..r.read_u32()).map(|_| {
+                AnimationEvent {
+                    name: read_name(&mut r),
+                    frame: r.read_f32()
                 }
-            }).collect::<Vec<_>>().into(),
-            keep_root_translation_axis: Default::default()
-        }
+            }).collect()
+        };
+
+        let joints = if r.is_finished() { vec![] } else {
+            (0..joints).map(|_| {
+                AnimationJoint {
+                    name: read_name(&mut r),
+                    humanoid: Some(read_name(&mut r)).filter(|v| !v.is_empty()),
+                    rest: r.read_transform()
+                }
+            }).collect()
+        };
+
+        Animation::new(frames, events, joints)
     }
+}
+
+fn read_name(r: &mut Reader) -> String {
+    let mut name = r.read_str();
+    name.pop();
+    name
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::{path::Path, marker::PhantomData, sync::Arc};
 use compiler::Skeleton;
+use math::{Aabb, Vec3};
 use wgpu::util::DeviceExt;
 
 use crate::{Engine, Vertex, decode};
@@ -9,28 +10,44 @@ pub struct Mesh<V: Vertex> {
     vertex_type: PhantomData<V>,
     pub vertices_buffer: Arc<wgpu::Buffer>,
     pub vertices_len: u32,
-    pub skeleton: Option<Arc<Skeleton>>
+    pub skeleton: Option<Arc<Skeleton>>,
+    /// Bounds of the vertex positions, in the bind pose for skinned meshes
+    pub bounds: Aabb
+}
+impl<V: Vertex> Mesh<V> {
+    pub fn new(vertices_buffer: Arc<wgpu::Buffer>, vertices_len: u32, skeleton: Option<Arc<Skeleton>>, bounds: Aabb) -> Self {
+        Self {
+            vertex_type: PhantomData,
+            vertices_buffer,
+            vertices_len,
+            skeleton,
+            bounds
+        }
+    }
 }
 impl Engine {
     pub fn load_mesh<V: Vertex>(&self, path: impl AsRef<Path>) -> Mesh<V> {
-        let mesh: compiler::Mesh = decode(&path);
+        self.create_mesh(&decode(&path))
+    }
+    pub fn create_mesh<V: Vertex>(&self, mesh: &compiler::Mesh) -> Mesh<V> {
         let vertices_len = mesh.indices.len() as u32;
         let mut contents = Vec::with_capacity(mesh.indices.len() * bytemuck::bytes_of(&V::default()).len());
-        for i in mesh.indices {
+        for i in mesh.indices.iter().copied() {
             contents.append(&mut bytemuck::bytes_of(&V::new(i as usize, &mesh.positions, &mesh.uvs, &mesh.normals, &mesh.joints, &mesh.weights)).to_vec())
         }
 
         let vertices_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: &contents,
-            usage: wgpu::BufferUsages::VERTEX
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE
         });
 
         Mesh {
             vertex_type: PhantomData::default(),
             vertices_buffer: vertices_buffer.into(),
             vertices_len,
-            skeleton: if let Some(skeleton) = mesh.skeleton { Some(skeleton.into()) } else { None }
+            skeleton: mesh.skeleton.clone().map(Arc::new),
+            bounds: Aabb::from_points(mesh.positions.iter().map(|v| Vec3::from(*v)))
         }
     }
 }
\ No newline at end of file
@@ -1,10 +1,10 @@
 use std::{path::Path, sync::Arc};
 use wgpu::{
     Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, SamplerDescriptor,
-    TextureView, Sampler, Device, TextureDescriptor, BindGroup, TextureUsages
+    TextureView, Sampler, Device, TextureDescriptor, BindGroup, TextureUsages, TextureViewDimension
 };
 
-use crate::{Engine, decode};
+use crate::{Engine, decode, environment::{srgb_to_linear, linear_to_srgb}};
 
 pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
@@ -42,7 +42,10 @@ impl Texture {
 }
 impl Engine {
     pub fn load_texture(&self, path: impl AsRef<Path>) -> Texture {
-        let image: compiler::Image = decode(&path);
+        self.texture_from_image(decode(&path), TextureFormat::Rgba8UnormSrgb)
+    }
+    /// `format` is `Rgba8UnormSrgb` for colors and `Rgba8Unorm` for data like normals or roughness
+    pub fn texture_from_image(&self, image: compiler::Image, format: TextureFormat) -> Texture {
         let width = image.width;
         let height = image.height;
         
@@ -58,7 +61,7 @@ impl Engine {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 view_formats: &[]
             }
@@ -114,19 +117,120 @@ impl Engine {
     }
 }
 
+/// Mip levels of RGBA pixels down to 1x1, each texel the average of 2x2 of the level above. The colors of
+/// `srgb` images are averaged in linear space
+pub fn mip_chain(width: u32, height: u32, pixels: Vec<u8>, srgb: bool) -> Vec<(Vec<u8>, u32, u32)> {
+    let mut chain = vec![(pixels, width, height)];
+    loop {
+        let (pixels, width, height) = chain.last().unwrap();
+        let (width, height) = (*width, *height);
+        if (width, height) == (1, 1) { break chain }
+        let (w, h) = ((width / 2).max(1), (height / 2).max(1));
+        let texel = |x: u32, y: u32, c: usize| {
+            let v = pixels[((x.min(width - 1) + y.min(height - 1) * width) * 4) as usize + c];
+            if srgb && c < 3 { srgb_to_linear(v) } else { v as f32 / 255. }
+        };
+        let level = (0..w * h)
+            .flat_map(|i| {
+                let (x, y) = (i % w * 2, i / w * 2);
+                (0..4).map(move |c| {
+                    let v = (texel(x, y, c) + texel(x + 1, y, c) + texel(x, y + 1, c) + texel(x + 1, y + 1, c)) / 4.;
+                    if srgb && c < 3 { linear_to_srgb(v) } else { (v * 255.).round() as u8 }
+                })
+            })
+            .collect();
+        chain.push((level, w, h))
+    }
+}
+
+/// Images of the same size in the layers of one texture, sampled by index in the shaders
+#[derive(Clone)]
+pub struct TextureArray {
+    pub texture: Arc<wgpu::Texture>,
+    pub view: Arc<TextureView>,
+    pub layers: u32
+}
+impl Engine {
+    pub fn texture_array_from_images(&self, images: Vec<compiler::Image>, format: TextureFormat) -> TextureArray {
+        let (width, height) = images.first().map(|v| (v.width, v.height)).expect("Texture array without images");
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: images.len() as u32
+        };
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some("Texture array"),
+            size,
+            mip_level_count: width.max(height).ilog2() + 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        for (layer, image) in images.into_iter().enumerate() {
+            assert!(image.width == width && image.height == height, "Texture array layers of different sizes");
+            let mips = mip_chain(width, height, image.get_pixels_rgba(), format.is_srgb());
+            for (mip, (pixels, w, h)) in mips.into_iter().enumerate() {
+                self.queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: mip as u32,
+                        origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                        aspect: wgpu::TextureAspect::All
+                    },
+                    &pixels,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * w),
+                        rows_per_image: Some(h)
+                    },
+                    Extent3d { width: w, height: h, depth_or_array_layers: 1 }
+                )
+            }
+        }
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        TextureArray {
+            layers: size.depth_or_array_layers,
+            texture: texture.into(),
+            view: view.into()
+        }
+    }
+}
+
 pub struct DepthTexture {
     pub texture: wgpu::Texture,
     pub view: TextureView,
-    pub sampler: Sampler
+    pub sampler: Sampler,
+    /// Returns 1 where the reference depth is less or equal to the stored one, filtered over 2x2 texels
+    pub comparison_sampler: Sampler
 }
 impl DepthTexture {
     pub fn new(device: &Device, width: u32, height: u32, usage: TextureUsages) -> Self {
+        Self::create(device, width, height, 1, usage, TextureViewDimension::D2)
+    }
+    /// One layer per view, e.g. shadow cascades
+    pub fn new_array(device: &Device, width: u32, height: u32, layers: u32, usage: TextureUsages) -> Self {
+        Self::create(device, width, height, layers, usage, TextureViewDimension::D2Array)
+    }
+    pub fn layer_view(&self, layer: u32) -> TextureView {
+        self.texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
+    fn create(device: &Device, width: u32, height: u32, layers: u32, usage: TextureUsages, dimension: TextureViewDimension) -> Self {
         let texture = device.create_texture(&TextureDescriptor {
             label: None,
             size: Extent3d {
                 width,
                 height,
-                depth_or_array_layers: 1
+                depth_or_array_layers: layers
             },
             mip_level_count: 1,
             sample_count: 1,
@@ -135,7 +239,10 @@ impl DepthTexture {
             usage,
             view_formats: &[]
         });
-        let view = texture.create_view(&TextureViewDescriptor::default());
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(dimension),
+            ..Default::default()
+        });
         let sampler = device.create_sampler(
             &SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -147,10 +254,23 @@ impl DepthTexture {
                 ..Default::default()
             }
         );
+        let comparison_sampler = device.create_sampler(
+            &SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                ..Default::default()
+            }
+        );
         Self {
             texture,
             view,
-            sampler
+            sampler,
+            comparison_sampler
         }
     }
 }
@@ -181,4 +301,56 @@ impl OutputTexture {
             view
         }
     }
+}
+impl Engine {
+    /// Copies the last frame of `output_texture` back to the CPU, waiting for the GPU to finish it
+    pub fn read_output(&self) -> compiler::Image {
+        let output_texture = self.output_texture.lock().unwrap();
+        let texture = &output_texture.texture;
+        let (width, height) = (texture.width(), texture.height());
+        let bgra = match texture.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => panic!("Output format {format:?} can not be read back")
+        };
+        let row = 4 * width;
+        let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Output readback"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let mut encoder = self.encoder();
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height)
+                }
+            },
+            texture.size()
+        );
+        drop(output_texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |res| res.unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+        let mut pixels = Vec::with_capacity((row * height) as usize);
+        for padded in slice.get_mapped_range().chunks(padded_row as usize) {
+            pixels.extend_from_slice(&padded[..row as usize])
+        }
+        if bgra {
+            pixels.chunks_mut(4).for_each(|v| v.swap(0, 2))
+        }
+        compiler::Image {
+            width,
+            height,
+            pixels: compiler::Pixels::ARGB(pixels)
+        }
+    }
 }
\ No newline at end of file
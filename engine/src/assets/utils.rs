@@ -43,6 +43,14 @@ impl Reader {
         self.bytes[self.cursor-1]
     }
     #[inline(always)]
+    pub fn read_u16(&mut self) -> u16 {
+        self.cursor += 2;
+        u16::from_be_bytes([
+            self.bytes[self.cursor-2],
+            self.bytes[self.cursor-1]
+        ])
+    }
+    #[inline(always)]
     pub fn read_u32(&mut self) -> u32 {
         self.cursor += 4;
         u32::from_be_bytes([
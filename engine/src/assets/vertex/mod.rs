@@ -5,6 +5,8 @@ pub mod pu;
 pub mod puj;
 pub mod pnj;
 pub mod pj;
+pub mod pn;
+pub mod pnu;
 
 pub trait Vertex: Default + Pod {
     const ATTRIBUTES: &'static [wgpu::VertexAttribute];
@@ -13,5 +15,5 @@ pub trait Vertex: Default + Pod {
         step_mode: wgpu::VertexStepMode::Vertex,
         attributes: Self::ATTRIBUTES
     };
-    fn new(i: usize, positions: &[[f32;3]], uvs: &[[f32;2]], normals: &[[f32;3]], joints: &[[u8;4]], weights: &[[f32;4]]) -> Self;
+    fn new(i: usize, positions: &[[f32;3]], uvs: &[[f32;2]], normals: &[[f32;3]], joints: &[[u16;4]], weights: &[[f32;4]]) -> Self;
 }
\ No newline at end of file
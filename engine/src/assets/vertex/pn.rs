@@ -0,0 +1,17 @@
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32;3],
+    pub normals: [f32;3]
+}
+impl crate::Vertex for Vertex {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        0 => Float32x3, 1 => Float32x3
+    ];
+    fn new(i: usize, positions: &[[f32;3]], _uvs: &[[f32;2]], normals: &[[f32;3]], _joints: &[[u16;4]], _weights: &[[f32;4]]) -> Self {
+        Self {
+            position: positions[i],
+            normals: normals[i]
+        }
+    }
+}
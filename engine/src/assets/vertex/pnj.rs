@@ -10,7 +10,7 @@ impl crate::Vertex for Vertex {
     const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
         0 => Float32x3, 1 => Float32x3, 2 => Uint32x4, 3 => Float32x4
     ];
-    fn new(i: usize, positions: &[[f32;3]], _uvs: &[[f32;2]], normals: &[[f32;3]], joints: &[[u8;4]], weights: &[[f32;4]]) -> Self {
+    fn new(i: usize, positions: &[[f32;3]], _uvs: &[[f32;2]], normals: &[[f32;3]], joints: &[[u16;4]], weights: &[[f32;4]]) -> Self {
         Self {
             position: positions[i],
             normals: normals[i],
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+use wgpu::{Buffer, BufferUsages, Features, util::DrawIndirect};
+
+use crate::{Engine, Shader, Mesh, Material, Model, ModelRenderer};
+
+const ARGS_SIZE: u64 = std::mem::size_of::<DrawIndirect>() as u64;
+
+/// How a `Batcher` issues its draws, picked from the device features
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawPath {
+    /// One `multi_draw_indirect` per batch
+    MultiDrawIndirect,
+    /// One `draw_indirect` per draw
+    Indirect,
+    /// One `draw` per draw, recorded from the arguments kept on the CPU
+    Direct
+}
+impl DrawPath {
+    /// Instanced draws start at their own instance, which indirect arguments only allow with `INDIRECT_FIRST_INSTANCE`
+    pub fn new(features: Features, instanced: bool) -> Self {
+        if instanced && !features.contains(Features::INDIRECT_FIRST_INSTANCE) {
+            Self::Direct
+        } else if features.contains(Features::MULTI_DRAW_INDIRECT) {
+            Self::MultiDrawIndirect
+        } else {
+            Self::Indirect
+        }
+    }
+}
+
+struct Draw<S: Shader> {
+    vertices: Arc<Buffer>,
+    material: Arc<S::Material>,
+    range: Range<u32>,
+    instance: S::Instance
+}
+
+/// Draws sharing a vertex buffer and a material, `draws` indexes the arguments
+struct Batch<S: Shader> {
+    vertices: Arc<Buffer>,
+    material: Arc<S::Material>,
+    draws: Range<u32>
+}
+
+/// Draws of one shader grouped by vertex buffer and material, each draw gets its instance from a buffer shared by
+/// the whole batcher, filled again every frame with `clear`, `push` and `update` before one pass renders it
+pub struct Batcher<S: Shader> {
+    path: DrawPath,
+    draws: Vec<Draw<S>>,
+    batches: Vec<Batch<S>>,
+    args: Vec<DrawIndirect>,
+    /// Instance of each draw, in the order of `args`
+    instances: Vec<S::Instance>,
+    args_buffer: Buffer,
+    args_buffer_capacity: u64,
+    instances_buffer: Buffer,
+    instances_buffer_capacity: u64
+}
+impl Engine {
+    pub fn create_batcher<S: Shader>(&self) -> Batcher<S> {
+        let instanced = std::mem::size_of::<S::Instance>() > 0;
+        Batcher {
+            path: DrawPath::new(self.device.features(), instanced),
+            draws: Vec::new(),
+            batches: Vec::new(),
+            args: Vec::new(),
+            instances: Vec::new(),
+            args_buffer: self.new_buffer(&[], Batcher::<S>::ARGS_USAGE),
+            args_buffer_capacity: 0,
+            instances_buffer: self.new_buffer(&[], Batcher::<S>::INSTANCES_USAGE),
+            instances_buffer_capacity: 0
+        }
+    }
+}
+impl<S: Shader> Batcher<S> {
+    /// Storage usages let compute passes write the draws in place of `update`
+    const ARGS_USAGE: BufferUsages = BufferUsages::INDIRECT.union(BufferUsages::STORAGE).union(BufferUsages::COPY_DST);
+    const INSTANCES_USAGE: BufferUsages = BufferUsages::VERTEX.union(BufferUsages::STORAGE).union(BufferUsages::COPY_DST);
+
+    pub fn path(&self) -> DrawPath {
+        self.path
+    }
+    pub fn clear(&mut self) {
+        self.draws.clear()
+    }
+    /// Draws the `vertices` of `mesh` with `material` and one `instance`
+    pub fn push(&mut self, mesh: &Mesh<S::Vertex>, vertices: Range<u32>, material: &Arc<S::Material>, instance: S::Instance) {
+        self.draws.push(Draw {
+            vertices: mesh.vertices_buffer.clone(),
+            material: material.clone(),
+            range: vertices,
+            instance
+        })
+    }
+    /// Draws uploaded by the last `update`
+    pub fn draw_count(&self) -> usize {
+        self.args.len()
+    }
+    /// Groups of draws sharing their bindings, each one is a single multi draw
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+    /// Range of `args` drawn by each batch
+    pub fn batches(&self) -> Vec<Range<u32>> {
+        self.batches.iter().map(|batch| batch.draws.clone()).collect()
+    }
+    /// Arguments uploaded by the last `update`
+    pub fn args(&self) -> &[DrawIndirect] {
+        &self.args
+    }
+    /// Instances uploaded by the last `update`, one per draw
+    pub fn instances(&self) -> &[S::Instance] {
+        &self.instances
+    }
+    /// Groups the pushed draws and uploads their arguments and instances. Batches follow the first draw pushed with their
+    /// material and vertices, and keep their draws in the order they were pushed
+    pub fn update(&mut self, e: &Engine) {
+        let mut groups = HashMap::new();
+        let mut key = |draw: &Draw<S>| {
+            let len = groups.len();
+            *groups.entry((Arc::as_ptr(&draw.material) as usize, Arc::as_ptr(&draw.vertices) as usize)).or_insert(len)
+        };
+        self.draws.sort_by_cached_key(|draw| key(draw));
+        let instanced = std::mem::size_of::<S::Instance>() > 0;
+        self.batches.clear();
+        self.args.clear();
+        self.instances.clear();
+        for (i, draw) in self.draws.iter().enumerate() {
+            let i = i as u32;
+            match self.batches.last_mut() {
+                Some(batch) if Arc::ptr_eq(&batch.vertices, &draw.vertices) && Arc::ptr_eq(&batch.material, &draw.material) =>
+                    batch.draws.end = i + 1,
+                _ => self.batches.push(Batch {
+                    vertices: draw.vertices.clone(),
+                    material: draw.material.clone(),
+                    draws: i..i + 1
+                })
+            }
+            self.args.push(DrawIndirect {
+                vertex_count: draw.range.len() as u32,
+                instance_count: 1,
+                base_vertex: draw.range.start,
+                base_instance: if instanced { i } else { 0 }
+            });
+            self.instances.push(draw.instance)
+        }
+        let args = self.args.iter().flat_map(|args| args.as_bytes()).copied().collect::<Vec<_>>();
+        write(e, &mut self.args_buffer, &mut self.args_buffer_capacity, &args, Self::ARGS_USAGE);
+        if instanced {
+            let instances = bytemuck::cast_slice(&self.instances);
+            write(e, &mut self.instances_buffer, &mut self.instances_buffer_capacity, instances, Self::INSTANCES_USAGE)
+        }
+    }
+}
+impl<S: ModelRenderer> Batcher<S> {
+    /// Pushes the submeshes of the renderer alpha modes
+    pub fn push_model(&mut self, model: &Model<S::Vertex>, instance: S::Instance) {
+        for submesh in model.submeshes.iter() {
+            let material = &model.materials[submesh.material.unwrap_or_default() as usize];
+            if !S::ALPHA_MODES.contains(&material.alpha_mode) { continue }
+            self.push(&model.mesh, submesh.start..submesh.start + submesh.len, material, instance)
+        }
+    }
+}
+
+/// Grows `buffer` when `contents` do not fit, its size never shrinks
+fn write(e: &Engine, buffer: &mut Buffer, capacity: &mut u64, contents: &[u8], usage: BufferUsages) {
+    if (contents.len() as u64) > *capacity {
+        *buffer = e.new_buffer(contents, usage);
+        *capacity = contents.len() as u64
+    } else if !contents.is_empty() {
+        e.queue.write_buffer(buffer, 0, contents)
+    }
+}
+
+pub trait BatchRenderer: Shader {
+    /// Draws the batches of this shader, or of another one with the same material, vertex and instance like a pre-pass
+    fn render_batches<'r, 's: 'r, S>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, batcher: &'s Batcher<S>)
+    where
+        Self: Sized,
+        S: Shader<Material = Self::Material, Vertex = Self::Vertex, Instance = Self::Instance>
+    {
+        if batcher.batches.is_empty() { return }
+        render_pass.set_pipeline(self.pipeline());
+        if std::mem::size_of::<Self::Instance>() > 0 {
+            render_pass.set_vertex_buffer(1, batcher.instances_buffer.slice(..));
+        }
+        let mut bound: Option<&Batch<S>> = None;
+        for batch in batcher.batches.iter() {
+            if !bound.is_some_and(|v| Arc::ptr_eq(&v.vertices, &batch.vertices)) {
+                render_pass.set_vertex_buffer(0, batch.vertices.slice(..));
+            }
+            if !bound.is_some_and(|v| Arc::ptr_eq(&v.material, &batch.material)) {
+                batch.material.set(render_pass);
+            }
+            bound = Some(batch);
+            match batcher.path {
+                DrawPath::MultiDrawIndirect => render_pass.multi_draw_indirect(
+                    &batcher.args_buffer,
+                    batch.draws.start as u64 * ARGS_SIZE,
+                    batch.draws.len() as u32
+                ),
+                DrawPath::Indirect => for i in batch.draws.clone() {
+                    render_pass.draw_indirect(&batcher.args_buffer, i as u64 * ARGS_SIZE)
+                },
+                DrawPath::Direct => for args in &batcher.args[batch.draws.start as usize..batch.draws.end as usize] {
+                    render_pass.draw(
+                        args.base_vertex..args.base_vertex + args.vertex_count,
+                        args.base_instance..args.base_instance + args.instance_count
+                    )
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use wgpu::{util::DeviceExt, Device};
+use math::{Vec3, Mat4x4, Frustum};
 
-use crate::Engine;
+use crate::{Engine, FogBinding};
 
 #[repr(C)]
 #[derive(Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -9,8 +10,10 @@ pub struct CameraBinding {
     pub position: [f32;4]
 }
 
+/// Group 0 of the scene shaders, the camera followed by the `Fog`
 pub struct CameraBuffer {
     buffer: wgpu::Buffer,
+    pub(crate) fog_buffer: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
     pub bgl: wgpu::BindGroupLayout
 }
@@ -23,20 +26,26 @@ impl CameraBuffer {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
             }
         );
+        let fog_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Fog"),
+                contents: bytemuck::bytes_of(&FogBinding::from(&Default::default())),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
         let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None
-                    },
-                    count: None
-                }
-            ]
+            entries: &[uniform(0), uniform(1)]
         });
         Self {
             bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -46,15 +55,89 @@ impl CameraBuffer {
                     wgpu::BindGroupEntry {
                         binding: 0,
                         resource: buffer.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: fog_buffer.as_entire_binding()
                     }
                 ]
             }),
             buffer,
+            fog_buffer,
             bgl
         }
     }
 }
+/// Camera state kept by the engine, used to fit shadow cascades to what is being seen
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32
+}
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0., 0., 1.),
+            target: Vec3::default(),
+            fov: std::f32::consts::FRAC_PI_2,
+            aspect: 1.,
+            near: 0.01,
+            far: 100.
+        }
+    }
+}
+impl Camera {
+    pub fn forward(&self) -> Vec3 {
+        (self.target - self.position).normalized()
+    }
+    pub fn view(&self) -> Mat4x4 {
+        Mat4x4::look_at(self.position, self.target)
+    }
+    pub fn projection(&self) -> Mat4x4 {
+        Mat4x4::perspective(self.fov, self.aspect, self.near, self.far)
+    }
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.projection() * self.view())
+    }
+    pub fn binding(&self) -> CameraBinding {
+        CameraBinding {
+            matrix: (self.projection() * self.view()).into(),
+            position: self.position.extend(1.).into()
+        }
+    }
+    /// World space corners of the frustum slice between the `near` and `far` view distances
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Vec3;8] {
+        let forward = self.forward();
+        let right = forward.cross(Mat4x4::view_up(forward)).normalized();
+        let up = right.cross(forward);
+        let tan = (self.fov / 2.).tan();
+        let mut corners = [Vec3::default();8];
+        for (i, distance) in [near, far].into_iter().enumerate() {
+            let center = self.position + forward * distance;
+            let h = up * (distance * tan);
+            let w = right * (distance * tan * self.aspect);
+            corners[i * 4] = center - w - h;
+            corners[i * 4 + 1] = center + w - h;
+            corners[i * 4 + 2] = center + w + h;
+            corners[i * 4 + 3] = center - w + h
+        }
+        corners
+    }
+}
+
 impl Engine {
+    pub fn camera(&self) -> Camera {
+        *self.camera.lock().unwrap()
+    }
+    /// Stores the camera state and updates the camera buffer
+    pub fn update_camera(&self, camera: Camera) {
+        self.update_camera_buffer(camera.binding());
+        *self.camera.lock().unwrap() = camera
+    }
     pub fn update_camera_buffer(&self, buffer: CameraBinding) {
         self.queue.write_buffer(&self.camera_buffer.buffer, 0, bytemuck::cast_slice(&[buffer]))
     }
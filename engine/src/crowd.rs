@@ -0,0 +1,130 @@
+use compiler::Skeleton;
+use math::SimpleTransform;
+use wgpu::{Buffer, util::DeviceExt};
+
+use crate::{Engine, Animation, InstanceBinding};
+
+/// Where each clip of an `AnimationBank` starts and how many frames it has
+#[derive(Clone, Debug)]
+pub struct AnimationClips {
+    /// Offset of the first matrix and frames count of each clip
+    clips: Vec<(u32, u32)>,
+    joints: u32
+}
+impl AnimationClips {
+    /// Clips of `animations` and the joint matrices of every one of their frames, in model space
+    pub fn bake(skeleton: &Skeleton, animations: &[Animation]) -> (Self, Vec<[[f32;4];4]>) {
+        let joints = skeleton.joints.len() as u32;
+        let mut clips = Vec::with_capacity(animations.len());
+        let mut matrices: Vec<[[f32;4];4]> = Vec::new();
+        for animation in animations {
+            assert!(
+                animation.frames.first().map(|frame| frame.joints.len()) == Some(skeleton.joints.len()),
+                "Animation does not match the skeleton, use a `Retarget` to map it"
+            );
+            clips.push((matrices.len() as u32, animation.frames.len() as u32));
+            for frame in animation.frames.iter() {
+                matrices.extend(
+                    frame.joints.iter()
+                        .zip(skeleton.joints.iter())
+                        .map(|(joint, skeleton_joint)| <[[f32;4];4]>::from(frame.root * (*joint * skeleton_joint.ibm)))
+                )
+            }
+        }
+        (Self { clips, joints }, matrices)
+    }
+    pub fn len(&self) -> usize {
+        self.clips.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+    pub fn frames(&self, clip: usize) -> u32 {
+        self.clips[clip].1
+    }
+    /// Instance blending the frame of `agent` with the next one, looping at the end of its clip
+    pub fn instance(&self, agent: &CrowdAgent) -> AnimatedInstance {
+        let (offset, frames) = self.clips[agent.clip];
+        let current = agent.time as u32 % frames;
+        let next = (current + 1) % frames;
+        AnimatedInstance {
+            transform: agent.transform.into(),
+            frames: [offset + current * self.joints, offset + next * self.joints],
+            blend: agent.time.fract(),
+            _padding: 0
+        }
+    }
+}
+
+/// Joint matrices of every frame of a set of clips, baked once and sampled per instance,
+/// so any number of characters sharing a mesh are drawn with a single instanced draw call
+pub struct AnimationBank {
+    pub buffer: Buffer,
+    pub clips: AnimationClips
+}
+impl AnimationBank {
+    pub fn new(e: &Engine, skeleton: &Skeleton, animations: &[Animation]) -> Self {
+        let (clips, matrices) = AnimationClips::bake(skeleton, animations);
+        Self {
+            buffer: e.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Animation bank"),
+                contents: bytemuck::cast_slice(&matrices),
+                usage: wgpu::BufferUsages::STORAGE
+            }),
+            clips
+        }
+    }
+    pub fn instance(&self, agent: &CrowdAgent) -> AnimatedInstance {
+        self.clips.instance(agent)
+    }
+}
+
+/// Per character state of an instanced crowd
+#[derive(Clone, Copy, Debug)]
+pub struct CrowdAgent {
+    pub transform: SimpleTransform,
+    pub clip: usize,
+    pub time: f32,
+    pub speed: f32
+}
+impl CrowdAgent {
+    pub fn new(transform: SimpleTransform, clip: usize) -> Self {
+        Self {
+            transform,
+            clip,
+            time: 0.,
+            speed: 30.
+        }
+    }
+    pub fn set_clip(&mut self, clip: usize) {
+        if clip != self.clip {
+            self.clip = clip;
+            self.time = 0.
+        }
+    }
+    /// Advances `time` by `speed` frames per second, looping over the frames of the clip
+    pub fn update(&mut self, clips: &AnimationClips, delta_time: f32) {
+        self.time = (self.time + delta_time * self.speed).rem_euclid(clips.frames(self.clip) as f32)
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AnimatedInstance {
+    pub transform: [[f32;4];4],
+    /// First matrix of the current and next frames inside the `AnimationBank`
+    pub frames: [u32;2],
+    pub blend: f32,
+    pub _padding: u32
+}
+impl AnimatedInstance {
+    pub const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Uint32x2, 9 => Float32
+    ];
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: Self::ATTRIBUTES
+    };
+}
+impl InstanceBinding for AnimatedInstance {}
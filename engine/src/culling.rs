@@ -0,0 +1,101 @@
+use math::{Aabb, Frustum, Vec3};
+
+/// Leaves hold up to this many items
+const BVH_LEAF_SIZE: usize = 4;
+
+enum BvhNode {
+    Leaf { bounds: Aabb, start: usize, len: usize },
+    Inner { bounds: Aabb, left: usize, right: usize }
+}
+impl BvhNode {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            Self::Leaf { bounds, .. } | Self::Inner { bounds, .. } => bounds
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the bounds of scene items, split at the median of the longest axis
+/// and rebuilt when the items move
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// Item indices, each leaf owns a contiguous range
+    items: Vec<usize>,
+    bounds: Vec<Aabb>
+}
+impl Bvh {
+    pub fn new(bounds: Vec<Aabb>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(bounds.len().div_ceil(BVH_LEAF_SIZE) * 2),
+            items: (0..bounds.len()).collect(),
+            bounds
+        };
+        if !bvh.bounds.is_empty() {
+            bvh.build(0, bvh.items.len());
+        }
+        bvh
+    }
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+    pub fn bounds(&self, item: usize) -> &Aabb {
+        &self.bounds[item]
+    }
+    fn build(&mut self, start: usize, len: usize) -> usize {
+        let items = &mut self.items[start..start + len];
+        let bounds = items.iter().fold(Aabb::EMPTY, |aabb, i| aabb.union(self.bounds[*i]));
+        let node = self.nodes.len();
+        if len <= BVH_LEAF_SIZE {
+            self.nodes.push(BvhNode::Leaf { bounds, start, len });
+            return node
+        }
+        let centers = Aabb::from_points(items.iter().map(|i| self.bounds[*i].center()));
+        let extents = centers.max - centers.min;
+        let axis = if extents.x >= extents.y && extents.x >= extents.z { 0 } else if extents.y >= extents.z { 1 } else { 2 };
+        let key = |aabb: &Aabb| { let c = aabb.center(); [c.x, c.y, c.z][axis] };
+        let all = &self.bounds;
+        items.select_nth_unstable_by(len / 2, |a, b| key(&all[*a]).total_cmp(&key(&all[*b])));
+
+        self.nodes.push(BvhNode::Inner { bounds, left: 0, right: 0 });
+        let left = self.build(start, len / 2);
+        let right = self.build(start + len / 2, len - len / 2);
+        self.nodes[node] = BvhNode::Inner { bounds, left, right };
+        node
+    }
+    /// Calls `f` with every item whose bounds pass `test`, subtrees whose bounds fail it are skipped
+    pub fn query(&self, test: impl Fn(&Aabb) -> bool, mut f: impl FnMut(usize)) {
+        if self.nodes.is_empty() { return }
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !test(node.bounds()) { continue }
+            match *node {
+                BvhNode::Leaf { start, len, .. } => for item in &self.items[start..start + len] {
+                    if test(&self.bounds[*item]) { f(*item) }
+                },
+                BvhNode::Inner { left, right, .. } => stack.extend([right, left])
+            }
+        }
+    }
+    /// Closest item hit by the ray from `origin` along `direction` and the distance to its bounds
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<(usize, f32)> {
+        let mut hit: Option<(usize, f32)> = None;
+        self.query(|aabb| aabb.ray(origin, direction).is_some(), |item| {
+            let distance = self.bounds[item].ray(origin, direction).unwrap();
+            if !hit.is_some_and(|(_, v)| v <= distance) {
+                hit = Some((item, distance))
+            }
+        });
+        hit
+    }
+    /// Sorted indices of the items intersecting at least one of `frustums`
+    pub fn cull(&self, frustums: &[Frustum]) -> Vec<usize> {
+        let mut visible = Vec::new();
+        self.query(|aabb| frustums.iter().any(|frustum| frustum.intersects_aabb(aabb)), |item| visible.push(item));
+        visible.sort_unstable();
+        visible
+    }
+}
@@ -0,0 +1,321 @@
+use std::f32::consts::PI;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, TextureView, Sampler};
+use math::Vec3;
+
+use crate::{Engine, IrradianceVolume, utils::ToColor};
+
+pub(crate) const SH_BANDS: [f32;3] = [PI, 2. * PI / 3., PI / 4.];
+/// GGX samples averaged per texel of the prefiltered radiance
+const PREFILTER_SAMPLES: u32 = 32;
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentBinding {
+    /// Irradiance spherical harmonics, divided by pi so they scale the diffuse color directly
+    pub sh: [[f32;4];9],
+    /// Intensity and highest mip level of the radiance texture
+    pub params: [f32;4]
+}
+
+/// Image based lighting from an equirectangular sky, the diffuse part is kept as 9 spherical harmonics and
+/// the specular part as a mip chain prefiltered with rougher GGX lobes at each level
+pub struct Environment {
+    pub sh: [[f32;4];9],
+    pub intensity: f32,
+    pub view: TextureView,
+    pub sampler: Sampler,
+    mips: u32,
+    buffer: Buffer,
+    bgl: BindGroupLayout
+}
+impl Environment {
+    pub fn new(e: &Engine, image: compiler::Image) -> Self {
+        let (width, height) = (image.width, image.height);
+        let pixels = image.get_pixels_rgba()
+            .chunks(4)
+            .map(|v| [srgb_to_linear(v[0]), srgb_to_linear(v[1]), srgb_to_linear(v[2])])
+            .collect::<Vec<_>>();
+        let sh = irradiance_sh(width, height, &pixels);
+
+        let mips = width.min(height).max(1).ilog2() + 1;
+        let texture = e.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Environment"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: mips,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        for (mip, (level, w, h)) in prefilter_radiance(width, height, pixels).into_iter().enumerate() {
+            let bytes = level.iter()
+                .flat_map(|v| [linear_to_srgb(v[0]), linear_to_srgb(v[1]), linear_to_srgb(v[2]), 255])
+                .collect::<Vec<_>>();
+            e.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: mip as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All
+                },
+                &bytes,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * w),
+                    rows_per_image: Some(h)
+                },
+                wgpu::Extent3d { width: w, height: h, depth_or_array_layers: 1 }
+            );
+        }
+
+        let binding = EnvironmentBinding {
+            sh,
+            params: [1., (mips - 1) as f32, 0., 0.]
+        };
+        Self {
+            sh,
+            intensity: 1.,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: e.device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            mips,
+            buffer: e.new_buffer(bytemuck::bytes_of(&binding), BufferUsages::UNIFORM | BufferUsages::COPY_DST),
+            bgl: Self::bgl(&e.device)
+        }
+    }
+    /// Vertical gradient from `ground` below the horizon to `sky` above it
+    pub fn gradient(e: &Engine, sky: impl ToColor, horizon: impl ToColor, ground: impl ToColor) -> Self {
+        let (sky, horizon, ground): ([f32;3], [f32;3], [f32;3]) = (
+            sky.to_color().into(),
+            horizon.to_color().into(),
+            ground.to_color().into()
+        );
+        let (width, height) = (64, 32);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let up = 1. - (y as f32 + 0.5) / height as f32 * 2.;
+            let (to, t) = if up > 0. { (sky, up.sqrt()) } else { (ground, (-up).sqrt()) };
+            let color = (0..3).map(|i| horizon[i] + (to[i] - horizon[i]) * t).collect::<Vec<_>>();
+            for _ in 0..width {
+                pixels.extend([color[0], color[1], color[2]].map(|v| (v.clamp(0., 1.) * 255.).round() as u8));
+                pixels.push(255)
+            }
+        }
+        Self::new(e, compiler::Image {
+            width,
+            height,
+            pixels: compiler::Pixels::ARGB(pixels)
+        })
+    }
+    pub fn set_intensity(&mut self, e: &Engine, intensity: f32) {
+        self.intensity = intensity;
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&EnvironmentBinding {
+            sh: self.sh,
+            params: [intensity, (self.mips - 1) as f32, 0., 0.]
+        }))
+    }
+    /// Irradiance reaching a surface facing `normal`, divided by pi
+    pub fn irradiance(&self, normal: Vec3) -> Vec3 {
+        let basis = sh_basis(normal.normalized());
+        let mut res = Vec3::default();
+        for (c, y) in self.sh.iter().zip(basis) {
+            res += Vec3::new(c[0], c[1], c[2]) * y
+        }
+        res * self.intensity
+    }
+    /// Layout of the group 3 read by `engine/lighting`, the environment followed by the `IrradianceVolume`
+    /// and the ambient occlusion from `Ssao`
+    pub fn bgl(device: &Device) -> BindGroupLayout {
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        let texture = |binding, sample_type, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture { multisampled: false, view_dimension, sample_type },
+            count: None
+        };
+        let sampler = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(ty),
+            count: None
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Environment"),
+            entries: &[
+                uniform(0),
+                texture(1, wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D2),
+                sampler(2, wgpu::SamplerBindingType::Filtering),
+                uniform(3),
+                texture(4, wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D3),
+                texture(5, wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D3),
+                texture(6, wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D3),
+                sampler(7, wgpu::SamplerBindingType::Filtering),
+                texture(8, wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D2)
+            ]
+        })
+    }
+    /// `occlusion` is the texture returned by `Ssao::add_passes`, the bind group is made again every frame with it
+    pub fn bind_group(&self, e: &Engine, probes: &IrradianceVolume, occlusion: &TextureView) -> BindGroup {
+        e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Environment"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: self.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&self.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+                wgpu::BindGroupEntry { binding: 3, resource: probes.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::TextureView(&probes.views[0]) },
+                wgpu::BindGroupEntry { binding: 5, resource: wgpu::BindingResource::TextureView(&probes.views[1]) },
+                wgpu::BindGroupEntry { binding: 6, resource: wgpu::BindingResource::TextureView(&probes.views[2]) },
+                wgpu::BindGroupEntry { binding: 7, resource: wgpu::BindingResource::Sampler(&probes.sampler) },
+                wgpu::BindGroupEntry { binding: 8, resource: wgpu::BindingResource::TextureView(occlusion) }
+            ]
+        })
+    }
+}
+
+/// Projects an equirectangular image of linear colors onto 9 spherical harmonics convolved with a cosine lobe
+pub fn irradiance_sh(width: u32, height: u32, pixels: &[[f32;3]]) -> [[f32;4];9] {
+    let mut sh = [[0.;4];9];
+    for y in 0..height {
+        let theta = (y as f32 + 0.5) / height as f32 * PI;
+        let solid_angle = 2. * PI / width as f32 * PI / height as f32 * theta.sin();
+        for x in 0..width {
+            let phi = (x as f32 + 0.5) / width as f32 * 2. * PI - PI;
+            let direction = Vec3::new(theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+            let color = pixels[(x + y * width) as usize];
+            for (c, basis) in sh.iter_mut().zip(sh_basis(direction)) {
+                for i in 0..3 {
+                    c[i] += color[i] * basis * solid_angle
+                }
+            }
+        }
+    }
+    for (i, c) in sh.iter_mut().enumerate() {
+        let band = SH_BANDS[if i == 0 { 0 } else if i < 4 { 1 } else { 2 }] / PI;
+        for v in c.iter_mut() {
+            *v *= band
+        }
+    }
+    sh
+}
+
+pub(crate) fn sh_basis(d: Vec3) -> [f32;9] {
+    [
+        0.282095,
+        0.488603 * d.y,
+        0.488603 * d.z,
+        0.488603 * d.x,
+        1.092548 * d.x * d.y,
+        1.092548 * d.y * d.z,
+        0.315392 * (3. * d.z * d.z - 1.),
+        1.092548 * d.x * d.z,
+        0.546274 * (d.x * d.x - d.y * d.y)
+    ]
+}
+
+/// Radiance mip chain of an equirectangular image of linear colors, as `(pixels, width, height)` per level. The first level
+/// is the image and the next ones are convolved with the GGX lobe of roughness `level / (levels - 1)` around the direction
+/// of each texel, taking the view and reflection along it as the split sum approximation does
+pub fn prefilter_radiance(width: u32, height: u32, pixels: Vec<[f32;3]>) -> Vec<(Vec<[f32;3]>, u32, u32)> {
+    let mips = width.min(height).max(1).ilog2() + 1;
+    // Box filtered chain the samples read from, the wider a sample's solid angle the blurrier the level it reads
+    let mut sources = vec![(pixels, width, height)];
+    for _ in 1..mips {
+        let (level, w, h) = sources.last().unwrap();
+        sources.push(downsample(level, *w, *h))
+    }
+    let texel_angle = 4. * PI / (width * height) as f32;
+    let fetch = |direction: Vec3, lod: f32| {
+        let (level, w, h) = &sources[(lod.round() as usize).min(sources.len() - 1)];
+        let u = direction.x.atan2(direction.z) / (2. * PI) + 0.5;
+        let v = direction.y.clamp(-1., 1.).acos() / PI;
+        let (x, y) = (((u * *w as f32) as u32).min(w - 1), ((v * *h as f32) as u32).min(h - 1));
+        level[(x + y * w) as usize]
+    };
+
+    let mut res = vec![sources[0].clone()];
+    for mip in 1..mips {
+        let (w, h) = ((width >> mip).max(1), (height >> mip).max(1));
+        let a = (mip as f32 / (mips - 1) as f32).powi(2);
+        let mut level = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            let theta = (y as f32 + 0.5) / h as f32 * PI;
+            for x in 0..w {
+                let phi = (x as f32 + 0.5) / w as f32 * 2. * PI - PI;
+                let n = Vec3::new(theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+                let up = if n.y.abs() < 0.999 { Vec3::new(0., 1., 0.) } else { Vec3::new(1., 0., 0.) };
+                let tangent = up.cross(n).normalized();
+                let bitangent = n.cross(tangent);
+                let (mut sum, mut weight) = (Vec3::default(), 0.);
+                for i in 0..PREFILTER_SAMPLES {
+                    // Hammersley point mapped to a half vector distributed like the GGX lobe
+                    let (u, v) = (i as f32 / PREFILTER_SAMPLES as f32, i.reverse_bits() as f32 / 2f32.powi(32));
+                    let cos = ((1. - v) / (1. + (a * a - 1.) * v)).sqrt();
+                    let sin = (1. - cos * cos).sqrt();
+                    let angle = 2. * PI * u;
+                    let h = tangent * (sin * angle.cos()) + bitangent * (sin * angle.sin()) + n * cos;
+                    let l = h * (2. * n.dot(h)) - n;
+                    let n_l = n.dot(l);
+                    if n_l <= 0. { continue }
+                    // With n = v the pdf of l is the distribution over 4
+                    let d = cos * cos * (a * a - 1.) + 1.;
+                    let pdf = a * a / (PI * d * d) / 4.;
+                    let lod = 0.5 * (1. / (PREFILTER_SAMPLES as f32 * pdf * texel_angle)).log2().max(0.);
+                    let color = fetch(l, lod);
+                    sum += Vec3::new(color[0], color[1], color[2]) * n_l;
+                    weight += n_l
+                }
+                let color = sum / weight.max(0.0001);
+                level.push([color.x, color.y, color.z])
+            }
+        }
+        res.push((level, w, h))
+    }
+    res
+}
+
+fn downsample(pixels: &[[f32;3]], width: u32, height: u32) -> (Vec<[f32;3]>, u32, u32) {
+    let (w, h) = ((width / 2).max(1), (height / 2).max(1));
+    let mut res = Vec::with_capacity((w * h) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0.;3];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let p = pixels[((x * 2 + dx).min(width - 1) + (y * 2 + dy).min(height - 1) * width) as usize];
+                for i in 0..3 {
+                    sum[i] += p[i] / 4.
+                }
+            }
+            res.push(sum)
+        }
+    }
+    (res, w, h)
+}
+
+pub(crate) fn srgb_to_linear(v: u8) -> f32 {
+    let v = v as f32 / 255.;
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+pub(crate) fn linear_to_srgb(v: f32) -> u8 {
+    let v = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1. / 2.4) - 0.055 };
+    (v.clamp(0., 1.) * 255.).round() as u8
+}
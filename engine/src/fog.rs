@@ -0,0 +1,81 @@
+use math::Vec3;
+
+use crate::{Engine, utils::ToColor};
+
+/// Distance and height fog read by every shader including `engine/fog`, at binding 1 of the camera group.
+/// The density falls off exponentially above `height` and the fog brightens towards the directional light
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    pub color: [f32;3],
+    /// Extinction per unit of distance at `height`, 0 disables the fog
+    pub density: f32,
+    pub height: f32,
+    /// Decrease of the density per unit above `height`, 0 keeps it uniform
+    pub height_falloff: f32,
+    /// Distance from the camera left clear
+    pub start: f32,
+    pub max_opacity: f32,
+    /// Light of the directional light scattered towards the camera, added to `color` around its direction
+    pub inscattering: [f32;3],
+    /// Narrowness of the inscattering lobe
+    pub inscattering_exponent: f32,
+    /// Direction the directional light travels to
+    pub light_direction: Vec3
+}
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: [0.5, 0.6, 0.7],
+            density: 0.,
+            height: 0.,
+            height_falloff: 0.2,
+            start: 0.,
+            max_opacity: 1.,
+            inscattering: [0.;3],
+            inscattering_exponent: 8.,
+            light_direction: Vec3::new(0., -1., 0.)
+        }
+    }
+}
+impl Fog {
+    pub fn new(color: impl ToColor, density: f32) -> Self {
+        Self {
+            color: color.to_color().into(),
+            density,
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogBinding {
+    /// Color and density
+    pub color: [f32;4],
+    /// Inscattering color and exponent
+    pub inscattering: [f32;4],
+    /// Light direction and start distance
+    pub direction: [f32;4],
+    /// Height, height falloff and max opacity
+    pub height: [f32;4]
+}
+impl From<&Fog> for FogBinding {
+    fn from(v: &Fog) -> Self {
+        Self {
+            color: [v.color[0], v.color[1], v.color[2], v.density],
+            inscattering: [v.inscattering[0], v.inscattering[1], v.inscattering[2], v.inscattering_exponent],
+            direction: v.light_direction.normalized().extend(v.start).into(),
+            height: [v.height, v.height_falloff, v.max_opacity, 0.]
+        }
+    }
+}
+
+impl Engine {
+    pub fn fog(&self) -> Fog {
+        *self.fog.lock().unwrap()
+    }
+    pub fn set_fog(&self, fog: Fog) {
+        self.queue.write_buffer(&self.camera_buffer.fog_buffer, 0, bytemuck::bytes_of(&FogBinding::from(&fog)));
+        *self.fog.lock().unwrap() = fog
+    }
+}
@@ -0,0 +1,238 @@
+use compiler::Skeleton;
+use math::{SimpleTransform, Vec3, Quaternion};
+
+use crate::AnimationFrame;
+
+const EPSILON: f32 = 0.0001;
+
+#[derive(Clone, Copy, Debug)]
+pub enum ChainSolver {
+    Fabrik,
+    Ccd
+}
+
+/// Analytic solver for limbs (legs and arms), `pole` sets the direction the middle joint bends to
+#[derive(Clone)]
+pub struct TwoBoneIk {
+    pub root: usize,
+    pub mid: usize,
+    pub end: usize,
+    pub target: Vec3,
+    pub pole: Vec3,
+    pub weight: f32
+}
+impl TwoBoneIk {
+    pub fn new(skeleton: &Skeleton, end: usize, target: Vec3, pole: Vec3) -> Self {
+        let parents = &skeleton.joints[end].parents;
+        assert!(parents.len() >= 2, "Joint {end} needs at least two parents");
+        Self {
+            root: parents[1] as usize,
+            mid: parents[0] as usize,
+            end,
+            target,
+            pole,
+            weight: 1.
+        }
+    }
+    fn solve(&self, skeleton: &Skeleton, joints: &mut [SimpleTransform], target: Vec3, pole: Vec3) {
+        let a = joints[self.root].translation;
+        let b = joints[self.mid].translation;
+        let c = joints[self.end].translation;
+
+        let lab = (b - a).length();
+        let lcb = (c - b).length();
+        let lat = (target - a).length().clamp(EPSILON, lab + lcb - EPSILON);
+
+        let ac_ab_0 = angle_between(c - a, b - a);
+        let ba_bc_0 = angle_between(a - b, c - b);
+        let ac_at_0 = angle_between(c - a, target - a);
+
+        let ac_ab_1 = ((lcb * lcb - lab * lab - lat * lat) / (-2. * lab * lat)).clamp(-1., 1.).acos();
+        let ba_bc_1 = ((lat * lat - lab * lab - lcb * lcb) / (-2. * lab * lcb)).clamp(-1., 1.).acos();
+
+        let axis0 = (c - a).cross(pole - a);
+        let axis0 = if axis0.length() < EPSILON { (c - a).cross(b - a) } else { axis0 };
+        if axis0.length() < EPSILON { return }
+        let axis0 = axis0.normalized();
+        let axis1 = (c - a).cross(target - a);
+
+        rotate_joint(skeleton, joints, self.root, Quaternion::from_axis_angle(axis0, ac_ab_1 - ac_ab_0));
+        rotate_joint(skeleton, joints, self.mid, Quaternion::from_axis_angle(axis0, ba_bc_1 - ba_bc_0));
+        if axis1.length() > EPSILON {
+            rotate_joint(skeleton, joints, self.root, Quaternion::from_axis_angle(axis1.normalized(), ac_at_0))
+        }
+    }
+}
+
+/// Rotates `joint` so that its local `forward` axis points to `target`, also used as an aim constraint
+#[derive(Clone)]
+pub struct LookAt {
+    pub joint: usize,
+    pub forward: Vec3,
+    pub target: Vec3,
+    pub max_angle: f32,
+    pub weight: f32
+}
+impl LookAt {
+    pub fn new(joint: usize, forward: Vec3, target: Vec3) -> Self {
+        Self {
+            joint,
+            forward,
+            target,
+            max_angle: std::f32::consts::PI,
+            weight: 1.
+        }
+    }
+    fn solve(&self, skeleton: &Skeleton, joints: &mut [SimpleTransform], target: Vec3) {
+        let joint = joints[self.joint];
+        let to_target = target - joint.translation;
+        if to_target.length() < EPSILON { return }
+        let mut rotation = Quaternion::from_to(joint.rotation * self.forward, to_target);
+        let angle = rotation.angle();
+        if angle > self.max_angle {
+            rotation = Quaternion::default().slerp(rotation, self.max_angle / angle)
+        }
+        rotate_joint(skeleton, joints, self.joint, rotation)
+    }
+}
+
+/// Multi joint chain, `joints` goes from the chain root to the end effector
+#[derive(Clone)]
+pub struct ChainIk {
+    pub joints: Vec<usize>,
+    pub target: Vec3,
+    pub solver: ChainSolver,
+    pub iterations: u32,
+    pub tolerance: f32,
+    pub weight: f32
+}
+impl ChainIk {
+    pub fn new(skeleton: &Skeleton, end: usize, length: usize, target: Vec3, solver: ChainSolver) -> Self {
+        let parents = &skeleton.joints[end].parents;
+        assert!(length >= 2 && parents.len() >= length - 1, "Joint {end} does not have {} parents", length - 1);
+        let mut joints = parents[..length - 1].iter()
+            .rev()
+            .map(|parent| *parent as usize)
+            .collect::<Vec<_>>();
+        joints.push(end);
+        Self {
+            joints,
+            target,
+            solver,
+            iterations: 10,
+            tolerance: 0.001,
+            weight: 1.
+        }
+    }
+    fn end_distance(&self, joints: &[SimpleTransform], target: Vec3) -> f32 {
+        (joints[*self.joints.last().unwrap()].translation - target).length()
+    }
+    fn solve(&self, skeleton: &Skeleton, joints: &mut [SimpleTransform], target: Vec3) {
+        match self.solver {
+            ChainSolver::Fabrik => self.solve_fabrik(skeleton, joints, target),
+            ChainSolver::Ccd => self.solve_ccd(skeleton, joints, target)
+        }
+    }
+    fn solve_fabrik(&self, skeleton: &Skeleton, joints: &mut [SimpleTransform], target: Vec3) {
+        let mut positions = self.joints.iter().map(|j| joints[*j].translation).collect::<Vec<_>>();
+        let lengths = positions.windows(2).map(|v| (v[1] - v[0]).length()).collect::<Vec<_>>();
+        let root = positions[0];
+        let last = positions.len() - 1;
+        for _ in 0..self.iterations {
+            if (positions[last] - target).length() <= self.tolerance { break }
+            positions[last] = target;
+            for i in (0..last).rev() {
+                positions[i] = positions[i + 1] + direction(positions[i] - positions[i + 1]) * lengths[i]
+            }
+            positions[0] = root;
+            for i in 1..=last {
+                positions[i] = positions[i - 1] + direction(positions[i] - positions[i - 1]) * lengths[i - 1]
+            }
+        }
+        for i in 0..last {
+            let joint = joints[self.joints[i]].translation;
+            let child = joints[self.joints[i + 1]].translation;
+            rotate_joint(skeleton, joints, self.joints[i], Quaternion::from_to(child - joint, positions[i + 1] - joint))
+        }
+    }
+    fn solve_ccd(&self, skeleton: &Skeleton, joints: &mut [SimpleTransform], target: Vec3) {
+        let end = *self.joints.last().unwrap();
+        for _ in 0..self.iterations {
+            if self.end_distance(joints, target) <= self.tolerance { break }
+            for joint in self.joints[..self.joints.len() - 1].iter().rev() {
+                let position = joints[*joint].translation;
+                let to_end = joints[end].translation - position;
+                let to_target = target - position;
+                if to_end.length() < EPSILON || to_target.length() < EPSILON { continue }
+                rotate_joint(skeleton, joints, *joint, Quaternion::from_to(to_end, to_target))
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum IkConstraint {
+    TwoBone(TwoBoneIk),
+    LookAt(LookAt),
+    Chain(ChainIk)
+}
+impl IkConstraint {
+    fn weight(&self) -> f32 {
+        match self {
+            Self::TwoBone(v) => v.weight,
+            Self::LookAt(v) => v.weight,
+            Self::Chain(v) => v.weight
+        }
+    }
+    /// Solves the constraint over the model space joints of `frame`,
+    /// targets are in world space and `transform` maps the frame to world space
+    pub fn solve(&self, skeleton: &Skeleton, frame: &mut AnimationFrame, transform: SimpleTransform) {
+        let weight = self.weight().clamp(0., 1.);
+        if weight == 0. { return }
+        let inverse = transform.inverse();
+        let original = if weight < 1. { Some(frame.joints.clone()) } else { None };
+        let joints = &mut frame.joints[..];
+        match self {
+            Self::TwoBone(v) => v.solve(skeleton, joints, inverse * v.target, inverse * v.pole),
+            Self::LookAt(v) => v.solve(skeleton, joints, inverse * v.target),
+            Self::Chain(v) => v.solve(skeleton, joints, inverse * v.target)
+        }
+        if let Some(original) = original {
+            for (joint, original) in joints.iter_mut().zip(original) {
+                let solved = *joint;
+                *joint = original;
+                joint.lerp(solved, weight)
+            }
+        }
+    }
+}
+impl From<TwoBoneIk> for IkConstraint {
+    fn from(v: TwoBoneIk) -> Self { Self::TwoBone(v) }
+}
+impl From<LookAt> for IkConstraint {
+    fn from(v: LookAt) -> Self { Self::LookAt(v) }
+}
+impl From<ChainIk> for IkConstraint {
+    fn from(v: ChainIk) -> Self { Self::Chain(v) }
+}
+
+/// Rotates `joint` around its own position, carrying every child joint along
+fn rotate_joint(skeleton: &Skeleton, joints: &mut [SimpleTransform], joint: usize, rotation: Quaternion) {
+    let pivot = joints[joint].translation;
+    joints[joint].rotation = (rotation * joints[joint].rotation).normalised();
+    for (i, child) in skeleton.joints.iter().enumerate() {
+        if child.parents.contains(&(joint as u16)) {
+            joints[i].translation = pivot + rotation * (joints[i].translation - pivot);
+            joints[i].rotation = (rotation * joints[i].rotation).normalised()
+        }
+    }
+}
+
+fn angle_between(a: Vec3, b: Vec3) -> f32 {
+    direction(a).dot(direction(b)).clamp(-1., 1.).acos()
+}
+
+fn direction(v: Vec3) -> Vec3 {
+    let length = v.length();
+    if length < EPSILON { Vec3::default() } else { v / length }
+}
@@ -1,36 +1,88 @@
-use std::ops::{Index, IndexMut};
+use std::{borrow::Cow, ops::{Index, IndexMut}};
 use bytemuck::Pod;
 use wgpu::util::DeviceExt;
+use math::{Vec3, Aabb, Frustum};
 
-use crate::{Shader, Mesh, Engine, Material};
+use crate::{Shader, Mesh, Engine, Material, TransparentQueue};
 
 pub struct Instances <S: Shader> {
     pub material: S::Material,
     mesh: Mesh<S::Vertex>,
     instances_buffer: wgpu::Buffer,
+    /// Instances drawn, the visible ones at the start of the buffer
     instances_buffer_length: u32,
+    instances_buffer_capacity: u32,
     instances: Vec<S::Instance>,
+    /// Result of the last `cull`, instances pushed since are visible
+    visible: Option<Vec<bool>>,
     needs_update: bool
 }
 impl<S: Shader> Instances<S> {
+    /// Uploads the visible instances, the buffer only grows so culling does not reallocate it every frame
     pub fn update(&mut self, e: &Engine) {
         if !self.needs_update { return }
-        if self.instances_buffer_length != self.instances.len() as u32 {
+        let instances = match &self.visible {
+            Some(visible) => Cow::Owned(self.instances.iter()
+                .zip(visible.iter().copied().chain(std::iter::repeat(true)))
+                .filter(|(_, visible)| *visible)
+                .map(|(instance, _)| *instance)
+                .collect::<Vec<_>>()),
+            None => Cow::Borrowed(&self.instances[..])
+        };
+        if self.instances_buffer_capacity < instances.len() as u32 {
             self.instances_buffer = e.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: None,
-                contents: bytemuck::cast_slice(&self.instances),
+                contents: bytemuck::cast_slice(&instances),
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
-            })
-        } else {
-            e.queue.write_buffer(&self.instances_buffer, 0, &bytemuck::cast_slice(&self.instances))
+            });
+            self.instances_buffer_capacity = instances.len() as u32
+        } else if !instances.is_empty() {
+            e.queue.write_buffer(&self.instances_buffer, 0, bytemuck::cast_slice(&instances))
         }
-        self.instances_buffer_length = self.instances.len() as u32;
+        self.instances_buffer_length = instances.len() as u32;
         self.needs_update = false
     }
+    /// Keeps the instances whose `bounds` intersect one of `frustums`, they are compacted by the next `update`
+    pub fn cull(&mut self, frustums: &[Frustum], bounds: impl Fn(&S::Instance) -> Aabb) {
+        let visible = self.instances.iter()
+            .map(|instance| {
+                let bounds = bounds(instance);
+                frustums.iter().any(|frustum| frustum.intersects_aabb(&bounds))
+            })
+            .collect::<Vec<_>>();
+        if self.visible.as_ref() != Some(&visible) {
+            self.visible = Some(visible);
+            self.needs_update = true
+        }
+    }
+    /// Draws every instance again after `cull`
+    pub fn uncull(&mut self) {
+        if self.visible.take().is_some() {
+            self.needs_update = true
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+    /// Instances drawn since the last `update`
+    pub fn drawn(&self) -> u32 {
+        self.instances_buffer_length
+    }
+    pub fn mesh(&self) -> &Mesh<S::Vertex> {
+        &self.mesh
+    }
     pub fn push(&mut self, instance: S::Instance) {
         self.needs_update = true;
         self.instances.push(instance)
     }
+    /// Orders the instances back to front from the queue camera, for transparent instances that overlap each other
+    pub fn sort_back_to_front(&mut self, queue: &TransparentQueue, position: impl Fn(&S::Instance) -> Vec3) {
+        self.needs_update = true;
+        self.instances.sort_by(|a, b| queue.depth(position(b)).total_cmp(&queue.depth(position(a))))
+    }
 }
 impl Engine {
     pub fn create_instances<S: Shader>(&'static self, mesh: Mesh<S::Vertex>, material: S::Material, instances: Option<Vec<S::Instance>>) -> Instances<S> {
@@ -43,8 +95,10 @@ impl Engine {
                     contents: bytemuck::cast_slice(&instances),
                     usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
                 }),
-                instances_buffer_length: instances.len()as u32,
+                instances_buffer_length: instances.len() as u32,
+                instances_buffer_capacity: instances.len() as u32,
                 instances,
+                visible: None,
                 needs_update: false
             },
             None => Instances {
@@ -57,7 +111,9 @@ impl Engine {
                     usage: wgpu::BufferUsages::VERTEX
                 }),
                 instances_buffer_length: 0,
+                instances_buffer_capacity: 0,
                 instances: vec![],
+                visible: None,
                 needs_update: false
             }
         }
@@ -81,7 +137,12 @@ pub trait InstanceBinding: Pod {}
 impl InstanceBinding for () {}
 
 pub trait InstancesRenderer: Shader {
-    fn render_instances<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, instances: &'s Instances<Self>) where Self: Sized {
+    /// Draws instances of this shader, or of another one with the same material, vertex and instance like a pre-pass
+    fn render_instances<'r, 's: 'r, S>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, instances: &'s Instances<S>)
+    where
+        Self: Sized,
+        S: Shader<Material = Self::Material, Vertex = Self::Vertex, Instance = Self::Instance>
+    {
         if instances.instances_buffer_length == 0 { return }
         render_pass.set_pipeline(self.pipeline());
         render_pass.set_vertex_buffer(0, instances.mesh.vertices_buffer.slice(..));
@@ -89,4 +150,8 @@ pub trait InstancesRenderer: Shader {
         instances.material.set(render_pass);
         render_pass.draw(0..instances.mesh.vertices_len, 0..instances.instances_buffer_length);
     }
+    /// Draws the instances with the transparent surfaces, sorted by the view depth of `position`
+    fn queue_instances<'r, 's: 'r>(&'s self, queue: &mut TransparentQueue<'r>, instances: &'s Instances<Self>, position: Vec3) where Self: Sized {
+        queue.push(position, move |render_pass| self.render_instances(render_pass, instances))
+    }
 }
\ No newline at end of file
@@ -16,6 +16,28 @@ mod time;       pub use time::*;
 mod instances;  pub use instances::*;
 mod shader;     pub use shader::*;
 mod animator;   pub use animator::*;
+mod skinning;   pub use skinning::*;
 mod light;      pub use light::*;
+mod lights;     pub use lights::*;
+mod ik;         pub use ik::*;
+mod retarget;   pub use retarget::*;
+mod crowd;      pub use crowd::*;
+mod pbr;        pub use pbr::*;
+mod model;      pub use model::*;
+mod environment;    pub use environment::*;
+mod render_graph;   pub use render_graph::*;
+mod post;           pub use post::*;
+mod transparency;   pub use transparency::*;
+mod culling;        pub use culling::*;
+mod batch;          pub use batch::*;
+mod adapter;        pub use adapter::*;
+mod settings;       pub use settings::*;
+mod resolution;     pub use resolution::*;
+mod fog;            pub use fog::*;
+mod sky;            pub use sky::*;
+mod time_of_day;    pub use time_of_day::*;
+mod ssao;           pub use ssao::*;
+mod probes;         pub use probes::*;
+mod terrain;        pub use terrain::*;
 
 pub mod utils;
\ No newline at end of file
@@ -1,41 +1,235 @@
-use wgpu::{Buffer, BufferUsages, TextureUsages};
-use math::{Vec3, Quaternion, Mat4x4};
+use wgpu::{Buffer, BufferUsages, TextureUsages, BindGroup, BindGroupLayout, TextureView, Device, CommandEncoder, RenderPass};
+use math::{Vec3, Vec4, Quaternion, Mat4x4, Frustum};
 
-use crate::{Engine, DepthTexture};
+use crate::{Engine, DepthTexture, utils::new_render_pass};
+
+pub const MAX_CASCADES: usize = 4;
+
+/// Maps the OpenGL clip depth range [-1, 1] used by `Mat4x4::orthographic` to wgpu's [0, 1]
+const OPENGL_TO_WGPU: Mat4x4 = Mat4x4::new(
+    Vec4::new(1., 0., 0., 0.),
+    Vec4::new(0., 1., 0., 0.),
+    Vec4::new(0., 0., 0.5, 0.),
+    Vec4::new(0., 0., 0.5, 1.)
+);
 
 #[repr(C)]
-#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightBinding {
-    pub perspective: [[f32;4];4],
-    pub direction: [f32;4]
+    pub cascades: [[[f32;4];4];MAX_CASCADES],
+    /// Bounding sphere of each cascade, center and radius
+    pub spheres: [[f32;4];MAX_CASCADES],
+    pub direction: [f32;4],
+    pub cascades_len: u32,
+    pub blend: f32,
+    pub filter: u32,
+    pub kernel: u32,
+    pub light_size: f32,
+    pub _padding: [u32;3],
+    /// Color times intensity
+    pub color: [f32;4]
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ShadowFilter {
+    /// Single comparison sample
+    Hard,
+    /// Square kernel of `2 * n + 1` samples per side
+    Pcf(u32),
+    /// Percentage closer soft shadows, `light_size` is the blocker search radius in texels
+    Pcss { kernel: u32, light_size: f32 }
+}
+impl Default for ShadowFilter {
+    fn default() -> Self {
+        Self::Pcf(1)
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CascadeBinding {
+    pub perspective: [[f32;4];4]
+}
+
+pub struct Cascade {
+    pub perspective: Mat4x4,
+    pub center: Vec3,
+    pub radius: f32,
+    pub view: TextureView,
+    pub buffer: Buffer,
+    pub bind_group: BindGroup
+}
+impl Cascade {
+    /// Volume of the shadow casters rendered into the cascade
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.perspective)
+    }
 }
 
-pub struct Light {
+/// Sun like light, its shadow map is split in cascades fitted to slices of the camera frustum
+pub struct DirectionalLight {
     pub direction: Quaternion,
+    pub color: [f32;3],
+    /// 1 gives a white lambertian surface facing the light a radiance of 1
+    pub intensity: f32,
     pub buffer: Buffer,
-    pub depth_texture: DepthTexture
+    pub depth_texture: DepthTexture,
+    pub cascades: Vec<Cascade>,
+    /// Farthest distance from the camera that receives shadows
+    pub distance: f32,
+    /// Mix between uniform (0) and logarithmic (1) cascade splits
+    pub split_lambda: f32,
+    /// Fraction of each cascade blended with the next one
+    pub blend: f32,
+    /// Distance behind each cascade where shadow casters are still rendered
+    pub caster_distance: f32,
+    pub filter: ShadowFilter,
+    resolution: u32
 }
-impl Light {
+impl DirectionalLight {
+    /// `direction` rotates the +Z axis to the direction the light travels to
     pub fn new(
         e: &'static Engine,
         direction: Quaternion,
-        (width, height): (u32, u32)
+        resolution: u32,
+        cascades: usize
     ) -> Self {
-        let s = 10.;
-        let dir = direction * Vec3::new(0., 0., 1.);
-        let proj = Mat4x4::orthographic(-s, s, -s, s, -s, s);
-        let view = Mat4x4::look_at([1.;3].into(), [0.;3].into());
-        let perspective = proj * view;
-        Self {
+        assert!(cascades > 0 && cascades <= MAX_CASCADES, "A directional light has 1 to {MAX_CASCADES} cascades");
+        let depth_texture = DepthTexture::new_array(
+            &e.device,
+            resolution,
+            resolution,
+            cascades as u32,
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING
+        );
+        let bgl = Self::cascade_bgl(&e.device);
+        let cascades = (0..cascades)
+            .map(|i| {
+                let buffer = e.new_buffer(
+                    bytemuck::bytes_of(&CascadeBinding::default()),
+                    BufferUsages::UNIFORM | BufferUsages::COPY_DST
+                );
+                Cascade {
+                    perspective: Mat4x4::IDENTITY,
+                    center: Vec3::default(),
+                    radius: 0.,
+                    view: depth_texture.layer_view(i as u32),
+                    bind_group: e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Shadow cascade"),
+                        layout: &bgl,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding()
+                            }
+                        ]
+                    }),
+                    buffer
+                }
+            })
+            .collect();
+        let mut s = Self {
             direction,
+            color: [1.;3],
+            intensity: 1.,
             buffer: e.new_buffer(
-                bytemuck::bytes_of(&LightBinding {
-                    perspective: perspective.into(),
-                    direction: dir.extend(1.).into()
-                }),
-                BufferUsages::UNIFORM 
+                bytemuck::bytes_of(&LightBinding::default()),
+                BufferUsages::UNIFORM | BufferUsages::COPY_DST
             ),
-            depth_texture: DepthTexture::new(&e.device, width, height, TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
+            depth_texture,
+            cascades,
+            distance: 50.,
+            split_lambda: 0.75,
+            blend: 0.1,
+            caster_distance: 20.,
+            filter: Default::default(),
+            resolution
+        };
+        s.update(e);
+        s
+    }
+    /// Layout of the bind group set at index 0 by `cascade_pass`, shadow shaders read the cascade matrix from it
+    pub fn cascade_bgl(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow cascade"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                }
+            ]
+        })
+    }
+    pub fn world_direction(&self) -> Vec3 {
+        (self.direction * Vec3::new(0., 0., 1.)).normalized()
+    }
+    /// Fits the cascades to the current `Engine::camera`
+    pub fn update(&mut self, e: &Engine) {
+        let camera = e.camera();
+        let direction = self.world_direction();
+        let view = Mat4x4::look_to(Vec3::default(), direction);
+        let far = self.distance.min(camera.far);
+        let len = self.cascades.len();
+        let (filter, kernel, light_size) = match self.filter {
+            ShadowFilter::Hard => (0, 0, 0.),
+            ShadowFilter::Pcf(kernel) => (1, kernel, 0.),
+            ShadowFilter::Pcss { kernel, light_size } => (2, kernel, light_size)
+        };
+        let [r, g, b] = self.color.map(|v| v * self.intensity);
+        let mut binding = LightBinding {
+            direction: direction.extend(0.).into(),
+            cascades_len: len as u32,
+            blend: self.blend,
+            filter,
+            kernel,
+            light_size,
+            color: [r, g, b, 0.],
+            ..Default::default()
+        };
+        let mut near = camera.near;
+        for (i, cascade) in self.cascades.iter_mut().enumerate() {
+            let p = (i + 1) as f32 / len as f32;
+            let split = self.split_lambda * camera.near * (far / camera.near).powf(p) +
+                (1. - self.split_lambda) * (camera.near + (far - camera.near) * p);
+            let corners = camera.frustum_corners(near, split);
+            near = split;
+
+            let center = corners.iter().fold(Vec3::default(), |a, b| a + *b) / 8.;
+            let radius = corners.iter().map(|corner| (*corner - center).length()).fold(0., f32::max);
+
+            // Snapping the center to shadow map texels keeps shadow edges from shimmering while the camera moves
+            let texel = radius * 2. / self.resolution as f32;
+            let light_center = view * center.extend(1.);
+            let x = (light_center.x / texel).floor() * texel;
+            let y = (light_center.y / texel).floor() * texel;
+            let projection = Mat4x4::orthographic(
+                x - radius, x + radius,
+                y - radius, y + radius,
+                -light_center.z - radius - self.caster_distance, -light_center.z + radius
+            );
+
+            cascade.perspective = OPENGL_TO_WGPU * projection * view;
+            cascade.center = center;
+            cascade.radius = radius;
+            binding.cascades[i] = cascade.perspective.into();
+            binding.spheres[i] = center.extend(radius).into();
+            e.queue.write_buffer(&cascade.buffer, 0, bytemuck::bytes_of(&CascadeBinding {
+                perspective: cascade.perspective.into()
+            }))
         }
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&binding))
     }
-}
\ No newline at end of file
+    /// Depth only render pass over one cascade, with its bind group set at index 0
+    pub fn cascade_pass<'s>(&'s self, encoder: &'s mut CommandEncoder, cascade: usize) -> RenderPass<'s> {
+        let cascade = &self.cascades[cascade];
+        let mut render_pass = new_render_pass(encoder, None, Some(&cascade.view));
+        render_pass.set_bind_group(0, &cascade.bind_group, &[]);
+        render_pass
+    }
+}
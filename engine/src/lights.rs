@@ -0,0 +1,314 @@
+use wgpu::{Buffer, BufferUsages, BindGroup, BindGroupLayout, Device};
+use math::Vec3;
+
+use crate::{Engine, Camera, DirectionalLight, utils::ToColor};
+
+pub const CLUSTERS_X: u32 = 16;
+pub const CLUSTERS_Y: u32 = 9;
+pub const CLUSTERS_Z: u32 = 24;
+pub const CLUSTERS: u32 = CLUSTERS_X * CLUSTERS_Y * CLUSTERS_Z;
+pub const MAX_LIGHTS: u32 = 1024;
+pub const MAX_CLUSTER_INDICES: u32 = 1 << 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: [f32;3],
+    pub intensity: f32,
+    pub range: f32
+}
+impl PointLight {
+    pub fn new(position: Vec3, color: impl ToColor, intensity: f32, range: f32) -> Self {
+        Self {
+            position,
+            color: color.to_color().into(),
+            intensity,
+            range
+        }
+    }
+}
+
+/// Light cone, fully lit inside `inner_angle` and fading until `outer_angle`, both from the cone axis
+#[derive(Clone, Copy, Debug)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: [f32;3],
+    pub intensity: f32,
+    pub range: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32
+}
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, color: impl ToColor, intensity: f32, range: f32, outer_angle: f32) -> Self {
+        Self {
+            position,
+            direction: direction.normalized(),
+            color: color.to_color().into(),
+            intensity,
+            range,
+            inner_angle: outer_angle * 0.8,
+            outer_angle
+        }
+    }
+}
+
+/// Point or spot light of `LightClusters`, as opposed to the shadowed `DirectionalLight`
+#[derive(Clone, Copy, Debug)]
+pub enum PunctualLight {
+    Point(PointLight),
+    Spot(SpotLight)
+}
+impl PunctualLight {
+    pub fn position(&self) -> Vec3 {
+        match self {
+            Self::Point(v) => v.position,
+            Self::Spot(v) => v.position
+        }
+    }
+    pub fn range(&self) -> f32 {
+        match self {
+            Self::Point(v) => v.range,
+            Self::Spot(v) => v.range
+        }
+    }
+    fn binding(&self) -> ClusterLightBinding {
+        match self {
+            // A cone wider than the sphere lights every direction
+            Self::Point(v) => ClusterLightBinding {
+                position: v.position.extend(v.range).into(),
+                color: [v.color[0], v.color[1], v.color[2], v.intensity],
+                direction: [0., 0., 1., -2.],
+                cone: [-1., 0., 0., 0.]
+            },
+            Self::Spot(v) => ClusterLightBinding {
+                position: v.position.extend(v.range).into(),
+                color: [v.color[0], v.color[1], v.color[2], v.intensity],
+                direction: v.direction.extend(v.outer_angle.cos()).into(),
+                cone: [v.inner_angle.cos(), 0., 0., 0.]
+            }
+        }
+    }
+}
+impl From<PointLight> for PunctualLight {
+    fn from(v: PointLight) -> Self { Self::Point(v) }
+}
+impl From<SpotLight> for PunctualLight {
+    fn from(v: SpotLight) -> Self { Self::Spot(v) }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ClusterLightBinding {
+    /// Position and range
+    pub position: [f32;4],
+    /// Color and intensity
+    pub color: [f32;4],
+    /// Cone axis and cosine of the outer angle
+    pub direction: [f32;4],
+    /// Cosine of the inner angle
+    pub cone: [f32;4]
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ClustersBinding {
+    pub view: [[f32;4];4],
+    /// Clusters per axis and lights count
+    pub grid: [u32;4],
+    /// Camera near, far, tangent of half the fov and aspect
+    pub camera: [f32;4]
+}
+
+/// Lights of each cluster, `ranges` holds the offset and count of every cluster inside `indices`
+#[derive(Default, Clone, Debug)]
+pub struct ClusterList {
+    pub ranges: Vec<[u32;2]>,
+    pub indices: Vec<u32>
+}
+impl ClusterList {
+    pub fn cluster(x: u32, y: u32, z: u32) -> usize {
+        (x + y * CLUSTERS_X + z * CLUSTERS_X * CLUSTERS_Y) as usize
+    }
+    pub fn lights(&self, cluster: usize) -> &[u32] {
+        let [offset, count] = self.ranges[cluster];
+        &self.indices[offset as usize..(offset + count) as usize]
+    }
+    /// Assigns each light to every cluster its bounding sphere may touch,
+    /// clusters split the screen in tiles and the view depth in exponential slices
+    pub fn build(camera: &Camera, lights: &[PunctualLight]) -> Self {
+        let view = camera.view();
+        let tan = (camera.fov / 2.).tan();
+        let slice = |depth: f32| ((depth / camera.near).ln() / (camera.far / camera.near).ln() * CLUSTERS_Z as f32)
+            .clamp(0., CLUSTERS_Z as f32 - 1.) as u32;
+        let tile = |ndc: f32, tiles: u32| (((ndc + 1.) / 2. * tiles as f32).floor().clamp(0., tiles as f32 - 1.)) as u32;
+        // Smallest and largest normalized device coordinate of [lo, hi] between depths [near, far]
+        let bounds = |lo: f32, hi: f32, near: f32, far: f32, scale: f32| (
+            lo / (if lo < 0. { near } else { far } * scale),
+            hi / (if hi > 0. { near } else { far } * scale)
+        );
+
+        // Clusters touched by each light, as inclusive ranges of tiles and slices
+        let boxes = lights.iter().enumerate().take(MAX_LIGHTS as usize).filter_map(|(i, light)| {
+            let v = view * light.position().extend(1.);
+            let (depth, r) = (-v.z, light.range());
+            if depth + r < camera.near || depth - r > camera.far { return None }
+            let (z0, z1) = (slice((depth - r).max(camera.near)), slice((depth + r).min(camera.far)));
+            let ((x0, x1), (y0, y1)) = if depth - r <= camera.near {
+                ((0, CLUSTERS_X - 1), (0, CLUSTERS_Y - 1))
+            } else {
+                let (near, far) = (depth - r, depth + r);
+                let (lx, hx) = bounds(v.x - r, v.x + r, near, far, tan * camera.aspect);
+                let (ly, hy) = bounds(v.y - r, v.y + r, near, far, tan);
+                if lx > 1. || hx < -1. || ly > 1. || hy < -1. { return None }
+                ((tile(lx, CLUSTERS_X), tile(hx, CLUSTERS_X)), (tile(ly, CLUSTERS_Y), tile(hy, CLUSTERS_Y)))
+            };
+            Some((i as u32, [x0, x1, y0, y1, z0, z1]))
+        }).collect::<Vec<_>>();
+        let clusters = |[x0, x1, y0, y1, z0, z1]: [u32;6]| (z0..=z1)
+            .flat_map(move |z| (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| Self::cluster(x, y, z))));
+
+        // Counts the lights of each cluster, then gives each cluster its offset and fills them in light order
+        let mut res = Self {
+            ranges: vec![[0, 0]; CLUSTERS as usize],
+            indices: Vec::new()
+        };
+        for (_, bounds) in boxes.iter() {
+            for cluster in clusters(*bounds) {
+                res.ranges[cluster][1] += 1
+            }
+        }
+        let mut offset = 0;
+        for range in res.ranges.iter_mut() {
+            let count = range[1].min(MAX_CLUSTER_INDICES - offset);
+            if count < range[1] {
+                warn!("Cluster light indices are full, {} lights dropped", range[1] - count)
+            }
+            *range = [offset, count];
+            offset += count
+        }
+        res.indices.resize(offset as usize, 0);
+        let mut filled = vec![0; CLUSTERS as usize];
+        for (light, bounds) in boxes {
+            for cluster in clusters(bounds) {
+                let [offset, count] = res.ranges[cluster];
+                if filled[cluster] < count {
+                    res.indices[(offset + filled[cluster]) as usize] = light;
+                    filled[cluster] += 1
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Point and spot lights culled per view cluster, bound at group 2 by the shaders including `engine/lights`.
+/// The group also holds the directional light and its shadows read by `engine/lighting`
+pub struct LightClusters {
+    pub lights: Vec<PunctualLight>,
+    pub bind_group: BindGroup,
+    buffer: Buffer,
+    lights_buffer: Buffer,
+    ranges_buffer: Buffer,
+    indices_buffer: Buffer
+}
+impl LightClusters {
+    /// `sun` lives as long as the clusters, both are created again when the shadow map changes
+    pub fn new(e: &Engine, sun: &DirectionalLight) -> Self {
+        let storage = |size: u32| e.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light clusters"),
+            size: size as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let buffer = e.new_buffer(bytemuck::bytes_of(&ClustersBinding::default()), BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        let lights_buffer = storage(MAX_LIGHTS * std::mem::size_of::<ClusterLightBinding>() as u32);
+        let ranges_buffer = storage(CLUSTERS * 8);
+        let indices_buffer = storage(MAX_CLUSTER_INDICES * 4);
+        Self {
+            lights: Vec::new(),
+            bind_group: e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Light clusters"),
+                layout: &Self::bgl(&e.device),
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: lights_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 2, resource: ranges_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 3, resource: indices_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 4, resource: sun.buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 5, resource: wgpu::BindingResource::TextureView(&sun.depth_texture.view) },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: wgpu::BindingResource::Sampler(&sun.depth_texture.comparison_sampler)
+                    }
+                ]
+            }),
+            buffer,
+            lights_buffer,
+            ranges_buffer,
+            indices_buffer
+        }
+    }
+    pub fn bgl(device: &Device) -> BindGroupLayout {
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Light clusters"),
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(2, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(3, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(4, wgpu::BufferBindingType::Uniform),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None
+                }
+            ]
+        })
+    }
+    pub fn push(&mut self, light: impl Into<PunctualLight>) {
+        self.lights.push(light.into())
+    }
+    /// Culls the lights against the current `Engine::camera` and uploads the cluster lists
+    pub fn update(&self, e: &Engine) {
+        let camera = e.camera();
+        let list = ClusterList::build(&camera, &self.lights);
+        let lights = self.lights.iter()
+            .take(MAX_LIGHTS as usize)
+            .map(|light| light.binding())
+            .collect::<Vec<_>>();
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&ClustersBinding {
+            view: camera.view().into(),
+            grid: [CLUSTERS_X, CLUSTERS_Y, CLUSTERS_Z, lights.len() as u32],
+            camera: [camera.near, camera.far, (camera.fov / 2.).tan(), camera.aspect]
+        }));
+        if !lights.is_empty() {
+            e.queue.write_buffer(&self.lights_buffer, 0, bytemuck::cast_slice(&lights))
+        }
+        e.queue.write_buffer(&self.ranges_buffer, 0, bytemuck::cast_slice(&list.ranges));
+        if !list.indices.is_empty() {
+            e.queue.write_buffer(&self.indices_buffer, 0, bytemuck::cast_slice(&list.indices))
+        }
+    }
+}
@@ -142,4 +142,11 @@ fn pretty_backtrace() -> String {
         })
         .collect::<Vec<_>>()
         .join("\n")
-}
\ No newline at end of file
+}
+
+/// Logs `msg` as an error, which also writes it to the log file and shows it in a dialog, and exits,
+/// for failures the user has to fix rather than bugs
+pub fn fatal(msg: &str) -> ! {
+    error!("{msg}");
+    std::process::exit(1)
+}
@@ -0,0 +1,81 @@
+use std::{path::Path, sync::Arc};
+use compiler::Submesh;
+use math::Vec3;
+
+use crate::{Engine, Mesh, Vertex, PbrMaterial, Shader, Material, AlphaMode, TransparentQueue, decode};
+
+/// Mesh drawn in parts, each one with its own glTF material
+#[derive(Clone)]
+pub struct Model<V: Vertex> {
+    pub mesh: Mesh<V>,
+    /// Submeshes without a material point to a default one appended to `materials`
+    pub submeshes: Vec<Submesh>,
+    /// Center of each submesh, transparent submeshes are sorted by its view depth
+    pub centers: Vec<Vec3>,
+    pub materials: Vec<Arc<PbrMaterial>>
+}
+impl Engine {
+    pub fn load_model<V: Vertex>(&self, path: impl AsRef<Path>) -> Model<V> {
+        self.create_model(decode(&path))
+    }
+    pub fn create_model<V: Vertex>(&self, mut mesh: compiler::Mesh) -> Model<V> {
+        let mut materials = std::mem::take(&mut mesh.materials)
+            .into_iter()
+            .map(|material| PbrMaterial::new(self, material).into())
+            .collect::<Vec<_>>();
+        let default = materials.len() as u32;
+        let mut submeshes = std::mem::take(&mut mesh.submeshes);
+        for submesh in submeshes.iter_mut() {
+            submesh.material = Some(submesh.material.unwrap_or(default))
+        }
+        if submeshes.iter().any(|v| v.material == Some(default)) {
+            materials.push(PbrMaterial::new(self, Default::default()).into())
+        }
+        let centers = submeshes.iter()
+            .map(|submesh| {
+                let indices = &mesh.indices[submesh.start as usize..(submesh.start + submesh.len) as usize];
+                let sum = indices.iter().fold(Vec3::default(), |sum, i| sum + Vec3::from(mesh.positions[*i as usize]));
+                sum / indices.len().max(1) as f32
+            })
+            .collect();
+        Model {
+            mesh: self.create_mesh(&mesh),
+            submeshes,
+            centers,
+            materials
+        }
+    }
+}
+
+pub trait ModelRenderer: Shader<Material = PbrMaterial> {
+    /// Submeshes whose material has another alpha mode are skipped
+    const ALPHA_MODES: &'static [AlphaMode] = &[AlphaMode::Opaque];
+    fn render_model<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, model: &'s Model<Self::Vertex>) where Self: Sized {
+        let mut submeshes = (0..model.submeshes.len())
+            .filter(|i| Self::ALPHA_MODES.contains(&model.materials[model.submeshes[*i].material.unwrap_or_default() as usize].alpha_mode))
+            .peekable();
+        if submeshes.peek().is_none() { return }
+        render_pass.set_pipeline(self.pipeline());
+        render_pass.set_vertex_buffer(0, model.mesh.vertices_buffer.slice(..));
+        for i in submeshes {
+            self.render_submesh(render_pass, model, i)
+        }
+    }
+    /// Draws a submesh with the pipeline and vertex buffer already set
+    fn render_submesh<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, model: &'s Model<Self::Vertex>, i: usize) where Self: Sized {
+        let submesh = &model.submeshes[i];
+        model.materials[submesh.material.unwrap_or_default() as usize].set(render_pass);
+        render_pass.draw(submesh.start..submesh.start + submesh.len, 0..1);
+    }
+    /// Queues each submesh of the renderer alpha modes on its own, sorted by its center
+    fn queue_model<'r, 's: 'r>(&'s self, queue: &mut TransparentQueue<'r>, model: &'s Model<Self::Vertex>) where Self: Sized {
+        for (i, submesh) in model.submeshes.iter().enumerate() {
+            if !Self::ALPHA_MODES.contains(&model.materials[submesh.material.unwrap_or_default() as usize].alpha_mode) { continue }
+            queue.push(model.centers[i], move |render_pass| {
+                render_pass.set_pipeline(self.pipeline());
+                render_pass.set_vertex_buffer(0, model.mesh.vertices_buffer.slice(..));
+                self.render_submesh(render_pass, model, i)
+            })
+        }
+    }
+}
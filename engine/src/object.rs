@@ -1,4 +1,6 @@
-use crate::{Mesh, Engine, Shader, Material};
+use math::Vec3;
+
+use crate::{Mesh, Engine, Shader, Material, TransparentQueue};
 
 pub struct Object<S: Shader> {
     pub material: S::Material,
@@ -14,10 +16,19 @@ impl Engine {
 }
 
 pub trait ObjectRenderer: Shader {
-    fn render_object<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, object: &'s Object<Self>) where Self: Sized {
+    /// Draws an object of this shader, or of another one with the same material and vertex like a pre-pass
+    fn render_object<'r, 's: 'r, S>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, object: &'s Object<S>)
+    where
+        Self: Sized,
+        S: Shader<Material = Self::Material, Vertex = Self::Vertex>
+    {
         render_pass.set_pipeline(self.pipeline());
         render_pass.set_vertex_buffer(0, object.mesh.vertices_buffer.slice(..));
         object.material.set(render_pass);
         render_pass.draw(0..object.mesh.vertices_len, 0..1);
     }
+    /// Draws the object with the transparent surfaces, sorted by the view depth of `position`
+    fn queue_object<'r, 's: 'r>(&'s self, queue: &mut TransparentQueue<'r>, object: &'s Object<Self>, position: Vec3) where Self: Sized {
+        queue.push(position, move |render_pass| self.render_object(render_pass, object))
+    }
 }
\ No newline at end of file
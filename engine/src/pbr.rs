@@ -0,0 +1,164 @@
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, Sampler, TextureFormat};
+
+use crate::{Engine, Material, Texture, AlphaMode};
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PbrFactors {
+    pub base_color: [f32;4],
+    /// Emissive color and normal scale
+    pub emissive: [f32;4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    /// Fragments with a lower alpha are discarded, 0 renders them all
+    pub alpha_cutoff: f32
+}
+impl From<&compiler::Material> for PbrFactors {
+    fn from(v: &compiler::Material) -> Self {
+        Self {
+            base_color: v.base_color,
+            emissive: [v.emissive[0], v.emissive[1], v.emissive[2], v.normal_scale],
+            metallic: v.metallic,
+            roughness: v.roughness,
+            occlusion_strength: v.occlusion_strength,
+            alpha_cutoff: v.alpha_cutoff.unwrap_or(0.)
+        }
+    }
+}
+
+/// Metallic-roughness material read by the shaders including `engine/pbr`, missing textures are replaced by
+/// 1x1 textures that leave the factors untouched
+pub struct PbrMaterial {
+    pub factors: PbrFactors,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+    /// Base color, metallic-roughness, normal, occlusion and emissive textures
+    pub textures: [Texture;5],
+    buffer: Buffer,
+    bind_group: BindGroup
+}
+impl PbrMaterial {
+    pub fn new(e: &Engine, material: compiler::Material) -> Self {
+        let factors = PbrFactors::from(&material);
+        let texture = |image: Option<compiler::Image>, format: TextureFormat, default: [u8;4]| e.texture_from_image(
+            image.unwrap_or(compiler::Image {
+                width: 1,
+                height: 1,
+                pixels: compiler::Pixels::ARGB(default.to_vec())
+            }),
+            format
+        );
+        let textures: [Texture;5] = [
+            texture(material.base_color_texture, TextureFormat::Rgba8UnormSrgb, [255;4]),
+            texture(material.metallic_roughness_texture, TextureFormat::Rgba8Unorm, [255;4]),
+            texture(material.normal_texture, TextureFormat::Rgba8Unorm, [128, 128, 255, 255]),
+            texture(material.occlusion_texture, TextureFormat::Rgba8Unorm, [255;4]),
+            texture(material.emissive_texture, TextureFormat::Rgba8UnormSrgb, [255;4])
+        ];
+        Self::with_textures(
+            e,
+            factors,
+            if material.alpha_blend {
+                AlphaMode::Blend
+            } else if material.alpha_cutoff.is_some() {
+                AlphaMode::Mask
+            } else {
+                AlphaMode::Opaque
+            },
+            material.double_sided,
+            textures
+        )
+    }
+    pub fn with_textures(e: &Engine, factors: PbrFactors, alpha_mode: AlphaMode, double_sided: bool, textures: [Texture;5]) -> Self {
+        let buffer = e.new_buffer(bytemuck::bytes_of(&factors), BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        let mut entries = vec![wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }];
+        for (i, texture) in textures.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: wgpu::BindingResource::TextureView(&texture.view)
+            })
+        }
+        entries.push(wgpu::BindGroupEntry { binding: 6, resource: wgpu::BindingResource::Sampler(e.material_sampler()) });
+        Self {
+            factors,
+            alpha_mode,
+            double_sided,
+            bind_group: e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("PBR material"),
+                layout: &Self::bgl(&e.device),
+                entries: &entries
+            }),
+            textures,
+            buffer
+        }
+    }
+    /// Same material with another base color texture, its factors are copied in a buffer of their own
+    pub fn with_base_color(&self, e: &Engine, texture: Texture) -> Self {
+        let mut textures = self.textures.clone();
+        textures[0] = texture;
+        Self::with_textures(e, self.factors, self.alpha_mode, self.double_sided, textures)
+    }
+    /// Factors uniform, base color, metallic-roughness, normal, occlusion and emissive textures and their sampler
+    pub fn bgl(device: &Device) -> BindGroupLayout {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true }
+            },
+            count: None
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("PBR material"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                texture(1),
+                texture(2),
+                texture(3),
+                texture(4),
+                texture(5),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        })
+    }
+    pub fn set_factors(&mut self, e: &Engine, factors: PbrFactors) {
+        self.factors = factors;
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&factors))
+    }
+}
+impl Engine {
+    /// Repeating trilinear sampler shared by every `PbrMaterial`
+    pub fn material_sampler(&self) -> &Sampler {
+        self.material_sampler.get_or_init(|| self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("PBR material"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        }))
+    }
+}
+impl Material for PbrMaterial {
+    const BGI: u32 = 1;
+    fn bind_group(&self) -> &BindGroup { &self.bind_group }
+}
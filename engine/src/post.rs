@@ -0,0 +1,366 @@
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, RenderPipeline, Sampler, TextureFormat, TextureView};
+
+use crate::{Engine, RenderGraph, TextureHandle, TextureDesc, TextureSize, PassContext};
+
+/// Format of the scene color, rendered by every `shader!` pipeline
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// Bloom mip chain length, the first level is half the scene color size
+pub const BLOOM_LEVELS: usize = 5;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapper {
+    #[default]
+    Aces,
+    AgX,
+    Reinhard
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Bloom {
+    /// Brightness from which colors start to bleed
+    pub threshold: f32,
+    /// Fraction of the threshold blended in below it
+    pub knee: f32,
+    pub intensity: f32
+}
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.,
+            knee: 0.5,
+            intensity: 0.05
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Vignette {
+    pub intensity: f32,
+    /// Distance from the center, 1 being the corners, where darkening starts
+    pub radius: f32
+}
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 0.3,
+            radius: 0.5
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PostSettings {
+    pub tone_mapper: ToneMapper,
+    /// In stops, the scene color is scaled by `2^exposure` before tone mapping
+    pub exposure: f32,
+    pub bloom: Option<Bloom>,
+    pub vignette: Option<Vignette>,
+    /// Mix between the tone mapped color and its color grading, see `PostProcess::set_lut`
+    pub lut_strength: f32,
+    pub fxaa: bool,
+    /// Contrast adaptive sharpening from 0 to 1 of the upscale to the output, `None` upscales bilinearly
+    pub sharpen: Option<f32>
+}
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            tone_mapper: Default::default(),
+            exposure: 0.,
+            bloom: Some(Default::default()),
+            vignette: Some(Default::default()),
+            lut_strength: 0.,
+            fxaa: true,
+            sharpen: Some(0.5)
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PostBinding {
+    pub exposure: f32,
+    pub tone_mapper: u32,
+    pub bloom_threshold: f32,
+    pub bloom_knee: f32,
+    pub bloom_intensity: f32,
+    pub vignette_intensity: f32,
+    pub vignette_radius: f32,
+    pub lut_strength: f32,
+    pub sharpness: f32,
+    pub _padding: [f32; 3]
+}
+impl From<&PostSettings> for PostBinding {
+    fn from(v: &PostSettings) -> Self {
+        let bloom = v.bloom.unwrap_or(Bloom { intensity: 0., ..Default::default() });
+        let vignette = v.vignette.unwrap_or(Vignette { intensity: 0., ..Default::default() });
+        Self {
+            exposure: v.exposure,
+            tone_mapper: v.tone_mapper as u32,
+            bloom_threshold: bloom.threshold,
+            bloom_knee: bloom.knee,
+            bloom_intensity: bloom.intensity,
+            vignette_intensity: vignette.intensity,
+            vignette_radius: vignette.radius,
+            lut_strength: v.lut_strength,
+            sharpness: v.sharpen.unwrap_or_default().clamp(0., 1.),
+            _padding: Default::default()
+        }
+    }
+}
+
+/// Turns the HDR scene color into the output texture: bloom, tone mapping, color grading, vignette and FXAA,
+/// then an upscale when the scene renders below the output size
+pub struct PostProcess {
+    pub settings: PostSettings,
+    buffer: Buffer,
+    sampler: Sampler,
+    lut: TextureView,
+    bgl: BindGroupLayout,
+    prefilter: RenderPipeline,
+    downsample: RenderPipeline,
+    upsample: RenderPipeline,
+    composite: RenderPipeline,
+    fxaa: RenderPipeline,
+    upscale: RenderPipeline,
+    sharpen: RenderPipeline
+}
+impl PostProcess {
+    pub fn new(e: &Engine) -> Self {
+        let settings = PostSettings::default();
+        let bgl = Self::bgl(&e.device);
+        let shader = e.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post process"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/post.wgsl").into())
+        });
+        let layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post process"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[]
+        });
+        let output_format = e.surface_config.lock().unwrap().format;
+        let pipeline = |entry_point, format, blend| e.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None
+        });
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add
+            },
+            alpha: wgpu::BlendComponent::REPLACE
+        };
+        Self {
+            buffer: e.new_buffer(bytemuck::bytes_of(&PostBinding::from(&settings)), BufferUsages::UNIFORM | BufferUsages::COPY_DST),
+            settings,
+            sampler: e.device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            }),
+            lut: Self::lut_view(e, 2, &[0, 0, 0, 255, 255, 0, 0, 255, 0, 255, 0, 255, 255, 255, 0, 255,
+                0, 0, 255, 255, 255, 0, 255, 255, 0, 255, 255, 255, 255, 255, 255, 255]),
+            bgl,
+            prefilter: pipeline("fs_prefilter", crate::HDR_FORMAT, None),
+            downsample: pipeline("fs_downsample", crate::HDR_FORMAT, None),
+            upsample: pipeline("fs_upsample", crate::HDR_FORMAT, Some(additive)),
+            composite: pipeline("fs_composite", output_format, None),
+            fxaa: pipeline("fs_fxaa", output_format, None),
+            upscale: pipeline("fs_upscale", output_format, None),
+            sharpen: pipeline("fs_sharpen", output_format, None)
+        }
+    }
+    fn bgl(device: &wgpu::Device) -> BindGroupLayout {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true }
+            },
+            count: None
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post process"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                texture(1, wgpu::TextureViewDimension::D2),
+                texture(2, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                },
+                texture(4, wgpu::TextureViewDimension::D3)
+            ]
+        })
+    }
+    fn lut_view(e: &Engine, size: u32, pixels: &[u8]) -> TextureView {
+        let texture = e.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color grading"),
+            size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        e.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size)
+            },
+            wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size }
+        );
+        texture.create_view(&Default::default())
+    }
+    /// Color grading LUT as a horizontal strip of `size` slices of `size` x `size` texels, blue growing per slice
+    pub fn set_lut(&mut self, e: &Engine, image: compiler::Image) {
+        let size = image.height;
+        assert_eq!(image.width, size * size, "A color grading LUT strip is {size} slices of {size}x{size} texels");
+        let strip = image.get_pixels_rgba();
+        let mut pixels = Vec::with_capacity(strip.len());
+        for slice in 0..size {
+            for y in 0..size {
+                let start = ((y * size * size + slice * size) * 4) as usize;
+                pixels.extend_from_slice(&strip[start..start + size as usize * 4])
+            }
+        }
+        self.lut = Self::lut_view(e, size, &pixels);
+        if self.settings.lut_strength == 0. {
+            self.settings.lut_strength = 1.
+        }
+        self.update(e)
+    }
+    /// Uploads `settings`
+    pub fn update(&self, e: &Engine) {
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&PostBinding::from(&self.settings)))
+    }
+    fn draw(&self, e: &Engine, ctx: &mut PassContext, pipeline: &RenderPipeline, target: TextureHandle, source: TextureHandle, bloom: TextureHandle) {
+        let bind_group = self.bind_group(e, ctx.view(source), ctx.view(bloom));
+        let mut render_pass = ctx.render_pass(&[target], None);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1)
+    }
+    fn bind_group(&self, e: &Engine, source: &TextureView, bloom: &TextureView) -> BindGroup {
+        e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post process"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: self.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(source) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(bloom) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+                wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::TextureView(&self.lut) }
+            ]
+        })
+    }
+    /// Adds the passes turning `hdr` into `RenderGraph::OUTPUT`. Everything runs at the size of `hdr` up to a final
+    /// upscale, passes added after these ones draw at the output resolution
+    pub fn add_passes<'a>(&'a self, e: &'a Engine, graph: &mut RenderGraph<'a>, hdr: TextureHandle) {
+        let scale = match graph.desc(hdr).map(|v| v.size) {
+            Some(TextureSize::Surface(scale)) => scale,
+            _ => 1.
+        };
+        let bloom = if self.settings.bloom.is_some() {
+            let levels = (0..BLOOM_LEVELS)
+                .map(|i| graph.create("bloom", TextureDesc::new(crate::HDR_FORMAT).scaled(scale * 0.5f32.powi(i as i32 + 1))))
+                .collect::<Vec<_>>();
+            let first = levels[0];
+            graph.add_pass("bloom prefilter")
+                .read(hdr)
+                .write(first)
+                .execute(move |ctx| self.draw(e, ctx, &self.prefilter, first, hdr, hdr));
+            for i in 1..BLOOM_LEVELS {
+                let (source, target) = (levels[i - 1], levels[i]);
+                graph.add_pass("bloom downsample")
+                    .read(source)
+                    .write(target)
+                    .execute(move |ctx| self.draw(e, ctx, &self.downsample, target, source, source));
+            }
+            for i in (0..BLOOM_LEVELS - 1).rev() {
+                let (source, target) = (levels[i + 1], levels[i]);
+                graph.add_pass("bloom upsample")
+                    .read(source)
+                    .write(target)
+                    .execute(move |ctx| self.draw(e, ctx, &self.upsample, target, source, source));
+            }
+            first
+        } else {
+            hdr
+        };
+
+        let upscaled = scale != 1.;
+        let ldr = TextureDesc::new(e.surface_config.lock().unwrap().format).scaled(scale);
+        let composite = if self.settings.fxaa || upscaled {
+            graph.create("ldr", ldr)
+        } else {
+            RenderGraph::OUTPUT
+        };
+        graph.add_pass("composite")
+            .read(hdr)
+            .read(bloom)
+            .write(composite)
+            .execute(move |ctx| self.draw(e, ctx, &self.composite, composite, hdr, bloom));
+        let antialiased = if self.settings.fxaa {
+            let target = if upscaled { graph.create("ldr antialiased", ldr) } else { RenderGraph::OUTPUT };
+            graph.add_pass("fxaa")
+                .read(composite)
+                .write(target)
+                .execute(move |ctx| self.draw(e, ctx, &self.fxaa, target, composite, composite));
+            target
+        } else {
+            composite
+        };
+        if upscaled {
+            let pipeline = if self.settings.sharpen.is_some() { &self.sharpen } else { &self.upscale };
+            graph.add_pass("upscale")
+                .read(antialiased)
+                .write(RenderGraph::OUTPUT)
+                .execute(move |ctx| self.draw(e, ctx, pipeline, RenderGraph::OUTPUT, antialiased, antialiased));
+        }
+    }
+}
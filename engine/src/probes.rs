@@ -0,0 +1,189 @@
+use std::f32::consts::PI;
+use wgpu::{Buffer, BufferUsages, Sampler, TextureView};
+use math::{Aabb, Vec3};
+
+use crate::{Engine, environment::{sh_basis, SH_BANDS}};
+
+/// Directions traced from each probe while baking
+pub const PROBE_SAMPLES: usize = 256;
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct IrradianceVolumeBinding {
+    /// Minimum corner and 1 when the probes are used
+    pub min: [f32;4],
+    /// Maximum corner and intensity
+    pub max: [f32;4]
+}
+
+/// Light probes baked over `bounds`, each one keeps the irradiance around it as L1 spherical harmonics
+#[derive(Clone, Debug)]
+pub struct ProbeGrid {
+    pub bounds: Aabb,
+    /// Probes along each axis, at the centers of as many cells
+    pub resolution: [u32;3],
+    /// Irradiance harmonics of each probe divided by pi, x varying fastest then y
+    pub probes: Vec<[[f32;3];4]>
+}
+impl ProbeGrid {
+    /// Bakes `resolution` probes over `bounds`, `radiance(origin, direction)` is the light reaching `origin`
+    /// from `direction`, for instance the environment where a ray escapes and the lit surface it hits otherwise
+    pub fn bake(bounds: Aabb, resolution: [u32;3], radiance: impl Fn(Vec3, Vec3) -> Vec3) -> Self {
+        let directions = (0..PROBE_SAMPLES)
+            .map(|i| {
+                let y = 1. - 2. * (i as f32 + 0.5) / PROBE_SAMPLES as f32;
+                let r = (1. - y * y).sqrt();
+                let (sin, cos) = (i as f32 * PI * (3. - 5f32.sqrt())).sin_cos();
+                Vec3::new(cos * r, y, sin * r)
+            })
+            .collect::<Vec<_>>();
+        let resolution = resolution.map(|v| v.max(1));
+        let [rx, ry, rz] = resolution;
+        let mut probes = Vec::with_capacity((rx * ry * rz) as usize);
+        for z in 0..rz {
+            for y in 0..ry {
+                for x in 0..rx {
+                    let origin = Self::probe_position(bounds, resolution, [x, y, z]);
+                    let mut sh = [[0.;3];4];
+                    for direction in directions.iter().copied() {
+                        let light = radiance(origin, direction);
+                        for (c, basis) in sh.iter_mut().zip(sh_basis(direction)) {
+                            let weight = basis * 4. * PI / PROBE_SAMPLES as f32;
+                            c[0] += light.x * weight;
+                            c[1] += light.y * weight;
+                            c[2] += light.z * weight
+                        }
+                    }
+                    for (i, c) in sh.iter_mut().enumerate() {
+                        let band = SH_BANDS[if i == 0 { 0 } else { 1 }] / PI;
+                        *c = c.map(|v| v * band)
+                    }
+                    probes.push(sh)
+                }
+            }
+        }
+        Self { bounds, resolution, probes }
+    }
+    fn probe_position(bounds: Aabb, resolution: [u32;3], probe: [u32;3]) -> Vec3 {
+        let [x, y, z] = [0, 1, 2].map(|i| (probe[i] as f32 + 0.5) / resolution[i] as f32);
+        bounds.min + (bounds.max - bounds.min).mul_element_wise(Vec3::new(x, y, z))
+    }
+    /// Center of a probe from its index along each axis
+    pub fn position(&self, probe: [u32;3]) -> Vec3 {
+        Self::probe_position(self.bounds, self.resolution, probe)
+    }
+    /// Irradiance divided by pi reaching a surface facing `normal` at `position`, interpolated between the probes
+    /// around it as `engine/lighting` does inside the volume
+    pub fn irradiance(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        let (offset, size) = (position - self.bounds.min, self.bounds.max - self.bounds.min);
+        let t = Vec3::new(offset.x / size.x, offset.y / size.y, offset.z / size.z);
+        let cell = |i: usize, t: f32| {
+            let g = (t * self.resolution[i] as f32 - 0.5).clamp(0., (self.resolution[i] - 1) as f32);
+            let low = g.floor() as u32;
+            (low, (low + 1).min(self.resolution[i] - 1), g - low as f32)
+        };
+        let (cells, basis) = ([cell(0, t.x), cell(1, t.y), cell(2, t.z)], sh_basis(normal.normalized()));
+        let mut res = Vec3::default();
+        for corner in 0..8 {
+            let mut weight = 1.;
+            let [x, y, z] = [0, 1, 2].map(|i| {
+                let (low, high, f) = cells[i];
+                let upper = (corner >> i) & 1 == 1;
+                weight *= if upper { f } else { 1. - f };
+                if upper { high } else { low }
+            });
+            let sh = &self.probes[(x + (y + z * self.resolution[1]) * self.resolution[0]) as usize];
+            for (c, b) in sh.iter().zip(basis) {
+                res += Vec3::new(c[0], c[1], c[2]) * (b * weight)
+            }
+        }
+        res.max_element_wise(Vec3::default())
+    }
+}
+
+/// Grid of light probes uploaded to the GPU. `engine/lighting` interpolates them in place of the environment
+/// irradiance inside the volume, fading back to it outside, so the scene blocks and reflects the light of the sky
+pub struct IrradianceVolume {
+    pub grid: ProbeGrid,
+    pub intensity: f32,
+    /// Leaves the environment irradiance everywhere when off
+    pub enabled: bool,
+    pub(crate) buffer: Buffer,
+    /// Harmonics of the red, green and blue channels
+    pub(crate) views: [TextureView;3],
+    pub(crate) sampler: Sampler
+}
+impl IrradianceVolume {
+    /// Volume without probes, the environment lights everything
+    pub fn empty(e: &Engine) -> Self {
+        let bounds = Aabb::new(Vec3::default(), Vec3::new(1., 1., 1.));
+        let mut volume = Self::new(e, ProbeGrid { bounds, resolution: [1;3], probes: vec![[[0.;3];4]] });
+        volume.enabled = false;
+        volume.update(e);
+        volume
+    }
+    /// Bakes a `ProbeGrid` and uploads it
+    pub fn bake(e: &Engine, bounds: Aabb, resolution: [u32;3], radiance: impl Fn(Vec3, Vec3) -> Vec3) -> Self {
+        Self::new(e, ProbeGrid::bake(bounds, resolution, radiance))
+    }
+    /// Volume of probes baked beforehand
+    pub fn new(e: &Engine, grid: ProbeGrid) -> Self {
+        let resolution = grid.resolution;
+        let size = wgpu::Extent3d { width: resolution[0], height: resolution[1], depth_or_array_layers: resolution[2] };
+        let views = [0, 1, 2].map(|channel| {
+            let texture = e.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Irradiance volume"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba16Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[]
+            });
+            let texels = grid.probes.iter()
+                .flat_map(|sh| sh.iter().map(|c| half::f16::from_f32(c[channel]).to_bits()))
+                .collect::<Vec<_>>();
+            e.queue.write_texture(
+                texture.as_image_copy(),
+                bytemuck::cast_slice(&texels),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(8 * resolution[0]),
+                    rows_per_image: Some(resolution[1])
+                },
+                size
+            );
+            texture.create_view(&Default::default())
+        });
+        let volume = Self {
+            grid,
+            intensity: 1.,
+            enabled: true,
+            buffer: e.new_buffer(&[0; std::mem::size_of::<IrradianceVolumeBinding>()], BufferUsages::UNIFORM | BufferUsages::COPY_DST),
+            views,
+            sampler: e.device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            })
+        };
+        volume.update(e);
+        volume
+    }
+    /// Irradiance divided by pi of the probes scaled by `intensity`
+    pub fn irradiance(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        self.grid.irradiance(position, normal) * self.intensity
+    }
+    /// Uploads `intensity` and `enabled`
+    pub fn update(&self, e: &Engine) {
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&IrradianceVolumeBinding {
+            min: self.grid.bounds.min.extend(if self.enabled { 1. } else { 0. }).into(),
+            max: self.grid.bounds.max.extend(self.intensity).into()
+        }))
+    }
+}
@@ -0,0 +1,441 @@
+use wgpu::{CommandEncoder, RenderPass, Texture, TextureFormat, TextureUsages, TextureView};
+
+use crate::Engine;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureSize {
+    /// Fraction of the surface size, follows window resizes
+    Surface(f32),
+    Fixed(u32, u32)
+}
+impl TextureSize {
+    pub fn resolve(&self, surface: (u32, u32)) -> (u32, u32) {
+        match *self {
+            Self::Surface(scale) => (
+                ((surface.0 as f32 * scale) as u32).max(1),
+                ((surface.1 as f32 * scale) as u32).max(1)
+            ),
+            Self::Fixed(width, height) => (width, height)
+        }
+    }
+}
+
+/// Texture allocated by the graph for one frame, textures with equal descriptions and disjoint lifetimes share memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureDesc {
+    pub format: TextureFormat,
+    pub size: TextureSize,
+    pub usage: TextureUsages,
+    /// Multisampled textures are resolved with `PassBuilder::resolve`
+    pub samples: u32,
+    /// Color the first pass writing the texture clears it to, depth textures are cleared to 1
+    pub clear: wgpu::Color
+}
+impl TextureDesc {
+    pub fn new(format: TextureFormat) -> Self {
+        Self {
+            format,
+            size: TextureSize::Surface(1.),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            samples: 1,
+            clear: wgpu::Color::BLACK
+        }
+    }
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.size = TextureSize::Surface(scale);
+        self
+    }
+    pub fn fixed(mut self, width: u32, height: u32) -> Self {
+        self.size = TextureSize::Fixed(width, height);
+        self
+    }
+    pub fn usage(mut self, usage: TextureUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
+    pub fn clear(mut self, color: wgpu::Color) -> Self {
+        self.clear = color;
+        self
+    }
+}
+
+enum Resource<'a> {
+    /// `Engine::output_texture`, copied to the surface after the last pass
+    Output,
+    Imported(&'a TextureView),
+    Transient(TextureDesc)
+}
+
+type Execute<'a> = Box<dyn FnOnce(&mut PassContext) + 'a>;
+
+struct Pass<'a> {
+    name: &'static str,
+    reads: Vec<TextureHandle>,
+    writes: Vec<TextureHandle>,
+    resolves: Vec<(TextureHandle, TextureHandle)>,
+    execute: Option<Execute<'a>>
+}
+
+/// Passes of one frame and the textures they use. A pass reads what the passes declared before it wrote,
+/// or the final texture when no writer is declared before it. Passes whose writes nobody reads are skipped
+/// unless they write the output or an imported texture and passes writing no texture always run
+pub struct RenderGraph<'a> {
+    names: Vec<&'static str>,
+    resources: Vec<Resource<'a>>,
+    passes: Vec<Pass<'a>>
+}
+impl<'a> Default for RenderGraph<'a> {
+    fn default() -> Self {
+        Self {
+            names: vec!["output"],
+            resources: vec![Resource::Output],
+            passes: Vec::new()
+        }
+    }
+}
+impl<'a> RenderGraph<'a> {
+    pub const OUTPUT: TextureHandle = TextureHandle(0);
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn create(&mut self, name: &'static str, desc: TextureDesc) -> TextureHandle {
+        self.resource(name, Resource::Transient(desc))
+    }
+    /// Description of a texture created by the graph, `None` for `OUTPUT` and imported ones
+    pub fn desc(&self, texture: TextureHandle) -> Option<TextureDesc> {
+        match self.resources[texture.0] {
+            Resource::Transient(desc) => Some(desc),
+            _ => None
+        }
+    }
+    /// Texture owned outside of the graph, like a shadow map kept between frames
+    pub fn import(&mut self, name: &'static str, view: &'a TextureView) -> TextureHandle {
+        self.resource(name, Resource::Imported(view))
+    }
+    pub fn add_pass<'g>(&'g mut self, name: &'static str) -> PassBuilder<'g, 'a> {
+        self.passes.push(Pass {
+            name,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            resolves: Vec::new(),
+            execute: None
+        });
+        PassBuilder {
+            pass: self.passes.last_mut().unwrap()
+        }
+    }
+    pub fn name(&self, texture: TextureHandle) -> &'static str {
+        self.names[texture.0]
+    }
+    fn resource(&mut self, name: &'static str, resource: Resource<'a>) -> TextureHandle {
+        self.names.push(name);
+        self.resources.push(resource);
+        TextureHandle(self.resources.len() - 1)
+    }
+    fn writers(&self, texture: TextureHandle) -> impl Iterator<Item = usize> + '_ {
+        self.passes.iter()
+            .enumerate()
+            .filter(move |(_, pass)| pass.writes.contains(&texture))
+            .map(|(i, _)| i)
+    }
+    /// Culls, orders and assigns memory to the passes and textures, panics on cyclic dependencies
+    pub fn compile(&self) -> Schedule {
+        let len = self.passes.len();
+
+        let mut needed = self.passes.iter()
+            .map(|pass| pass.writes.is_empty() || pass.writes.iter().any(|v| !matches!(self.resources[v.0], Resource::Transient(_))))
+            .collect::<Vec<_>>();
+        let mut stack = (0..len).filter(|i| needed[*i]).collect::<Vec<_>>();
+        while let Some(i) = stack.pop() {
+            let pass = &self.passes[i];
+            for texture in pass.reads.iter().chain(pass.writes.iter()) {
+                for writer in self.writers(*texture) {
+                    if !needed[writer] {
+                        needed[writer] = true;
+                        stack.push(writer)
+                    }
+                }
+            }
+        }
+
+        // Readers wait for the writers declared before them, or for every writer when none is,
+        // writers wait for the readers of the previous version and keep their declaration order
+        let mut dependencies = vec![Vec::new(); len];
+        for (i, pass) in self.passes.iter().enumerate().filter(|(i, _)| needed[*i]) {
+            for texture in pass.reads.iter() {
+                let earlier = self.writers(*texture).filter(|w| *w < i).collect::<Vec<_>>();
+                if earlier.is_empty() {
+                    dependencies[i].extend(self.writers(*texture).filter(|w| *w != i))
+                } else {
+                    dependencies[i].extend(earlier);
+                    for writer in self.writers(*texture).filter(|w| *w > i) {
+                        dependencies[writer].push(i)
+                    }
+                }
+            }
+            for texture in pass.writes.iter() {
+                dependencies[i].extend(self.writers(*texture).filter(|w| *w < i))
+            }
+        }
+        let mut order = Vec::with_capacity(len);
+        let mut done = vec![false; len];
+        while order.len() < needed.iter().filter(|v| **v).count() {
+            let next = (0..len)
+                .find(|i| needed[*i] && !done[*i] && dependencies[*i].iter().all(|d| done[*d]))
+                .unwrap_or_else(|| panic!(
+                    "Render graph cycle between passes: {:?}",
+                    (0..len).filter(|i| needed[*i] && !done[*i]).map(|i| self.passes[i].name).collect::<Vec<_>>()
+                ));
+            done[next] = true;
+            order.push(next)
+        }
+
+        let mut lifetimes = vec![None; self.resources.len()];
+        for (step, pass) in order.iter().map(|i| &self.passes[*i]).enumerate() {
+            for texture in pass.reads.iter().chain(pass.writes.iter()) {
+                let lifetime: &mut Option<(usize, usize)> = &mut lifetimes[texture.0];
+                *lifetime = Some(lifetime.map(|(first, _)| (first, step)).unwrap_or((step, step)))
+            }
+        }
+        let mut physical: Vec<(TextureDesc, usize)> = Vec::new();
+        let mut slots = vec![None; self.resources.len()];
+        let mut transients = self.resources.iter()
+            .enumerate()
+            .filter_map(|(i, resource)| match (resource, lifetimes[i]) {
+                (Resource::Transient(desc), Some(lifetime)) => Some((i, *desc, lifetime)),
+                _ => None
+            })
+            .collect::<Vec<_>>();
+        transients.sort_by_key(|(_, _, (first, _))| *first);
+        for (i, desc, (first, last)) in transients {
+            let slot = match physical.iter().position(|(d, busy)| *d == desc && *busy < first) {
+                Some(slot) => slot,
+                None => {
+                    physical.push((desc, 0));
+                    physical.len() - 1
+                }
+            };
+            physical[slot].1 = last;
+            slots[i] = Some(slot)
+        }
+
+        let mut written = vec![false; self.resources.len()];
+        let clears = order.iter()
+            .map(|i| self.passes[*i].writes.iter()
+                .map(|texture| !std::mem::replace(&mut written[texture.0], true))
+                .collect())
+            .collect();
+
+        Schedule {
+            order,
+            slots,
+            textures: physical.into_iter().map(|(desc, _)| desc).collect(),
+            clears
+        }
+    }
+}
+
+pub struct PassBuilder<'g, 'a> {
+    pass: &'g mut Pass<'a>
+}
+impl<'g, 'a> PassBuilder<'g, 'a> {
+    pub fn read(self, texture: TextureHandle) -> Self {
+        self.pass.reads.push(texture);
+        self
+    }
+    pub fn write(self, texture: TextureHandle) -> Self {
+        self.pass.writes.push(texture);
+        self
+    }
+    /// Resolves the multisampled `texture` into `target` at the end of the render pass attaching it,
+    /// does nothing when both are the same texture
+    pub fn resolve(self, texture: TextureHandle, target: TextureHandle) -> Self {
+        if texture != target {
+            self.pass.writes.push(target);
+            self.pass.resolves.push((texture, target))
+        }
+        self
+    }
+    pub fn execute(self, f: impl FnOnce(&mut PassContext) + 'a) {
+        self.pass.execute = Some(Box::new(f))
+    }
+}
+
+/// Result of `RenderGraph::compile`
+#[derive(Debug)]
+pub struct Schedule {
+    /// Passes to run, in order
+    pub order: Vec<usize>,
+    slots: Vec<Option<usize>>,
+    /// Textures to allocate
+    pub textures: Vec<TextureDesc>,
+    /// Whether each scheduled pass is the first writer of each of its writes
+    pub clears: Vec<Vec<bool>>
+}
+impl Schedule {
+    /// Index in `textures` of a transient texture, `None` if no scheduled pass uses it
+    pub fn slot(&self, texture: TextureHandle) -> Option<usize> {
+        self.slots[texture.0]
+    }
+}
+
+pub struct PassContext<'g> {
+    pub encoder: &'g mut CommandEncoder,
+    views: &'g [&'g TextureView],
+    clear_colors: &'g [wgpu::Color],
+    writes: &'g [TextureHandle],
+    resolves: &'g [(TextureHandle, TextureHandle)],
+    clears: &'g [bool],
+    name: &'static str
+}
+impl<'g> PassContext<'g> {
+    pub fn view(&self, texture: TextureHandle) -> &'g TextureView {
+        self.views[texture.0]
+    }
+    fn load<V>(&self, texture: TextureHandle, clear: V) -> wgpu::LoadOp<V> {
+        let write = self.writes.iter().position(|v| *v == texture)
+            .unwrap_or_else(|| panic!("Render pass '{}' attaches a texture it does not write", self.name));
+        if self.clears[write] { wgpu::LoadOp::Clear(clear) } else { wgpu::LoadOp::Load }
+    }
+    /// Render pass over declared writes, the first pass writing a texture in the frame clears it
+    pub fn render_pass(&mut self, colors: &[TextureHandle], depth: Option<TextureHandle>) -> RenderPass<'_> {
+        let color_attachments = colors.iter()
+            .map(|texture| Some(wgpu::RenderPassColorAttachment {
+                view: self.views[texture.0],
+                resolve_target: self.resolves.iter().find(|(v, _)| v == texture).map(|(_, target)| self.views[target.0]),
+                ops: wgpu::Operations {
+                    load: self.load(*texture, self.clear_colors[texture.0]),
+                    store: true
+                }
+            }))
+            .collect::<Vec<_>>();
+        let depth_stencil_attachment = depth.map(|texture| wgpu::RenderPassDepthStencilAttachment {
+            view: self.views[texture.0],
+            depth_ops: Some(wgpu::Operations {
+                load: self.load(texture, 1.),
+                store: true
+            }),
+            stencil_ops: None
+        });
+        self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.name),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment
+        })
+    }
+}
+
+struct PooledTexture {
+    desc: TextureDesc,
+    size: (u32, u32),
+    _texture: Texture,
+    view: TextureView,
+    used: bool
+}
+
+/// Transient textures kept between frames, emptied when the window is resized
+#[derive(Default)]
+pub struct TexturePool(Vec<PooledTexture>);
+impl TexturePool {
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+    fn acquire(&mut self, device: &wgpu::Device, desc: TextureDesc, size: (u32, u32)) -> usize {
+        if let Some(i) = self.0.iter().position(|v| !v.used && v.desc == desc && v.size == size) {
+            self.0[i].used = true;
+            return i
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Render graph"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: desc.samples,
+            dimension: wgpu::TextureDimension::D2,
+            format: desc.format,
+            usage: desc.usage,
+            view_formats: &[]
+        });
+        self.0.push(PooledTexture {
+            desc,
+            size,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            _texture: texture,
+            used: true
+        });
+        self.0.len() - 1
+    }
+}
+
+impl Engine {
+    /// Runs the graph passes and presents `Engine::output_texture`
+    pub fn render_graph(&self, mut graph: RenderGraph) {
+        let schedule = graph.compile();
+        let surface = {
+            let config = self.surface_config.lock().unwrap();
+            (config.width, config.height)
+        };
+        let output_texture = self.output_texture.lock().unwrap();
+        let mut pool = self.texture_pool.lock().unwrap();
+        pool.0.retain(|v| v.used);
+        pool.0.iter_mut().for_each(|v| v.used = false);
+        let textures = schedule.textures.iter()
+            .map(|desc| pool.acquire(&self.device, *desc, desc.size.resolve(surface)))
+            .collect::<Vec<_>>();
+
+        let mut clear_colors = Vec::with_capacity(graph.resources.len());
+        let views = graph.resources.iter()
+            .enumerate()
+            .map(|(i, resource)| match resource {
+                Resource::Output => {
+                    clear_colors.push(wgpu::Color::BLACK);
+                    &output_texture.view
+                }
+                Resource::Imported(view) => {
+                    clear_colors.push(wgpu::Color::BLACK);
+                    *view
+                }
+                Resource::Transient(desc) => {
+                    clear_colors.push(desc.clear);
+                    // Unused transients are never attached, any view fills their place
+                    schedule.slots[i].map(|slot| &pool.0[textures[slot]].view).unwrap_or(&output_texture.view)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut encoder = self.encoder();
+        let mut gpu_timer = self.gpu_timer.lock().unwrap();
+        if let Some(timer) = gpu_timer.as_mut() { timer.begin(&mut encoder) }
+        for (step, i) in schedule.order.iter().enumerate() {
+            let pass = &mut graph.passes[*i];
+            if let Some(execute) = pass.execute.take() {
+                execute(&mut PassContext {
+                    encoder: &mut encoder,
+                    views: &views,
+                    clear_colors: &clear_colors,
+                    writes: &pass.writes,
+                    resolves: &pass.resolves,
+                    clears: &schedule.clears[step],
+                    name: pass.name
+                })
+            }
+        }
+        if let Some(timer) = gpu_timer.as_mut() { timer.end(&mut encoder) }
+        drop(views);
+        drop(output_texture);
+        self.present_output_texture(encoder);
+        if let Some(timer) = gpu_timer.as_mut() { timer.submitted(&self.device) }
+        drop(gpu_timer);
+        self.update_render_scale()
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use wgpu::{Buffer, BufferUsages, CommandEncoder, Device, QuerySet, Queue};
+
+use crate::Engine;
+
+/// Render scale following the frame time: it drops when frames take longer than the budget and grows back when
+/// they are well under it, in steps of `STEP` so the scene targets are not reallocated every frame
+#[derive(Clone, Debug)]
+pub struct DynamicResolution {
+    pub min_scale: f32,
+    pub max_scale: f32,
+    scale: f32,
+    /// Exponential average of the frame times, in milliseconds
+    frame_time: Option<f32>
+}
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self {
+            min_scale: 0.5,
+            max_scale: 1.,
+            scale: 1.,
+            frame_time: None
+        }
+    }
+}
+impl DynamicResolution {
+    pub const STEP: f32 = 0.05;
+    /// Frames over the budget by this factor lower the scale
+    const OVER: f32 = 1.05;
+    /// Frames under the budget by this factor raise it
+    const UNDER: f32 = 0.85;
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+    /// Feeds the time of the last frame and returns the scale of the next ones
+    pub fn update(&mut self, frame_ms: f32, budget_ms: f32) -> f32 {
+        let average = match self.frame_time {
+            Some(v) => v + (frame_ms - v) * 0.1,
+            None => frame_ms
+        };
+        self.frame_time = Some(average);
+        if average > budget_ms * Self::OVER || average < budget_ms * Self::UNDER {
+            // The pixel count, and roughly the frame time, grows with the square of the scale
+            let ideal = self.scale * (budget_ms / average).sqrt();
+            let step = (ideal - self.scale).clamp(-Self::STEP * 2., Self::STEP);
+            let scale = ((self.scale + step) / Self::STEP).round() * Self::STEP;
+            self.scale = scale.clamp(self.min_scale, self.max_scale);
+        }
+        self.scale
+    }
+}
+
+enum Readback {
+    Free,
+    /// Copied this frame, mapped once submitted
+    Copied,
+    Mapping(Arc<AtomicBool>)
+}
+
+/// GPU time of the render graph from timestamp queries, read back a few frames late without stalling
+pub struct GpuTimer {
+    query_set: QuerySet,
+    resolve: Buffer,
+    readbacks: Vec<(Buffer, Readback)>,
+    frame: usize,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    last: Option<f32>
+}
+impl GpuTimer {
+    const READBACKS: usize = 3;
+    const SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+    /// `None` without `Features::TIMESTAMP_QUERY`
+    pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) { return None }
+        let buffer = |usage| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU timer"),
+            size: Self::SIZE,
+            usage,
+            mapped_at_creation: false
+        });
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("GPU timer"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2
+            }),
+            resolve: buffer(BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC),
+            readbacks: (0..Self::READBACKS)
+                .map(|_| (buffer(BufferUsages::MAP_READ | BufferUsages::COPY_DST), Readback::Free))
+                .collect(),
+            frame: 0,
+            period: queue.get_timestamp_period(),
+            last: None
+        })
+    }
+    /// Last GPU time read back, in milliseconds
+    pub fn last(&self) -> Option<f32> {
+        self.last
+    }
+    pub fn begin(&mut self, encoder: &mut CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 0)
+    }
+    /// Resolves the frame timestamps into a free readback buffer, frames finding none are not measured
+    pub fn end(&mut self, encoder: &mut CommandEncoder) {
+        encoder.write_timestamp(&self.query_set, 1);
+        let (buffer, state) = &mut self.readbacks[self.frame % Self::READBACKS];
+        if let Readback::Free = state {
+            encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve, 0);
+            encoder.copy_buffer_to_buffer(&self.resolve, 0, buffer, 0, Self::SIZE);
+            *state = Readback::Copied
+        }
+    }
+    /// Maps what `end` copied once the encoder is submitted and reads the buffers mapped since
+    pub fn submitted(&mut self, device: &Device) {
+        let (buffer, state) = &mut self.readbacks[self.frame % Self::READBACKS];
+        if let Readback::Copied = state {
+            let mapped = Arc::new(AtomicBool::new(false));
+            let done = mapped.clone();
+            buffer.slice(..).map_async(wgpu::MapMode::Read, move |res| done.store(res.is_ok(), Ordering::Release));
+            *state = Readback::Mapping(mapped)
+        }
+        self.frame += 1;
+        device.poll(wgpu::Maintain::Poll);
+        for (buffer, state) in self.readbacks.iter_mut() {
+            let Readback::Mapping(mapped) = state else { continue };
+            if !mapped.load(Ordering::Acquire) { continue }
+            let ticks: [u64;2] = bytemuck::cast_slice::<u8, u64>(&buffer.slice(..).get_mapped_range())
+                .try_into()
+                .unwrap();
+            buffer.unmap();
+            *state = Readback::Free;
+            self.last = Some(ticks[1].saturating_sub(ticks[0]) as f32 * self.period / 1e6)
+        }
+    }
+}
+
+impl Engine {
+    /// Size of the scene targets relative to the output, from `GraphicsSettings::render_scale` or below it
+    /// when `GraphicsSettings::dynamic_resolution` is on
+    pub fn render_scale(&self) -> f32 {
+        let settings = self.graphics_settings.lock().unwrap();
+        if settings.dynamic_resolution {
+            self.dynamic_resolution.lock().unwrap().scale().min(settings.render_scale)
+        } else {
+            settings.render_scale
+        }
+    }
+    /// GPU time of a recent frame in milliseconds, `None` when the adapter has no timestamp queries
+    pub fn gpu_frame_time(&self) -> Option<f32> {
+        self.gpu_timer.lock().unwrap().as_ref().and_then(GpuTimer::last)
+    }
+    /// Feeds the last frame time to the dynamic resolution, the GPU time when measured and the CPU one otherwise.
+    /// The budget is the frame rate cap, or 60 frames per second without one
+    pub(crate) fn update_render_scale(&self) {
+        let settings = self.graphics_settings.lock().unwrap();
+        if !settings.dynamic_resolution { return }
+        let frame_ms = self.gpu_frame_time().unwrap_or(self.time.delta() * 1000.);
+        let budget_ms = 1000. / if settings.max_fps > 0. { settings.max_fps as f32 } else { 60. };
+        let mut dynamic = self.dynamic_resolution.lock().unwrap();
+        dynamic.max_scale = settings.render_scale;
+        dynamic.update(frame_ms, budget_ms);
+    }
+}
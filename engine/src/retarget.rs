@@ -0,0 +1,99 @@
+use std::{collections::HashMap, sync::Arc};
+use compiler::Skeleton;
+use math::{SimpleTransform, Quaternion};
+
+use crate::{Animation, AnimationFrame, AnimationJoint};
+
+/// Maps animation clips authored for one skeleton onto another one, correcting the difference between both rest poses
+pub struct Retarget {
+    target: Arc<Skeleton>,
+    /// Source joint of each target joint
+    sources: Vec<Option<usize>>,
+    source_rest: Vec<SimpleTransform>,
+    /// Target joints sorted from the roots to the leaves
+    order: Vec<usize>,
+    scale: f32
+}
+impl Retarget {
+    /// `mapping` goes from source joint names to target joint names
+    pub fn new(source: &[AnimationJoint], target: Arc<Skeleton>, mapping: &HashMap<String, String>) -> Self {
+        assert!(!source.is_empty(), "Animation has no joint names, recompile it");
+        let sources = target.joints.iter()
+            .map(|joint| source.iter().position(|v| mapping.get(&v.name) == Some(&joint.name)))
+            .collect::<Vec<_>>();
+
+        let mut order = (0..target.joints.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| target.joints[*i].parents.len());
+
+        let scale = order.iter()
+            .find_map(|i| sources[*i].map(|s| (target.joints[*i].rest.translation.y, source[s].rest.translation.y)))
+            .filter(|(_, source)| source.abs() > 0.0001)
+            .map(|(target, source)| target / source)
+            .unwrap_or(1.);
+
+        Self {
+            sources,
+            source_rest: source.iter().map(|joint| joint.rest).collect(),
+            order,
+            scale,
+            target
+        }
+    }
+    /// Maps joints with the same name
+    pub fn by_name(source: &[AnimationJoint], target: Arc<Skeleton>) -> Self {
+        let mapping = source.iter()
+            .map(|joint| (joint.name.clone(), joint.name.clone()))
+            .collect();
+        Self::new(source, target, &mapping)
+    }
+    /// Maps joints through their humanoid bone names, e.g. VRM humanoid bones prepared by `prepare_vrm.py`
+    pub fn humanoid(source: &[AnimationJoint], target: Arc<Skeleton>) -> Self {
+        let mapping = source.iter()
+            .filter_map(|joint| {
+                let humanoid = joint.humanoid.as_ref()?;
+                let target = target.joints.iter().find(|v| v.humanoid.as_ref() == Some(humanoid))?;
+                Some((joint.name.clone(), target.name.clone()))
+            })
+            .collect();
+        Self::new(source, target, &mapping)
+    }
+    pub fn frame(&self, frame: &AnimationFrame) -> AnimationFrame {
+        let joints = &self.target.joints;
+        let mut deltas = vec![Quaternion::default(); joints.len()];
+        let mut res = vec![SimpleTransform::default(); joints.len()];
+        for i in self.order.iter().copied() {
+            let joint = &joints[i];
+            let parent = joint.parents.first().map(|v| *v as usize);
+            deltas[i] = match (self.sources[i], parent) {
+                (Some(s), _) => frame.joints[s].rotation * self.source_rest[s].rotation.conjugate(),
+                (None, Some(parent)) => deltas[parent],
+                (None, None) => Quaternion::default()
+            };
+            let translation = match (parent, self.sources[i]) {
+                (Some(parent), _) =>
+                    res[parent].translation + deltas[parent] * (joint.rest.translation - joints[parent].rest.translation),
+                (None, Some(s)) =>
+                    joint.rest.translation + (frame.joints[s].translation - self.source_rest[s].translation) * self.scale,
+                (None, None) => joint.rest.translation
+            };
+            res[i] = SimpleTransform::new(translation, (deltas[i] * joint.rest.rotation).normalised())
+        }
+        AnimationFrame {
+            root: SimpleTransform::new(frame.root.translation * self.scale, frame.root.rotation),
+            joints: res
+        }
+    }
+    pub fn animation(&self, animation: &Animation) -> Animation {
+        Animation::new(
+            animation.frames.iter().map(|frame| self.frame(frame)).collect(),
+            animation.events.to_vec(),
+            self.target.joints.iter()
+                .map(|joint| AnimationJoint {
+                    name: joint.name.clone(),
+                    humanoid: joint.humanoid.clone(),
+                    rest: joint.rest
+                })
+                .collect()
+        )
+    }
+}
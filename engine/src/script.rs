@@ -11,6 +11,7 @@ static ID: IdHandler = IdHandler::default();
 #[derive(Debug, Clone, Copy)]
 pub enum ScriptEvent {
     WindowResized,
+    SettingsChanged,
     WindowFocus,
     WindowBlur,
     Update,
@@ -30,6 +31,8 @@ pub trait Script<'s>: Send + Sized + 'static {
     fn update(&mut self) {}
     fn render(&mut self) {}
     fn window_resized(&mut self) {}
+    /// Graphics settings changed, e.g. `Engine::set_sample_count`, pipelines created before are outdated
+    fn settings_changed(&mut self) {}
     fn dropped(self) {}
 }
 
@@ -74,6 +77,7 @@ impl Engine {
                     ScriptEvent::Update => script.update(),
                     ScriptEvent::Render => script.render(),
                     ScriptEvent::WindowResized => script.window_resized(),
+                    ScriptEvent::SettingsChanged => script.settings_changed(),
                     ScriptEvent::Nothing => {}
                     ScriptEvent::Close => {
                         script.dropped();
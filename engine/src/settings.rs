@@ -0,0 +1,228 @@
+use std::{path::{Path, PathBuf}, sync::atomic::Ordering};
+use serde::{Serialize, Deserialize};
+use winit::{dpi::PhysicalSize, window::Fullscreen};
+
+use crate::Engine;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Fullscreen window at the monitor resolution
+    Borderless,
+    /// Exclusive fullscreen at the video mode closest to the settings resolution
+    Fullscreen
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    #[default]
+    Vsync,
+    NoVsync,
+    /// Presents the latest frame at the next vertical blank without blocking, vsync where unsupported
+    Mailbox
+}
+impl PresentMode {
+    pub fn wgpu(self) -> wgpu::PresentMode {
+        match self {
+            Self::Vsync => wgpu::PresentMode::AutoVsync,
+            Self::NoVsync => wgpu::PresentMode::AutoNoVsync,
+            Self::Mailbox => wgpu::PresentMode::Mailbox
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ShadowQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra
+}
+impl ShadowQuality {
+    /// Size of each cascade of the shadow map
+    pub fn resolution(self) -> u32 {
+        match self {
+            Self::Low => 1024,
+            Self::Medium => 1536,
+            Self::High => 2048,
+            Self::Ultra => 4096
+        }
+    }
+    pub fn cascades(self) -> usize {
+        match self {
+            Self::Low => 2,
+            Self::Medium => 3,
+            Self::High | Self::Ultra => 4
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AmbientOcclusion {
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High
+}
+impl AmbientOcclusion {
+    /// Directions sampled around each pixel, 0 when off
+    pub fn samples(self) -> u32 {
+        match self {
+            Self::Off => 0,
+            Self::Low => 8,
+            Self::Medium => 12,
+            Self::High => 24
+        }
+    }
+    /// Size of the occlusion texture relative to the scene targets
+    pub fn scale(self) -> f32 {
+        match self {
+            Self::Off | Self::Low | Self::Medium => 0.5,
+            Self::High => 1.
+        }
+    }
+}
+
+/// Player facing graphics options, kept as JSON next to the log and applied with `Engine::set_graphics_settings`.
+/// Missing fields take their default so older files keep loading
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Inner size of the window, or the video mode of exclusive fullscreen
+    pub resolution: (u32, u32),
+    pub window_mode: WindowMode,
+    pub present_mode: PresentMode,
+    /// 0 leaves the frame rate uncapped
+    pub max_fps: f64,
+    pub shadow_quality: ShadowQuality,
+    /// Samples per pixel of the scene targets, see `Engine::set_sample_count`
+    pub msaa: u32,
+    /// Size of the scene targets relative to the window, post processing upscales them to it. At most 1, the
+    /// bilinear upscale would skip texels of larger targets instead of averaging them
+    pub render_scale: f32,
+    /// Lowers the render scale below `render_scale` when frames miss the `max_fps` budget, see `DynamicResolution`
+    pub dynamic_resolution: bool,
+    /// Screen space ambient occlusion of the environment lighting, see `Ssao`
+    pub ambient_occlusion: AmbientOcclusion,
+    /// Diffuse environment lighting from the baked `IrradianceVolume` instead of the sky alone
+    pub light_probes: bool,
+    /// Skins animated characters once per frame with `ComputeSkinning` instead of in the vertex shader of every pass
+    pub compute_skinning: bool
+}
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            resolution: (900, 600),
+            window_mode: WindowMode::Windowed,
+            present_mode: PresentMode::Vsync,
+            max_fps: 0.,
+            shadow_quality: ShadowQuality::High,
+            msaa: 4,
+            render_scale: 1.,
+            dynamic_resolution: false,
+            ambient_occlusion: AmbientOcclusion::Medium,
+            light_probes: true,
+            compute_skinning: false
+        }
+    }
+}
+impl GraphicsSettings {
+    pub const FILE: &'static str = "graphics.json";
+
+    pub fn path() -> PathBuf {
+        std::env::current_dir().unwrap_or(PathBuf::from("./")).join(Self::FILE)
+    }
+    /// Settings saved at `path`, the defaults when it is missing or invalid
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let Ok(json) = std::fs::read_to_string(&path) else { return Self::default() };
+        serde_json::from_str::<Self>(&json)
+            .map(Self::sanitized)
+            .unwrap_or_else(|e| {
+                warn!("Invalid graphics settings in {}: {e}", path.as_ref().display());
+                Self::default()
+            })
+    }
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) = std::fs::write(&path, json) {
+            warn!("Could not save the graphics settings to {}: {e}", path.as_ref().display())
+        }
+    }
+    /// Clamps values a hand edited file could break the renderer with
+    pub fn sanitized(mut self) -> Self {
+        self.resolution = (self.resolution.0.max(100), self.resolution.1.max(100));
+        self.max_fps = self.max_fps.max(0.);
+        if !self.msaa.is_power_of_two() || self.msaa > 16 { self.msaa = 1 }
+        self.render_scale = if self.render_scale.is_finite() { self.render_scale.clamp(0.25, 1.) } else { 1. };
+        self
+    }
+}
+
+impl Engine {
+    pub fn graphics_settings(&self) -> GraphicsSettings {
+        self.graphics_settings.lock().unwrap().clone()
+    }
+    /// Applies what differs from the current settings and saves them, window changes go through `Engine::resize`
+    /// and scripts recreate what depends on the shadow quality, MSAA or compute skinning in `Script::settings_changed`,
+    /// the render scale is read every frame from `Engine::render_scale` and the ambient occlusion and light probes
+    /// by the scripts rendering them
+    pub fn set_graphics_settings(&self, settings: GraphicsSettings) {
+        let mut settings = settings.sanitized();
+        let previous = std::mem::replace(&mut *self.graphics_settings.lock().unwrap(), settings.clone());
+        if let Some(window) = &self.window {
+            if (settings.resolution, settings.window_mode) != (previous.resolution, previous.window_mode) {
+                apply_window(window, &settings)
+            }
+        }
+        if settings.present_mode != previous.present_mode {
+            self.set_present_mode(settings.present_mode)
+        }
+        self.time.set_max_fps(settings.max_fps);
+        self.set_sample_count(settings.msaa);
+        // Keeps the samples in use when the adapter rejected those asked for
+        settings.msaa = self.sample_count();
+        self.graphics_settings.lock().unwrap().msaa = settings.msaa;
+        if settings.shadow_quality != previous.shadow_quality || settings.compute_skinning != previous.compute_skinning {
+            self.settings_changed.store(true, Ordering::Relaxed)
+        }
+        if let Some(path) = &self.graphics_settings_path {
+            settings.save(path)
+        }
+    }
+    fn set_present_mode(&self, mode: PresentMode) {
+        let Some(surface) = &self.surface else { return };
+        let supported = surface.get_capabilities(&self.adapter).present_modes;
+        let mut config = self.surface_config.lock().unwrap();
+        config.present_mode = match mode.wgpu() {
+            wgpu::PresentMode::Mailbox if !supported.contains(&wgpu::PresentMode::Mailbox) => wgpu::PresentMode::AutoVsync,
+            mode => mode
+        };
+        surface.configure(&self.device, &config)
+    }
+}
+
+/// Resizes the window or changes its fullscreen mode, the resize event that follows reconfigures the surface
+pub(crate) fn apply_window(window: &winit::window::Window, settings: &GraphicsSettings) {
+    let (width, height) = settings.resolution;
+    match settings.window_mode {
+        WindowMode::Windowed => {
+            window.set_fullscreen(None);
+            window.set_inner_size(PhysicalSize { width, height })
+        }
+        WindowMode::Borderless => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+        WindowMode::Fullscreen => {
+            let mode = window.current_monitor()
+                .and_then(|monitor| monitor.video_modes().min_by_key(|mode| {
+                    let size = mode.size();
+                    (size.width.abs_diff(width) + size.height.abs_diff(height), u32::MAX - mode.refresh_rate_millihertz())
+                }));
+            window.set_fullscreen(Some(match mode {
+                Some(mode) => Fullscreen::Exclusive(mode),
+                None => Fullscreen::Borderless(None)
+            }))
+        }
+    }
+}
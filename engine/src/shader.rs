@@ -1,4 +1,5 @@
-use wgpu::RenderPipeline;
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use wgpu::{Features, PolygonMode, RenderPipeline, ShaderModule};
 
 use crate::{Material, vertex::Vertex, Engine, InstanceBinding};
 
@@ -8,4 +9,182 @@ pub trait Shader {
     type Instance: InstanceBinding;
     fn pipeline(&self) -> &RenderPipeline;
     fn new(e: &'static Engine) -> Self where Self: Sized;
-}
\ No newline at end of file
+}
+/// WGSL files shared by every shader, pasted in place of `#include <name>` lines
+const INCLUDES: &[(&str, &str)] = &[
+    ("engine/camera", include_str!("shaders/camera.wgsl")),
+    ("engine/fog", include_str!("shaders/fog.wgsl")),
+    ("engine/cascade", include_str!("shaders/cascade.wgsl")),
+    ("engine/shadow", include_str!("shaders/shadow.wgsl")),
+    ("engine/lights", include_str!("shaders/lights.wgsl")),
+    ("engine/lighting", include_str!("shaders/lighting.wgsl")),
+    ("engine/material", include_str!("shaders/material.wgsl")),
+    ("engine/pbr", include_str!("shaders/pbr.wgsl")),
+    ("engine/oit", include_str!("shaders/oit.wgsl"))
+];
+
+/// Resolves `#include <name>` lines, each file is included only once
+pub fn preprocess(source: &'static str) -> Cow<'static, str> {
+    if !source.contains('#') { return Cow::Borrowed(source) }
+    Cow::Owned(preprocess_with(source, &[]))
+}
+
+/// Resolves includes and the `#define NAME [value]`, `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` directives,
+/// `defines` are set before the first line with the same `NAME [value]` syntax and values replace every use of their name.
+/// They take priority over the `#define` lines of the source, which only give defaults to the names the caller left out
+pub fn preprocess_with(source: &str, defines: &[&str]) -> String {
+    let mut state = Preprocessor::default();
+    for define in defines {
+        state.define(define)
+    }
+    state.fixed = state.defines.iter().map(|(name, _)| name.clone()).collect();
+    let res = state.run(source);
+    if !state.conditions.is_empty() {
+        panic!("Unterminated #ifdef in shader")
+    }
+    res
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    included: Vec<&'static str>,
+    defines: Vec<(String, String)>,
+    /// Names defined by the caller, the source can not redefine them
+    fixed: Vec<String>,
+    /// Whether each open conditional is active, including its parents
+    conditions: Vec<bool>
+}
+impl Preprocessor {
+    fn define(&mut self, define: &str) {
+        let (name, value) = define.trim().split_once(char::is_whitespace).unwrap_or((define.trim(), ""));
+        self.defines.retain(|(v, _)| v != name);
+        self.defines.push((name.into(), value.trim().into()))
+    }
+    fn active(&self) -> bool {
+        self.conditions.last().copied().unwrap_or(true)
+    }
+    fn run(&mut self, source: &str) -> String {
+        let mut res = String::with_capacity(source.len());
+        for line in source.lines() {
+            let directive = line.trim().strip_prefix('#')
+                .map(|v| v.split_once(char::is_whitespace).unwrap_or((v, "")))
+                .map(|(directive, arg)| (directive, arg.trim()));
+            match directive {
+                Some((kind @ ("ifdef" | "ifndef"), name)) => {
+                    let defined = self.defines.iter().any(|(v, _)| v == name);
+                    let active = self.active() && defined == (kind == "ifdef");
+                    self.conditions.push(active)
+                }
+                Some(("else", _)) => {
+                    let active = self.conditions.pop().expect("#else without #ifdef in shader");
+                    let parent = self.active();
+                    self.conditions.push(parent && !active)
+                }
+                Some(("endif", _)) => {
+                    self.conditions.pop().expect("#endif without #ifdef in shader");
+                }
+                _ if !self.active() => {}
+                Some(("define", define)) => {
+                    let name = define.split_whitespace().next().unwrap_or_default();
+                    if !self.fixed.iter().any(|v| v == name) {
+                        self.define(define)
+                    }
+                }
+                Some(("include", name)) => {
+                    let name = name.trim_start_matches('<').trim_end_matches('>');
+                    let (name, include) = INCLUDES.iter()
+                        .find(|(v, _)| *v == name)
+                        .unwrap_or_else(|| panic!("Unknown shader include: <{name}>"));
+                    if !self.included.contains(name) {
+                        self.included.push(name);
+                        res += &self.run(include)
+                    }
+                }
+                _ => res += &self.substitute(line)
+            }
+            res.push('\n')
+        }
+        res
+    }
+    /// Replaces the defines with a value wherever their name appears as a whole identifier
+    fn substitute(&self, line: &str) -> String {
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let mut res = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(start) = rest.find(is_ident) {
+            res += &rest[..start];
+            rest = &rest[start..];
+            let end = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            match self.defines.iter().find(|(name, value)| name == word && !value.is_empty()) {
+                Some((_, value)) => res += value,
+                None => res += word
+            }
+            rest = &rest[end..];
+        }
+        res + rest
+    }
+}
+
+/// Shader modules by source address and sorted defines, shared by the pipelines built from the same permutation
+pub(crate) type ShaderModules = HashMap<(usize, Vec<String>), Arc<ShaderModule>>;
+/// Render pipelines by source address, sorted defines and pipeline state, see `Engine::render_pipeline`
+pub(crate) type RenderPipelines = HashMap<(usize, Vec<String>, String), Arc<RenderPipeline>>;
+
+fn permutation_key(source: &'static str, defines: &[&str]) -> (usize, Vec<String>) {
+    let mut key = defines.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    key.sort();
+    (source.as_ptr() as usize, key)
+}
+
+impl Engine {
+    /// Preprocesses `source` with `defines` into a shader module, cached across calls with the same permutation
+    pub fn shader_module(&self, label: &str, source: &'static str, defines: &[&str]) -> Arc<ShaderModule> {
+        self.shader_modules.lock().unwrap()
+            .entry(permutation_key(source, defines))
+            .or_insert_with(|| Arc::new(self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(preprocess_with(source, defines).into())
+            })))
+            .clone()
+    }
+    /// Pipeline of the `source` permutation with `defines`, created by `create` unless a shader with the same permutation
+    /// and `state` made it before. `state` describes everything else the pipeline is built from
+    pub fn render_pipeline(
+        &self,
+        source: &'static str,
+        defines: &[&str],
+        state: String,
+        create: impl FnOnce() -> RenderPipeline
+    ) -> Arc<RenderPipeline> {
+        let (source, defines) = permutation_key(source, defines);
+        let key = (source, defines, state);
+        if let Some(pipeline) = self.render_pipelines.lock().unwrap().get(&key) {
+            return pipeline.clone()
+        }
+        // Created unlocked, shaders made at the same time do not wait for each other
+        let pipeline = Arc::new(create());
+        self.render_pipelines.lock().unwrap().entry(key).or_insert(pipeline).clone()
+    }
+}
+
+/// `mode` when `features` allow it, `Fill` otherwise
+pub fn supported_polygon_mode(features: Features, mode: PolygonMode) -> PolygonMode {
+    let feature = match mode {
+        PolygonMode::Fill => return mode,
+        PolygonMode::Line => Features::POLYGON_MODE_LINE,
+        PolygonMode::Point => Features::POLYGON_MODE_POINT
+    };
+    if features.contains(feature) { mode } else { PolygonMode::Fill }
+}
+
+impl Engine {
+    /// Polygon mode of the pipelines declared with a `polygon` key, filled when the device lacks the feature of `mode`
+    pub fn polygon_mode(&self, label: &str, mode: PolygonMode) -> PolygonMode {
+        let res = supported_polygon_mode(self.device.features(), mode);
+        if res != mode {
+            warn!("{label}: the device does not support the {mode:?} polygon mode, drawing it filled")
+        }
+        res
+    }
+}
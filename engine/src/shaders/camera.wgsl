@@ -0,0 +1,8 @@
+// `engine::CameraBuffer` at group 0
+
+struct Camera {
+    perspective: mat4x4<f32>,
+    position: vec4<f32>
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
@@ -0,0 +1,7 @@
+// Shadow cascade of `engine::DirectionalLight` at group 0, bound by its shadow passes
+
+struct Cascade {
+    perspective: mat4x4<f32>
+};
+@group(0) @binding(0)
+var<uniform> cascade: Cascade;
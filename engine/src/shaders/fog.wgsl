@@ -0,0 +1,29 @@
+// `engine::Fog` at binding 1 of the camera group
+
+#include <engine/camera>
+
+struct Fog {
+    // Color and density
+    color: vec4<f32>,
+    // Inscattering color and exponent
+    inscattering: vec4<f32>,
+    // Light direction and start distance
+    direction: vec4<f32>,
+    // Height, height falloff and max opacity
+    height: vec4<f32>
+};
+@group(0) @binding(1)
+var<uniform> fog: Fog;
+
+// Blends `color` seen at `position` with the fog, whose density integrates analytically along the view ray
+fn apply_fog(color: vec3<f32>, position: vec3<f32>) -> vec3<f32> {
+    let ray = position - camera.position.xyz;
+    let distance = length(ray);
+    let falloff = fog.height.y;
+    let density = fog.color.w * exp(-falloff * (camera.position.y - fog.height.x));
+    let rise = falloff * ray.y;
+    let integral = select(1., (1. - exp(-rise)) / rise, abs(rise) > 0.0001);
+    let amount = min(1. - exp(-density * integral * max(distance - fog.direction.w, 0.)), fog.height.z);
+    let sun = pow(max(dot(ray / max(distance, 0.0001), -fog.direction.xyz), 0.), fog.inscattering.w);
+    return mix(color, fog.color.rgb + fog.inscattering.rgb * sun, amount);
+}
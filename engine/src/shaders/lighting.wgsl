@@ -0,0 +1,146 @@
+// Lighting shared by the materials at group 1: the `engine::LightClusters` with their `engine::DirectionalLight` at group 2
+// and the `engine::Environment` and `engine::IrradianceVolume` at group 3 with the occlusion of `engine::Ssao`,
+// `TRANSPARENT` surfaces are not in the occlusion pre-pass and ignore it
+
+#include <engine/camera>
+#include <engine/fog>
+#include <engine/shadow>
+#include <engine/lights>
+
+const PI: f32 = 3.14159265;
+
+@group(2) @binding(4)
+var<uniform> light: Light;
+@group(2) @binding(5)
+var light_shadow_texture: texture_depth_2d_array;
+@group(2) @binding(6)
+var light_shadow_sampler: sampler_comparison;
+
+struct Environment {
+    // Irradiance spherical harmonics divided by pi
+    sh: array<vec4<f32>, 9>,
+    // Intensity and highest mip level of the radiance texture
+    params: vec4<f32>
+};
+@group(3) @binding(0)
+var<uniform> environment: Environment;
+@group(3) @binding(1)
+var environment_texture: texture_2d<f32>;
+@group(3) @binding(2)
+var environment_sampler: sampler;
+
+struct IrradianceVolume {
+    // Minimum corner and 1 when the probes are used
+    min: vec4<f32>,
+    // Maximum corner and intensity
+    max: vec4<f32>
+};
+@group(3) @binding(3)
+var<uniform> probes: IrradianceVolume;
+// L1 irradiance harmonics of the red, green and blue channels
+@group(3) @binding(4)
+var probes_red: texture_3d<f32>;
+@group(3) @binding(5)
+var probes_green: texture_3d<f32>;
+@group(3) @binding(6)
+var probes_blue: texture_3d<f32>;
+@group(3) @binding(7)
+var probes_sampler: sampler;
+@group(3) @binding(8)
+var occlusion_texture: texture_2d<f32>;
+
+fn environment_irradiance(n: vec3<f32>) -> vec3<f32> {
+    let sh = environment.sh;
+    let res = sh[0].rgb * 0.282095 +
+        sh[1].rgb * 0.488603 * n.y +
+        sh[2].rgb * 0.488603 * n.z +
+        sh[3].rgb * 0.488603 * n.x +
+        sh[4].rgb * 1.092548 * n.x * n.y +
+        sh[5].rgb * 1.092548 * n.y * n.z +
+        sh[6].rgb * 0.315392 * (3. * n.z * n.z - 1.) +
+        sh[7].rgb * 1.092548 * n.x * n.z +
+        sh[8].rgb * 0.546274 * (n.x * n.x - n.y * n.y);
+    return max(res, vec3<f32>(0.)) * environment.params.x;
+}
+
+// Environment irradiance divided by pi, from the probes inside the volume and fading to the sky over a tenth
+// of its size outside
+fn diffuse_irradiance(position: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    let sky = environment_irradiance(n);
+    if (probes.min.w == 0.) {
+        return sky;
+    }
+    let uvw = (position - probes.min.xyz) / (probes.max.xyz - probes.min.xyz);
+    let outside = abs(uvw - 0.5) - 0.5;
+    let weight = clamp(1. - max(max(outside.x, outside.y), outside.z) * 10., 0., 1.);
+    let basis = vec4<f32>(0.282095, 0.488603 * n.y, 0.488603 * n.z, 0.488603 * n.x);
+    let irradiance = vec3<f32>(
+        dot(textureSampleLevel(probes_red, probes_sampler, uvw, 0.), basis),
+        dot(textureSampleLevel(probes_green, probes_sampler, uvw, 0.), basis),
+        dot(textureSampleLevel(probes_blue, probes_sampler, uvw, 0.), basis)
+    );
+    return mix(sky, max(irradiance, vec3<f32>(0.)) * probes.max.w, weight);
+}
+
+// Screen space ambient occlusion at `position`
+fn screen_occlusion(position: vec3<f32>) -> f32 {
+#ifdef TRANSPARENT
+    return 1.;
+#else
+    let clip = camera.perspective * vec4<f32>(position, 1.);
+    return textureSampleLevel(occlusion_texture, probes_sampler, clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5, 0.).r;
+#endif
+}
+
+fn environment_radiance(direction: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let uv = vec2<f32>(atan2(direction.x, direction.z) / (2. * PI) + 0.5, acos(clamp(direction.y, -1., 1.)) / PI);
+    return textureSampleLevel(environment_texture, environment_sampler, uv, roughness * environment.params.y).rgb * environment.params.x;
+}
+
+// Analytic fit of the split sum specular integral, replaces a BRDF lookup texture
+fn env_brdf_approx(f0: vec3<f32>, roughness: f32, n_v: f32) -> vec3<f32> {
+    let r = roughness * vec4<f32>(-1., -0.0275, -0.572, 0.022) + vec4<f32>(1., 0.0425, 1.04, -0.04);
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_v)) * r.x + r.y;
+    let ab = vec2<f32>(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+fn distribution_ggx(n_h: f32, a: f32) -> f32 {
+    let a2 = a * a;
+    let d = n_h * n_h * (a2 - 1.) + 1.;
+    return a2 / (PI * d * d);
+}
+
+fn visibility_smith_ggx(n_v: f32, n_l: f32, a: f32) -> f32 {
+    let a2 = a * a;
+    let v = n_l * sqrt(n_v * n_v * (1. - a2) + a2);
+    let l = n_v * sqrt(n_l * n_l * (1. - a2) + a2);
+    return 0.5 / max(v + l, 0.0001);
+}
+
+fn fresnel_schlick(f0: vec3<f32>, v_h: f32) -> vec3<f32> {
+    return f0 + (1. - f0) * pow(1. - v_h, 5.);
+}
+
+// Light reflected towards `eye` by a surface at `position` facing `n`, `occlusion` only darkens the ambient light
+fn lighting(position: vec3<f32>, n: vec3<f32>, base_color: vec3<f32>, metallic: f32, roughness: f32, occlusion: f32, eye: vec3<f32>) -> vec3<f32> {
+    let a = roughness * roughness;
+    let f0 = mix(vec3<f32>(0.04), base_color, metallic);
+    let diffuse = base_color * (1. - metallic);
+    let v = normalize(eye - position);
+    let n_v = max(dot(n, v), 0.0001);
+
+    // Directional light, its irradiance is pi times its color so a white lambertian surface facing a white light reflects 1
+    let l = -light.direction.xyz;
+    let h = normalize(l + v);
+    let n_l = max(dot(n, l), 0.);
+    let f = fresnel_schlick(f0, max(dot(v, h), 0.));
+    let specular = distribution_ggx(max(dot(n, h), 0.), a) * visibility_smith_ggx(n_v, n_l, a) * f;
+    let direct = ((1. - f) * diffuse + PI * specular) * n_l * shadow(vec4<f32>(position, 1.)) * light.color.rgb;
+
+    let ao = occlusion * screen_occlusion(position);
+    let ambient = (diffuse * diffuse_irradiance(position, n) +
+        environment_radiance(reflect(-v, n), roughness) * env_brdf_approx(f0, roughness, n_v)) * ao;
+
+    return direct + ambient + diffuse * clustered_lights(position, n);
+}
@@ -0,0 +1,57 @@
+// Point and spot lights of `engine::LightClusters`, bound at group 2
+
+struct ClusterLight {
+    // Position and range
+    position: vec4<f32>,
+    // Color and intensity
+    color: vec4<f32>,
+    // Cone axis and cosine of the outer angle
+    direction: vec4<f32>,
+    // Cosine of the inner angle
+    cone: vec4<f32>
+};
+struct Clusters {
+    view: mat4x4<f32>,
+    grid: vec4<u32>,
+    // near, far, tangent of half the fov and aspect
+    camera: vec4<f32>
+};
+@group(2) @binding(0)
+var<uniform> clusters: Clusters;
+@group(2) @binding(1)
+var<storage, read> cluster_lights: array<ClusterLight>;
+@group(2) @binding(2)
+var<storage, read> cluster_ranges: array<vec2<u32>>;
+@group(2) @binding(3)
+var<storage, read> cluster_indices: array<u32>;
+
+fn cluster_index(position: vec3<f32>) -> u32 {
+    let v = clusters.view * vec4<f32>(position, 1.);
+    let depth = max(-v.z, clusters.camera.x);
+    let ndc = v.xy / (depth * vec2<f32>(clusters.camera.z * clusters.camera.w, clusters.camera.z));
+    let grid = vec3<f32>(clusters.grid.xyz);
+    let tile = clamp(floor((ndc + 1.) * 0.5 * grid.xy), vec2<f32>(0.), grid.xy - 1.);
+    let slice = clamp(floor(log(depth / clusters.camera.x) / log(clusters.camera.y / clusters.camera.x) * grid.z), 0., grid.z - 1.);
+    return u32(tile.x) + u32(tile.y) * clusters.grid.x + u32(slice) * clusters.grid.x * clusters.grid.y;
+}
+
+fn cluster_light(light: ClusterLight, position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let to_light = light.position.xyz - position;
+    let distance = length(to_light);
+    let l = to_light / max(distance, 0.0001);
+    // Inverse square falloff windowed to reach zero at the light range
+    let window = clamp(1. - pow(distance / light.position.w, 4.), 0., 1.);
+    let attenuation = window * window / (distance * distance + 1.);
+    let cone = smoothstep(light.direction.w, light.cone.x, dot(-l, light.direction.xyz));
+    return light.color.rgb * light.color.w * attenuation * cone * max(dot(normal, l), 0.);
+}
+
+// Diffuse light received from every light of the cluster containing `position`
+fn clustered_lights(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let range = cluster_ranges[cluster_index(position)];
+    var res = vec3<f32>(0.);
+    for (var i = 0u; i < range.y; i++) {
+        res += cluster_light(cluster_lights[cluster_indices[range.x + i]], position, normal);
+    }
+    return res;
+}
@@ -0,0 +1,25 @@
+// Factors and textures of `engine::PbrMaterial` at group 1, shaded by `engine/pbr`
+
+struct PbrFactors {
+    base_color: vec4<f32>,
+    // Emissive color and normal scale
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32
+};
+@group(1) @binding(0)
+var<uniform> material: PbrFactors;
+@group(1) @binding(1)
+var material_base_color: texture_2d<f32>;
+@group(1) @binding(2)
+var material_metallic_roughness: texture_2d<f32>;
+@group(1) @binding(3)
+var material_normal: texture_2d<f32>;
+@group(1) @binding(4)
+var material_occlusion: texture_2d<f32>;
+@group(1) @binding(5)
+var material_emissive: texture_2d<f32>;
+@group(1) @binding(6)
+var material_sampler: sampler;
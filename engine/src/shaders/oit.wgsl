@@ -0,0 +1,16 @@
+// Fragment outputs of the pipelines declared with `blend oit`, composited by `engine::Oit`
+
+struct OitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) revealage: vec4<f32>
+};
+
+// Weights `color` by coverage and by `depth`, the fragment depth in [0, 1], so nearer surfaces dominate
+fn oit_output(color: vec4<f32>, depth: f32) -> OitOutput {
+    let a = color.a;
+    let weight = clamp(pow(min(1., a * 10.) + 0.01, 3.) * 1e8 * pow(1. - depth * 0.9, 3.), 0.01, 3000.);
+    var out: OitOutput;
+    out.accum = vec4<f32>(color.rgb * a, a) * weight;
+    out.revealage = vec4<f32>(a);
+    return out;
+}
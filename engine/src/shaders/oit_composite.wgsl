@@ -0,0 +1,22 @@
+// Resolves the weighted transparency of `engine::Oit` over the scene color
+
+@group(0) @binding(0)
+var accum_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var revealage_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(f32(i == 1u) * 4. - 1., f32(i == 2u) * 4. - 1., 0., 1.);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let revealage = textureLoad(revealage_texture, texel, 0).r;
+    if (revealage >= 0.999) {
+        discard;
+    }
+    let accum = textureLoad(accum_texture, texel, 0);
+    return vec4<f32>(accum.rgb / clamp(accum.a, 0.0001, 50000.), 1. - revealage);
+}
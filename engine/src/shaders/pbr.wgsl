@@ -0,0 +1,62 @@
+// Metallic-roughness shading of `engine::PbrMaterial` at group 1 with `engine/lighting`, `ALPHA_TO_COVERAGE`
+// turns the alpha cutoff into a coverage ramp one pixel wide, the result is fogged by `engine/fog`
+
+#include <engine/lighting>
+
+#include <engine/material>
+
+// Tangent space normal mapping without tangents, the frame is rebuilt from screen space derivatives
+fn pbr_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, sample: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+    let dp2perp = cross(dp2, normal);
+    let dp1perp = cross(normal, dp1);
+    let t = dp2perp * duv1.x + dp1perp * duv2.x;
+    let b = dp2perp * duv1.y + dp1perp * duv2.y;
+    let scale = inverseSqrt(max(max(dot(t, t), dot(b, b)), 1e-12));
+    let tangent = (sample * 2. - 1.) * vec3<f32>(material.emissive.w, material.emissive.w, 1.);
+    return normalize(mat3x3<f32>(t * scale, b * scale, normal) * tangent);
+}
+
+// World normal of the material at `position`, drawn by the pre-pass of `engine::Ssao` with the cutout of `pbr`
+fn pbr_prepass(position: vec3<f32>, normal: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let alpha = material.base_color.a * textureSample(material_base_color, material_sampler, uv).a;
+    let n = pbr_normal(normalize(normal), position, uv, textureSample(material_normal, material_sampler, uv).xyz);
+    if (alpha < material.alpha_cutoff) {
+        discard;
+    }
+    return n;
+}
+
+// Lit color of the material at `position`, seen from `eye`
+fn pbr(position: vec3<f32>, normal: vec3<f32>, uv: vec2<f32>, eye: vec3<f32>) -> vec4<f32> {
+    let base_color = material.base_color * textureSample(material_base_color, material_sampler, uv);
+    let metallic_roughness = textureSample(material_metallic_roughness, material_sampler, uv);
+    let normal_sample = textureSample(material_normal, material_sampler, uv).xyz;
+    let occlusion = textureSample(material_occlusion, material_sampler, uv).r;
+    let emissive = material.emissive.rgb * textureSample(material_emissive, material_sampler, uv).rgb;
+    let n = pbr_normal(normalize(normal), position, uv, normal_sample);
+#ifdef ALPHA_TO_COVERAGE
+    let alpha = select(
+        base_color.a,
+        clamp((base_color.a - material.alpha_cutoff) / max(fwidth(base_color.a), 0.0001) + 0.5, 0., 1.),
+        material.alpha_cutoff > 0.
+    );
+    if (alpha <= 0.) {
+        discard;
+    }
+#else
+    let alpha = base_color.a;
+    if (alpha < material.alpha_cutoff) {
+        discard;
+    }
+#endif
+
+    let metallic = material.metallic * metallic_roughness.b;
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.03, 1.);
+    let ao = mix(1., occlusion, material.occlusion_strength);
+    let color = lighting(position, n, base_color.rgb, metallic, roughness, ao, eye) + emissive;
+    return vec4<f32>(apply_fog(color, position), alpha);
+}
@@ -0,0 +1,200 @@
+// Post processing of `engine::PostProcess`, every pass draws one fullscreen triangle
+
+struct Post {
+    exposure: f32,
+    // 0 ACES, 1 AgX, 2 Reinhard, see `engine::ToneMapper`
+    tone_mapper: u32,
+    bloom_threshold: f32,
+    bloom_knee: f32,
+    bloom_intensity: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    lut_strength: f32,
+    sharpness: f32
+};
+@group(0) @binding(0)
+var<uniform> post: Post;
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+@group(0) @binding(2)
+var bloom: texture_2d<f32>;
+@group(0) @binding(3)
+var linear_sampler: sampler;
+@group(0) @binding(4)
+var lut: texture_3d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VertexOutput {
+    let p = vec2<f32>(f32(i == 1u) * 4. - 1., f32(i == 2u) * 4. - 1.);
+    var vout: VertexOutput;
+    vout.clip_position = vec4<f32>(p, 0., 1.);
+    vout.uv = vec2<f32>(p.x * 0.5 + 0.5, 0.5 - p.y * 0.5);
+    return vout;
+}
+
+fn texel(t: texture_2d<f32>) -> vec2<f32> {
+    return 1. / vec2<f32>(textureDimensions(t));
+}
+
+// Average of 4 bilinear taps, 16 source texels
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let d = texel(source);
+    return (textureSample(source, linear_sampler, uv + vec2<f32>(-d.x, -d.y)).rgb +
+        textureSample(source, linear_sampler, uv + vec2<f32>(d.x, -d.y)).rgb +
+        textureSample(source, linear_sampler, uv + vec2<f32>(-d.x, d.y)).rgb +
+        textureSample(source, linear_sampler, uv + vec2<f32>(d.x, d.y)).rgb) * 0.25;
+}
+
+// Keeps what is brighter than the threshold, with a soft knee below it
+@fragment
+fn fs_prefilter(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(vin.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = post.bloom_threshold * post.bloom_knee;
+    var soft = clamp(brightness - post.bloom_threshold + knee, 0., 2. * knee);
+    soft = soft * soft / (4. * knee + 0.0001);
+    let contribution = max(soft, brightness - post.bloom_threshold) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.);
+}
+
+@fragment
+fn fs_downsample(vin: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(vin.uv), 1.);
+}
+
+// 3x3 tent filter, added to the level it is drawn over
+@fragment
+fn fs_upsample(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let d = texel(source);
+    var sum = textureSample(source, linear_sampler, vin.uv).rgb * 4.;
+    sum += (textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, 0.)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, 0.)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(0., -d.y)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(0., d.y)).rgb) * 2.;
+    sum += textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, -d.y)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, -d.y)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, d.y)).rgb +
+        textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, d.y)).rgb;
+    return vec4<f32>(sum / 16., 1.);
+}
+
+fn aces(c: vec3<f32>) -> vec3<f32> {
+    let x = c * 0.6;
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.), vec3<f32>(1.));
+}
+
+// Minimal AgX fit, returns linear colors
+fn agx(c: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    var x = clamp(log2(max(inset * c, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    x = 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+    return pow(max(outset * x, vec3<f32>(0.)), vec3<f32>(2.2));
+}
+
+fn tone_map(c: vec3<f32>) -> vec3<f32> {
+    switch post.tone_mapper {
+        case 1u: {
+            return agx(c);
+        }
+        case 2u: {
+            return c / (1. + c);
+        }
+        default: {
+            return aces(c);
+        }
+    }
+}
+
+@fragment
+fn fs_composite(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(source, linear_sampler, vin.uv).rgb +
+        textureSample(bloom, linear_sampler, vin.uv).rgb * post.bloom_intensity;
+    var color = tone_map(hdr * exp2(post.exposure));
+
+    // The LUT maps display encoded colors, half a texel is skipped at each border
+    let size = f32(textureDimensions(lut).x);
+    let encoded = pow(color, vec3<f32>(1. / 2.2));
+    let graded = textureSampleLevel(lut, linear_sampler, encoded * ((size - 1.) / size) + 0.5 / size, 0.).rgb;
+    color = mix(color, pow(graded, vec3<f32>(2.2)), post.lut_strength);
+
+    let d = distance(vin.uv, vec2<f32>(0.5)) * 1.41421;
+    color *= 1. - post.vignette_intensity * smoothstep(post.vignette_radius, 1., d);
+    return vec4<f32>(color, 1.);
+}
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(sqrt(c), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+// Fast approximate anti aliasing, blurs along the edge direction found from the luma of the 4 diagonal neighbours
+@fragment
+fn fs_fxaa(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let d = texel(source);
+    let nw = textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, -d.y)).rgb;
+    let ne = textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, -d.y)).rgb;
+    let sw = textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, d.y)).rgb;
+    let se = textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, d.y)).rgb;
+    let m = textureSample(source, linear_sampler, vin.uv).rgb;
+    let l_nw = luma(nw);
+    let l_ne = luma(ne);
+    let l_sw = luma(sw);
+    let l_se = luma(se);
+    let l_m = luma(m);
+    let l_min = min(l_m, min(min(l_nw, l_ne), min(l_sw, l_se)));
+    let l_max = max(l_m, max(max(l_nw, l_ne), max(l_sw, l_se)));
+
+    var dir = vec2<f32>(-((l_nw + l_ne) - (l_sw + l_se)), (l_nw + l_sw) - (l_ne + l_se));
+    let reduce = max((l_nw + l_ne + l_sw + l_se) * 0.25 * 0.125, 1. / 128.);
+    let scale = 1. / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2<f32>(-8.), vec2<f32>(8.)) * d;
+
+    let a = 0.5 * (textureSample(source, linear_sampler, vin.uv + dir * (1. / 3. - 0.5)).rgb +
+        textureSample(source, linear_sampler, vin.uv + dir * (2. / 3. - 0.5)).rgb);
+    let b = a * 0.5 + 0.25 * (textureSample(source, linear_sampler, vin.uv - dir * 0.5).rgb +
+        textureSample(source, linear_sampler, vin.uv + dir * 0.5).rgb);
+    let l_b = luma(b);
+    return vec4<f32>(select(b, a, l_b < l_min || l_b > l_max), 1.);
+}
+
+// Bilinear upscale of the render resolution to the output
+@fragment
+fn fs_upscale(vin: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(source, linear_sampler, vin.uv).rgb, 1.);
+}
+
+// Bilinear upscale sharpened like FSR 1 RCAS: the cross of neighbours one source texel away is subtracted with a
+// weight that shrinks where the result would leave their range, so edges sharpen without ringing
+@fragment
+fn fs_sharpen(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let d = texel(source);
+    let m = textureSample(source, linear_sampler, vin.uv).rgb;
+    let n = textureSample(source, linear_sampler, vin.uv + vec2<f32>(0., -d.y)).rgb;
+    let s = textureSample(source, linear_sampler, vin.uv + vec2<f32>(0., d.y)).rgb;
+    let w = textureSample(source, linear_sampler, vin.uv + vec2<f32>(-d.x, 0.)).rgb;
+    let e = textureSample(source, linear_sampler, vin.uv + vec2<f32>(d.x, 0.)).rgb;
+    let c_min = min(m, min(min(n, s), min(w, e)));
+    let c_max = max(m, max(max(n, s), max(w, e)));
+    let amount = sqrt(clamp(min(c_min, 1. - c_max) / max(c_max, vec3<f32>(0.0001)), vec3<f32>(0.), vec3<f32>(1.)));
+    let weight = amount * (-1. / mix(8., 5., post.sharpness));
+    let color = (m + (n + s + w + e) * weight) / (1. + 4. * weight);
+    return vec4<f32>(clamp(color, vec3<f32>(0.), vec3<f32>(1.)), 1.);
+}
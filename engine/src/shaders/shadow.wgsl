@@ -0,0 +1,96 @@
+// Shadows of `engine::DirectionalLight`, the including shader declares:
+//     var<uniform> light: Light;
+//     var light_shadow_texture: texture_depth_2d_array;
+//     var light_shadow_sampler: sampler_comparison;
+
+struct Light {
+    cascades: array<mat4x4<f32>, 4>,
+    spheres: array<vec4<f32>, 4>,
+    direction: vec4<f32>,
+    cascades_len: u32,
+    blend: f32,
+    // 0 hard, 1 PCF, 2 PCSS, see `engine::ShadowFilter`
+    shadow_filter: u32,
+    kernel: u32,
+    light_size: f32,
+    // Color times intensity
+    color: vec4<f32>
+};
+
+fn shadow_compare(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+    return textureSampleCompareLevel(light_shadow_texture, light_shadow_sampler, uv, i32(cascade), depth);
+}
+
+// Averages a square kernel of `2 * light.kernel + 1` samples per side, `spread` texels apart
+fn shadow_pcf(cascade: u32, uv: vec2<f32>, depth: f32, spread: f32) -> f32 {
+    let texel = spread / vec2<f32>(textureDimensions(light_shadow_texture));
+    let k = i32(light.kernel);
+    var sum = 0.;
+    for (var x = -k; x <= k; x++) {
+        for (var y = -k; y <= k; y++) {
+            sum += shadow_compare(cascade, uv + vec2<f32>(f32(x), f32(y)) * texel, depth);
+        }
+    }
+    let side = f32(2 * k + 1);
+    return sum / (side * side);
+}
+
+// Percentage closer soft shadows, the penumbra grows with the distance between the receiver and its blockers
+fn shadow_pcss(cascade: u32, uv: vec2<f32>, depth: f32) -> f32 {
+    let size = vec2<i32>(textureDimensions(light_shadow_texture));
+    let k = i32(max(light.kernel, 1u));
+    let step = max(light.light_size / f32(k), 1.);
+    let center = vec2<f32>(size) * uv;
+    var blockers = 0.;
+    var blockers_depth = 0.;
+    for (var x = -k; x <= k; x++) {
+        for (var y = -k; y <= k; y++) {
+            let texel = clamp(vec2<i32>(center + vec2<f32>(f32(x), f32(y)) * step), vec2<i32>(0), size - 1);
+            let d = textureLoad(light_shadow_texture, texel, i32(cascade), 0);
+            if (d < depth) {
+                blockers += 1.;
+                blockers_depth += d;
+            }
+        }
+    }
+    if (blockers == 0.) {
+        return 1.;
+    }
+    let average = blockers_depth / blockers;
+    let penumbra = (depth - average) / max(average, 0.0001) * light.light_size;
+    return shadow_pcf(cascade, uv, depth, clamp(penumbra / f32(k), 1., step));
+}
+
+fn cascade_shadow(cascade: u32, position: vec4<f32>) -> f32 {
+    let p = light.cascades[cascade] * position;
+    let uv = vec2<f32>(p.x * 0.5 + 0.5, p.y * -0.5 + 0.5);
+    switch light.shadow_filter {
+        case 1u: {
+            return shadow_pcf(cascade, uv, p.z, 1.);
+        }
+        case 2u: {
+            return shadow_pcss(cascade, uv, p.z);
+        }
+        default: {
+            return shadow_compare(cascade, uv, p.z);
+        }
+    }
+}
+
+// Light visibility from 0 (shadowed) to 1, picks the first cascade whose sphere contains `position`
+// and blends it with the next one near its border
+fn shadow(position: vec4<f32>) -> f32 {
+    for (var i = 0u; i < light.cascades_len; i++) {
+        let sphere = light.spheres[i];
+        let d = distance(position.xyz, sphere.xyz) / sphere.w;
+        if (d < 1.) {
+            let s = cascade_shadow(i, position);
+            let blend_start = 1. - light.blend;
+            if (d > blend_start && i + 1u < light.cascades_len) {
+                return mix(s, cascade_shadow(i + 1u, position), (d - blend_start) / max(light.blend, 1e-4));
+            }
+            return s;
+        }
+    }
+    return 1.;
+}
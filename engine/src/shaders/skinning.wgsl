@@ -0,0 +1,45 @@
+// Skins `engine::vertex::pnj` vertices into `engine::vertex::pn` vertices
+
+struct Animator {
+    offset: u32,
+    joints: u32
+};
+@group(0) @binding(0)
+var<uniform> animator: Animator;
+@group(0) @binding(1)
+var<storage, read> joints: array<mat4x4<f32>>;
+@group(0) @binding(2)
+var<storage, read> source: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> output: array<f32>;
+
+const SOURCE_STRIDE: u32 = 14u;
+const OUTPUT_STRIDE: u32 = 6u;
+
+fn read_f32(i: u32) -> f32 {
+    return bitcast<f32>(source[i]);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= arrayLength(&source) / SOURCE_STRIDE) {
+        return;
+    }
+    let i = id.x * SOURCE_STRIDE;
+    let position = vec4<f32>(read_f32(i), read_f32(i + 1u), read_f32(i + 2u), 1.);
+    let normal = vec4<f32>(read_f32(i + 3u), read_f32(i + 4u), read_f32(i + 5u), 0.);
+    let skin =
+        joints[animator.offset + source[i + 6u]] * read_f32(i + 10u) +
+        joints[animator.offset + source[i + 7u]] * read_f32(i + 11u) +
+        joints[animator.offset + source[i + 8u]] * read_f32(i + 12u) +
+        joints[animator.offset + source[i + 9u]] * read_f32(i + 13u);
+    let p = skin * position;
+    let n = normalize((skin * normal).xyz);
+    let o = id.x * OUTPUT_STRIDE;
+    output[o] = p.x;
+    output[o + 1u] = p.y;
+    output[o + 2u] = p.z;
+    output[o + 3u] = n.x;
+    output[o + 4u] = n.y;
+    output[o + 5u] = n.z;
+}
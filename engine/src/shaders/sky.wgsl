@@ -0,0 +1,115 @@
+// `engine::Sky` at group 1, drawn at the far plane behind the scene with the camera at group 0.
+// The procedural sky is the single scattering of `engine::Atmosphere`, mirrored by `Atmosphere::radiance`
+
+#include <engine/camera>
+
+const PI: f32 = 3.14159265;
+const PRIMARY_STEPS: i32 = 16;
+const LIGHT_STEPS: i32 = 8;
+
+struct Sky {
+    // Camera forward, and right and up scaled to the edges of the view
+    forward: vec4<f32>,
+    right: vec4<f32>,
+    up: vec4<f32>,
+    // Direction to the sun and its intensity
+    sun: vec4<f32>,
+    // Direction to the moon and its intensity
+    moon: vec4<f32>,
+    // Rayleigh scattering coefficients and scale height
+    rayleigh: vec4<f32>,
+    // Mie scattering coefficient, scale height, anisotropy and cosine of the sun disk radius
+    mie: vec4<f32>,
+    // Planet radius, atmosphere radius and observer altitude
+    planet: vec4<f32>,
+    // Intensity and 1 when the cubemap replaces the atmosphere
+    params: vec4<f32>
+};
+@group(1) @binding(0)
+var<uniform> sky: Sky;
+@group(1) @binding(1)
+var sky_cubemap: texture_cube<f32>;
+@group(1) @binding(2)
+var sky_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VertexOutput {
+    let p = vec2<f32>(f32(i == 1u) * 4. - 1., f32(i == 2u) * 4. - 1.);
+    var vout: VertexOutput;
+    vout.clip_position = vec4<f32>(p, 1., 1.);
+    vout.ndc = p;
+    return vout;
+}
+
+// Distance from `origin`, inside the sphere of `radius` around the planet center, to its surface along `direction`
+fn sphere_exit(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> f32 {
+    let b = dot(origin, direction);
+    let c = dot(origin, origin) - radius * radius;
+    return -b + sqrt(max(b * b - c, 0.));
+}
+
+// Light of a sun of `intensity` towards `sun` scattered along `direction`, the moon is lit the same way
+fn atmosphere(direction: vec3<f32>, sun: vec3<f32>, intensity: f32) -> vec3<f32> {
+    let origin = vec3<f32>(0., sky.planet.x + sky.planet.z, 0.);
+    let step = sphere_exit(origin, direction, sky.planet.y) / f32(PRIMARY_STEPS);
+    var optical_r = 0.;
+    var optical_m = 0.;
+    var total_r = vec3<f32>(0.);
+    var total_m = vec3<f32>(0.);
+    for (var i = 0; i < PRIMARY_STEPS; i++) {
+        let p = origin + direction * (step * (f32(i) + 0.5));
+        let height = length(p) - sky.planet.x;
+        let density_r = exp(-height / sky.rayleigh.w) * step;
+        let density_m = exp(-height / sky.mie.y) * step;
+        optical_r += density_r;
+        optical_m += density_m;
+
+        // Points in the shadow of the planet receive no sunlight
+        let b = dot(p, sun);
+        if (b < 0. && b * b - dot(p, p) + sky.planet.x * sky.planet.x > 0.) {
+            continue;
+        }
+        let light_step = sphere_exit(p, sun, sky.planet.y) / f32(LIGHT_STEPS);
+        var light_r = 0.;
+        var light_m = 0.;
+        for (var j = 0; j < LIGHT_STEPS; j++) {
+            let q = p + sun * (light_step * (f32(j) + 0.5));
+            let h = length(q) - sky.planet.x;
+            light_r += exp(-h / sky.rayleigh.w) * light_step;
+            light_m += exp(-h / sky.mie.y) * light_step;
+        }
+        let attenuation = exp(-(sky.rayleigh.rgb * (optical_r + light_r) + sky.mie.x * 1.1 * (optical_m + light_m)));
+        total_r += density_r * attenuation;
+        total_m += density_m * attenuation;
+    }
+    let mu = dot(direction, sun);
+    let g = sky.mie.z;
+    let phase_r = 3. / (16. * PI) * (1. + mu * mu);
+    let phase_m = 3. / (8. * PI) * ((1. - g * g) * (1. + mu * mu)) / ((2. + g * g) * pow(1. + g * g - 2. * g * mu, 1.5));
+    let transmittance = exp(-(sky.rayleigh.rgb * optical_r + sky.mie.x * 1.1 * optical_m));
+    let disk = smoothstep(sky.mie.w, sky.mie.w + (1. - sky.mie.w) * 0.2, mu);
+    return intensity * (phase_r * sky.rayleigh.rgb * total_r + phase_m * sky.mie.x * total_m + transmittance * disk);
+}
+
+@fragment
+fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(sky.forward.xyz + sky.right.xyz * vin.ndc.x + sky.up.xyz * vin.ndc.y);
+    if (sky.params.y > 0.) {
+        return vec4<f32>(textureSample(sky_cubemap, sky_sampler, direction).rgb * sky.params.x, 1.);
+    }
+    // Below the horizon the sky keeps its horizon color
+    let above = normalize(vec3<f32>(direction.x, max(direction.y, 0.), direction.z) + vec3<f32>(0., 0.0001, 0.));
+    var color = vec3<f32>(0.);
+    if (sky.sun.w > 0.) {
+        color += atmosphere(above, sky.sun.xyz, sky.sun.w);
+    }
+    if (sky.moon.w > 0.) {
+        color += atmosphere(above, sky.moon.xyz, sky.moon.w);
+    }
+    return vec4<f32>(color * sky.params.x, 1.);
+}
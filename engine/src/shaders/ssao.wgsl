@@ -0,0 +1,109 @@
+// Ambient occlusion of `engine::Ssao` from the pre-pass depth and world normals, then blurred along x and y
+// by a bilateral filter that ignores the texels of surfaces at another distance from the camera
+
+const BLUR_RADIUS: i32 = 4;
+
+struct Ssao {
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    camera: vec4<f32>,
+    // Radius, intensity, bias and sample count
+    params: vec4<f32>
+};
+@group(0) @binding(0)
+var<uniform> ssao: Ssao;
+// Depth bound as a float texture, depth textures cannot be loaded from on every backend
+@group(0) @binding(1)
+var depth_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var normal_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var occlusion_texture: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VertexOutput {
+    let p = vec2<f32>(f32(i == 1u) * 4. - 1., f32(i == 2u) * 4. - 1.);
+    var vout: VertexOutput;
+    vout.clip_position = vec4<f32>(p, 0., 1.);
+    vout.uv = vec2<f32>(p.x * 0.5 + 0.5, 0.5 - p.y * 0.5);
+    return vout;
+}
+
+fn texel_at(uv: vec2<f32>, size: vec2<u32>) -> vec2<i32> {
+    return clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), vec2<i32>(size) - 1);
+}
+
+// World position of the pre-pass surface under `uv`, `w` is 0 where nothing was drawn
+fn position_at(uv: vec2<f32>) -> vec4<f32> {
+    let depth = textureLoad(depth_texture, texel_at(uv, textureDimensions(depth_texture)), 0).r;
+    let p = ssao.inverse_view_projection * vec4<f32>(uv.x * 2. - 1., 1. - uv.y * 2., depth, 1.);
+    return vec4<f32>(p.xyz / p.w, f32(depth < 1.));
+}
+
+@fragment
+fn fs_occlusion(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let p = position_at(vin.uv);
+    if (p.w == 0.) {
+        return vec4<f32>(1.);
+    }
+    let n = normalize(textureLoad(normal_texture, texel_at(vin.uv, textureDimensions(normal_texture)), 0).xyz);
+    let up = select(vec3<f32>(0., 1., 0.), vec3<f32>(1., 0., 0.), abs(n.y) > 0.99);
+    let t = normalize(cross(up, n));
+    let b = cross(n, t);
+    let radius = ssao.params.x;
+    let samples = i32(ssao.params.w);
+    // Interleaved gradient noise turns the pattern per pixel, the blur averages it out
+    let noise = fract(52.9829189 * fract(dot(vin.clip_position.xy, vec2<f32>(0.06711056, 0.00583715))));
+    var occlusion = 0.;
+    for (var i = 0; i < samples; i++) {
+        // Cosine weighted directions on a golden angle spiral, at distances growing with the index
+        let f = (f32(i) + noise) / f32(samples);
+        let angle = f32(i) * 2.39996323 + noise * 6.28318531;
+        let r = sqrt(f);
+        let direction = t * (r * cos(angle)) + b * (r * sin(angle)) + n * sqrt(1. - f);
+        let s = p.xyz + direction * (radius * mix(0.1, 1., f * f));
+        let clip = ssao.view_projection * vec4<f32>(s, 1.);
+        let q = position_at(clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5);
+        // Surfaces in front of the sample occlude it, unless they are far out of the radius
+        let in_front = distance(ssao.camera.xyz, q.xyz) < distance(ssao.camera.xyz, s) - ssao.params.z;
+        let range = smoothstep(0., 1., radius / max(distance(p.xyz, q.xyz), 0.0001));
+        occlusion += select(0., range, in_front && q.w > 0.);
+    }
+    return vec4<f32>(pow(clamp(1. - occlusion / f32(max(samples, 1)), 0., 1.), ssao.params.y));
+}
+
+fn blur(uv: vec2<f32>, step: vec2<i32>) -> vec4<f32> {
+    let center = position_at(uv);
+    if (center.w == 0.) {
+        return vec4<f32>(1.);
+    }
+    let center_distance = distance(ssao.camera.xyz, center.xyz);
+    let size = textureDimensions(occlusion_texture);
+    let texel = texel_at(uv, size);
+    var sum = 0.;
+    var weights = 0.;
+    for (var i = -BLUR_RADIUS; i <= BLUR_RADIUS; i++) {
+        let sample = clamp(texel + step * i, vec2<i32>(0), vec2<i32>(size) - 1);
+        let q = position_at((vec2<f32>(sample) + 0.5) / vec2<f32>(size));
+        let difference = abs(distance(ssao.camera.xyz, q.xyz) - center_distance) / (center_distance * 0.05 + 0.001);
+        let weight = exp(-f32(i * i) / f32(BLUR_RADIUS * BLUR_RADIUS)) * exp(-difference * difference) * q.w;
+        sum += textureLoad(occlusion_texture, sample, 0).r * weight;
+        weights += weight;
+    }
+    return vec4<f32>(select(1., sum / weights, weights > 0.));
+}
+
+@fragment
+fn fs_blur_x(vin: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(vin.uv, vec2<i32>(1, 0));
+}
+
+@fragment
+fn fs_blur_y(vin: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(vin.uv, vec2<i32>(0, 1));
+}
@@ -0,0 +1,191 @@
+use std::{sync::{Arc, Mutex}, ops::Range};
+use wgpu::{Buffer, Device, BindGroup, BindGroupLayout, ComputePipeline, CommandEncoder};
+
+use crate::{Engine, Mesh, Animator, vertex::{pn, pnj}};
+
+/// Joints matrices available to every animated object, 64 bytes each
+pub const SKINNING_POOL_CAPACITY: u32 = 16384;
+
+/// First fit allocator of ranges in `0..capacity`, adjacent free ranges are merged back on release
+pub struct RangeAllocator {
+    free: Vec<Range<u32>>
+}
+impl RangeAllocator {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            free: std::iter::once(0..capacity).filter(|range| !range.is_empty()).collect()
+        }
+    }
+    /// Start of a free range of `len`, `None` when no free range is large enough
+    pub fn allocate(&mut self, len: u32) -> Option<u32> {
+        let i = self.free.iter().position(|range| range.len() as u32 >= len)?;
+        let offset = self.free[i].start;
+        self.free[i].start += len;
+        if self.free[i].is_empty() {
+            self.free.remove(i);
+        }
+        Some(offset)
+    }
+    pub fn release(&mut self, range: Range<u32>) {
+        if range.is_empty() { return }
+        let free = &mut self.free;
+        let i = free.partition_point(|v| v.start < range.start);
+        free.insert(i, range);
+        if i + 1 < free.len() && free[i].end == free[i + 1].start {
+            free[i].end = free.remove(i + 1).end
+        }
+        if i > 0 && free[i - 1].end == free[i].start {
+            free[i - 1].end = free.remove(i).end
+        }
+    }
+    /// Free ranges sorted by start
+    pub fn free(&self) -> &[Range<u32>] {
+        &self.free
+    }
+    pub fn available(&self) -> u32 {
+        self.free.iter().map(|range| range.len() as u32).sum()
+    }
+}
+
+/// Storage buffer shared by every `Animator`, each one writing its joint matrices into its own range
+pub struct SkinningPool {
+    pub buffer: Buffer,
+    ranges: Mutex<RangeAllocator>
+}
+impl SkinningPool {
+    pub fn new(device: &Device, capacity: u32) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Skinning pool"),
+                size: capacity as u64 * 64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            }),
+            ranges: Mutex::new(RangeAllocator::new(capacity))
+        }
+    }
+    /// `None` once the pool has no free range of `joints` left, the buffer is bound by the materials of the
+    /// animated objects so it never grows
+    pub fn allocate(self: &Arc<Self>, joints: u32) -> Option<SkinningAllocation> {
+        let offset = self.ranges.lock().unwrap().allocate(joints)?;
+        Some(SkinningAllocation {
+            pool: self.clone(),
+            offset,
+            joints
+        })
+    }
+    fn release(&self, range: Range<u32>) {
+        self.ranges.lock().unwrap().release(range)
+    }
+    pub fn available(&self) -> u32 {
+        self.ranges.lock().unwrap().available()
+    }
+}
+
+/// Range of joints owned by one animated object, released when dropped
+pub struct SkinningAllocation {
+    pool: Arc<SkinningPool>,
+    pub offset: u32,
+    pub joints: u32
+}
+impl SkinningAllocation {
+    pub fn write(&self, e: &Engine, joints: &[[[f32;4];4]]) {
+        assert!(joints.len() as u32 <= self.joints);
+        e.queue.write_buffer(&self.pool.buffer, self.offset as u64 * 64, bytemuck::cast_slice(joints))
+    }
+}
+impl Drop for SkinningAllocation {
+    fn drop(&mut self) {
+        self.pool.release(self.offset..self.offset + self.joints)
+    }
+}
+
+pub struct ComputeSkinning {
+    pipeline: ComputePipeline,
+    bgl: BindGroupLayout
+}
+impl ComputeSkinning {
+    pub fn new(device: &Device) -> Self {
+        let buffer = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compute skinning"),
+            entries: &[
+                buffer(0, wgpu::BufferBindingType::Uniform),
+                buffer(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer(2, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer(3, wgpu::BufferBindingType::Storage { read_only: false })
+            ]
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute skinning"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/skinning.wgsl").into())
+        });
+        Self {
+            pipeline: device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute skinning"),
+                layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Compute skinning"),
+                    bind_group_layouts: &[&bgl],
+                    push_constant_ranges: &[]
+                })),
+                module: &module,
+                entry_point: "cs_main"
+            }),
+            bgl
+        }
+    }
+}
+
+/// Mesh skinned once per frame by a compute pass, the skinned vertices are reused by every render pass.
+/// Opt-in, animated objects are skinned by their vertex shaders otherwise
+pub struct SkinnedMesh {
+    pub mesh: Mesh<pn::Vertex>,
+    bind_group: BindGroup,
+    workgroups: u32
+}
+impl SkinnedMesh {
+    pub fn skin(&self, e: &Engine, encoder: &mut CommandEncoder) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Compute skinning") });
+        pass.set_pipeline(&e.compute_skinning().pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(self.workgroups, 1, 1)
+    }
+}
+impl Engine {
+    /// Compute skinning pipeline, only built once the first `SkinnedMesh` is created
+    pub fn compute_skinning(&self) -> &ComputeSkinning {
+        self.compute_skinning.get_or_init(|| ComputeSkinning::new(&self.device))
+    }
+    pub fn skinned_mesh(&self, mesh: &Mesh<pnj::Vertex>, animator: &Animator) -> SkinnedMesh {
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Skinned mesh"),
+            size: mesh.vertices_len as u64 * std::mem::size_of::<pn::Vertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skinned mesh"),
+            layout: &self.compute_skinning().bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: animator.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: self.skinning_pool.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: mesh.vertices_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: output.as_entire_binding() }
+            ]
+        });
+        SkinnedMesh {
+            mesh: Mesh::new(output.into(), mesh.vertices_len, mesh.skeleton.clone(), mesh.bounds),
+            bind_group,
+            workgroups: mesh.vertices_len.div_ceil(64)
+        }
+    }
+}
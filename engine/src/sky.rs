@@ -0,0 +1,331 @@
+use std::f32::consts::PI;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, RenderPipeline, TextureView};
+use math::Vec3;
+
+use crate::{Engine, HDR_FORMAT, DEPTH_FORMAT};
+
+const PRIMARY_STEPS: usize = 16;
+const LIGHT_STEPS: usize = 8;
+
+/// Earth like atmosphere scattering the sun with Rayleigh and Mie single scattering, distances in meters
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    pub planet_radius: f32,
+    pub atmosphere_radius: f32,
+    /// Height of the observer above the planet surface
+    pub altitude: f32,
+    pub rayleigh: [f32;3],
+    pub rayleigh_height: f32,
+    pub mie: f32,
+    pub mie_height: f32,
+    /// Forward scattering of the Mie phase function, from -1 to 1
+    pub mie_anisotropy: f32,
+    pub sun_intensity: f32,
+    /// Angular radius of the sun disk in radians
+    pub sun_radius: f32
+}
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            planet_radius: 6371e3,
+            atmosphere_radius: 6471e3,
+            altitude: 100.,
+            rayleigh: [5.5e-6, 13e-6, 22.4e-6],
+            rayleigh_height: 8e3,
+            mie: 21e-6,
+            mie_height: 1.2e3,
+            mie_anisotropy: 0.758,
+            sun_intensity: 20.,
+            sun_radius: 0.02
+        }
+    }
+}
+impl Atmosphere {
+    /// Radiance seen along `direction` with the sun towards `sun`, as drawn by `Sky`
+    pub fn radiance(&self, direction: Vec3, sun: Vec3) -> Vec3 {
+        let (direction, sun) = (direction.normalized(), sun.normalized());
+        let rayleigh = Vec3::new(self.rayleigh[0], self.rayleigh[1], self.rayleigh[2]);
+        let extinction = |r: f32, m: f32| {
+            let v = rayleigh * r + Vec3::new(1., 1., 1.) * (self.mie * 1.1 * m);
+            Vec3::new((-v.x).exp(), (-v.y).exp(), (-v.z).exp())
+        };
+        let origin = Vec3::new(0., self.planet_radius + self.altitude, 0.);
+        let step = sphere_exit(origin, direction, self.atmosphere_radius) / PRIMARY_STEPS as f32;
+        let (mut optical_r, mut optical_m) = (0., 0.);
+        let (mut total_r, mut total_m) = (Vec3::default(), Vec3::default());
+        for i in 0..PRIMARY_STEPS {
+            let p = origin + direction * (step * (i as f32 + 0.5));
+            let height = p.length() - self.planet_radius;
+            let density_r = (-height / self.rayleigh_height).exp() * step;
+            let density_m = (-height / self.mie_height).exp() * step;
+            optical_r += density_r;
+            optical_m += density_m;
+
+            let b = p.dot(sun);
+            if b < 0. && b * b - p.dot(p) + self.planet_radius * self.planet_radius > 0. { continue }
+            let light_step = sphere_exit(p, sun, self.atmosphere_radius) / LIGHT_STEPS as f32;
+            let (mut light_r, mut light_m) = (0., 0.);
+            for j in 0..LIGHT_STEPS {
+                let h = (p + sun * (light_step * (j as f32 + 0.5))).length() - self.planet_radius;
+                light_r += (-h / self.rayleigh_height).exp() * light_step;
+                light_m += (-h / self.mie_height).exp() * light_step;
+            }
+            let attenuation = extinction(optical_r + light_r, optical_m + light_m);
+            total_r += attenuation * density_r;
+            total_m += attenuation * density_m
+        }
+        let mu = direction.dot(sun);
+        let g = self.mie_anisotropy;
+        let phase_r = 3. / (16. * PI) * (1. + mu * mu);
+        let phase_m = 3. / (8. * PI) * ((1. - g * g) * (1. + mu * mu)) / ((2. + g * g) * (1. + g * g - 2. * g * mu).powf(1.5));
+        let disk_cos = self.sun_radius.cos();
+        let t = ((mu - disk_cos) / ((1. - disk_cos) * 0.2)).clamp(0., 1.);
+        let disk = t * t * (3. - 2. * t);
+        (rayleigh.mul_element_wise(total_r) * phase_r + total_m * (phase_m * self.mie) + extinction(optical_r, optical_m) * disk)
+            * self.sun_intensity
+    }
+}
+
+impl Atmosphere {
+    /// Fraction of the light coming from `direction` that reaches the observer, 0 below the horizon of the planet
+    pub fn transmittance(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.normalized();
+        let origin = Vec3::new(0., self.planet_radius + self.altitude, 0.);
+        let b = origin.dot(direction);
+        if b < 0. && b * b - origin.dot(origin) + self.planet_radius * self.planet_radius > 0. { return Vec3::default() }
+        let step = sphere_exit(origin, direction, self.atmosphere_radius) / PRIMARY_STEPS as f32;
+        let (mut optical_r, mut optical_m) = (0., 0.);
+        for i in 0..PRIMARY_STEPS {
+            let height = (origin + direction * (step * (i as f32 + 0.5))).length() - self.planet_radius;
+            optical_r += (-height / self.rayleigh_height).exp() * step;
+            optical_m += (-height / self.mie_height).exp() * step;
+        }
+        let extinction = |rayleigh: f32| (-(rayleigh * optical_r + self.mie * 1.1 * optical_m)).exp();
+        Vec3::new(extinction(self.rayleigh[0]), extinction(self.rayleigh[1]), extinction(self.rayleigh[2]))
+    }
+}
+
+fn sphere_exit(origin: Vec3, direction: Vec3, radius: f32) -> f32 {
+    let b = origin.dot(direction);
+    let c = origin.dot(origin) - radius * radius;
+    -b + (b * b - c).max(0.).sqrt()
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyBinding {
+    /// Camera forward, and right and up scaled to the edges of the view
+    pub forward: [f32;4],
+    pub right: [f32;4],
+    pub up: [f32;4],
+    /// Direction to the sun and its intensity
+    pub sun: [f32;4],
+    /// Direction to the moon and its intensity
+    pub moon: [f32;4],
+    /// Rayleigh scattering coefficients and scale height
+    pub rayleigh: [f32;4],
+    /// Mie scattering coefficient, scale height, anisotropy and cosine of the sun disk radius
+    pub mie: [f32;4],
+    /// Planet radius, atmosphere radius and observer altitude
+    pub planet: [f32;4],
+    /// Intensity and 1 when the cubemap replaces the atmosphere
+    pub params: [f32;4]
+}
+
+/// Background of the main pass, a cubemap or the procedural `Atmosphere` lit by the sun and the moon.
+/// It is drawn after the opaque surfaces where the depth is still cleared
+pub struct Sky {
+    pub atmosphere: Atmosphere,
+    pub intensity: f32,
+    /// Direction sunlight travels to, usually `DirectionalLight::world_direction`
+    pub sun_direction: Vec3,
+    pub moon_direction: Vec3,
+    /// Moonlight relative to `Atmosphere::sun_intensity`, 0 hides the moon
+    pub moon_intensity: f32,
+    cubemap: bool,
+    buffer: Buffer,
+    bgl: BindGroupLayout,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline
+}
+impl Sky {
+    pub fn procedural(e: &Engine, atmosphere: Atmosphere) -> Self {
+        let mut sky = Self::new(e, Self::cube_view(e, 1, &[&[0, 0, 0, 255][..]; 6]), false);
+        sky.atmosphere = atmosphere;
+        sky
+    }
+    /// Skybox from its +X, -X, +Y, -Y, +Z and -Z faces
+    pub fn cubemap(e: &Engine, faces: [compiler::Image;6]) -> Self {
+        let size = faces[0].width;
+        assert!(faces.iter().all(|v| (v.width, v.height) == (size, size)), "Cubemap faces are squares of the same size");
+        let pixels = faces.map(|v| v.get_pixels_rgba());
+        let pixels = pixels.iter().map(|v| v.as_slice()).collect::<Vec<_>>();
+        Self::new(e, Self::cube_view(e, size, &pixels), true)
+    }
+    fn new(e: &Engine, cubemap: TextureView, is_cubemap: bool) -> Self {
+        let buffer = e.new_buffer(bytemuck::bytes_of(&SkyBinding::default()), BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        let sampler = e.device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bgl = Self::bgl(&e.device);
+        let bind_group = e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sky"),
+            layout: &bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&cubemap) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&sampler) }
+            ]
+        });
+        Self {
+            atmosphere: Default::default(),
+            intensity: 1.,
+            sun_direction: Vec3::new(0., -1., 0.),
+            moon_direction: Vec3::new(0., 1., 0.),
+            moon_intensity: 0.,
+            cubemap: is_cubemap,
+            buffer,
+            pipeline: Self::pipeline(e, &bgl),
+            bgl,
+            bind_group
+        }
+    }
+    fn cube_view(e: &Engine, size: u32, faces: &[&[u8]]) -> TextureView {
+        let texture = e.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Sky"),
+            size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 6 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        for (layer, pixels) in faces.iter().enumerate() {
+            e.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                    aspect: wgpu::TextureAspect::All
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size),
+                    rows_per_image: Some(size)
+                },
+                wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 }
+            )
+        }
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        })
+    }
+    pub fn bgl(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sky"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true }
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        })
+    }
+    /// Pipeline for the current `Engine::sample_count`
+    fn pipeline(e: &Engine, bgl: &BindGroupLayout) -> RenderPipeline {
+        let shader = e.shader_module("Sky", include_str!("shaders/sky.wgsl"), &[]);
+        let layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky"),
+            bind_group_layouts: &[&e.camera_buffer.bgl, bgl],
+            push_constant_ranges: &[]
+        });
+        e.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sky"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: Default::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: e.sample_count(),
+                ..Default::default()
+            },
+            multiview: None
+        })
+    }
+    /// Recreates the pipeline after `Engine::set_sample_count`
+    pub fn settings_changed(&mut self, e: &Engine) {
+        self.pipeline = Self::pipeline(e, &self.bgl)
+    }
+    /// Uploads the view of `Engine::camera` with the sun and moon, once per frame before rendering
+    pub fn update(&self, e: &Engine) {
+        let camera = e.camera();
+        let forward = camera.forward();
+        let right = forward.cross(Vec3::new(0., 1., 0.)).normalized();
+        let up = right.cross(forward);
+        let tan = (camera.fov / 2.).tan();
+        let a = &self.atmosphere;
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&SkyBinding {
+            forward: forward.extend(0.).into(),
+            right: (right * (tan * camera.aspect)).extend(0.).into(),
+            up: (up * tan).extend(0.).into(),
+            sun: (self.sun_direction.normalized() * -1.).extend(a.sun_intensity).into(),
+            moon: (self.moon_direction.normalized() * -1.).extend(a.sun_intensity * self.moon_intensity).into(),
+            rayleigh: [a.rayleigh[0], a.rayleigh[1], a.rayleigh[2], a.rayleigh_height],
+            mie: [a.mie, a.mie_height, a.mie_anisotropy, a.sun_radius.cos()],
+            planet: [a.planet_radius, a.atmosphere_radius, a.altitude, 0.],
+            params: [self.intensity, if self.cubemap { 1. } else { 0. }, 0., 0.]
+        }))
+    }
+    /// Draws the sky in a pass with the camera bound at group 0 and the scene depth, after the opaque surfaces
+    pub fn render<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1)
+    }
+}
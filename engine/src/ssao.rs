@@ -0,0 +1,224 @@
+use wgpu::{BindGroupLayout, Buffer, BufferUsages, RenderPipeline, TextureFormat, TextureView};
+
+use crate::{Engine, Camera, RenderGraph, TextureHandle, TextureDesc, TextureSize, PassContext, AmbientOcclusion, DEPTH_FORMAT};
+
+/// World space normals written by the pre-pass
+pub const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// Ambient occlusion sampled by `engine/lighting`, 1 where nothing occludes the environment
+pub const OCCLUSION_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SsaoSettings {
+    /// World distance around a surface searched for occluders
+    pub radius: f32,
+    /// Exponent of the unoccluded fraction, higher darkens creases more
+    pub intensity: f32,
+    /// Depth difference below which a sample does not occlude, hides the self occlusion of flat surfaces
+    pub bias: f32
+}
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            intensity: 1.5,
+            bias: 0.02
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsaoBinding {
+    pub view_projection: [[f32;4];4],
+    pub inverse_view_projection: [[f32;4];4],
+    pub camera: [f32;4],
+    /// Radius, intensity, bias and sample count
+    pub params: [f32;4]
+}
+impl SsaoBinding {
+    pub fn new(camera: &Camera, settings: &SsaoSettings, quality: AmbientOcclusion) -> Self {
+        let view_projection = camera.projection() * camera.view();
+        Self {
+            view_projection: view_projection.into(),
+            inverse_view_projection: view_projection.inverted().unwrap_or_default().into(),
+            camera: camera.position.extend(1.).into(),
+            params: [settings.radius, settings.intensity, settings.bias, quality.samples() as f32]
+        }
+    }
+}
+
+/// Depth and normal targets of the pre-pass, see `Ssao::prepass`
+#[derive(Clone, Copy, Debug)]
+pub struct Prepass {
+    pub normals: TextureHandle,
+    pub depth: TextureHandle
+}
+
+/// Screen space ambient occlusion: the opaque surfaces are drawn first into single sampled depth and normal targets,
+/// hemispheres around each pixel are tested against that depth and the result is blurred without crossing edges.
+/// The quality comes from `GraphicsSettings::ambient_occlusion` every frame
+pub struct Ssao {
+    pub settings: SsaoSettings,
+    buffer: Buffer,
+    bgl: BindGroupLayout,
+    /// White texture standing for the occlusion when it is off
+    unoccluded: TextureView,
+    occlusion: RenderPipeline,
+    blur_x: RenderPipeline,
+    blur_y: RenderPipeline
+}
+impl Ssao {
+    pub fn new(e: &Engine) -> Self {
+        let texture = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type
+            },
+            count: None
+        };
+        let bgl = e.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("SSAO"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                texture(1, wgpu::TextureSampleType::Float { filterable: false }),
+                texture(2, wgpu::TextureSampleType::Float { filterable: false }),
+                texture(3, wgpu::TextureSampleType::Float { filterable: false })
+            ]
+        });
+        let shader = e.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SSAO"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/ssao.wgsl").into())
+        });
+        let layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SSAO"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[]
+        });
+        let pipeline = |entry_point| e.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: OCCLUSION_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None
+        });
+
+        let unoccluded = e.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Unoccluded"),
+            size: wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OCCLUSION_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        e.queue.write_texture(
+            unoccluded.as_image_copy(),
+            &[255],
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: None, rows_per_image: None },
+            wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 }
+        );
+
+        let settings = SsaoSettings::default();
+        Self {
+            buffer: e.new_buffer(
+                bytemuck::bytes_of(&SsaoBinding::new(&e.camera(), &settings, AmbientOcclusion::Off)),
+                BufferUsages::UNIFORM | BufferUsages::COPY_DST
+            ),
+            settings,
+            bgl,
+            unoccluded: unoccluded.create_view(&Default::default()),
+            occlusion: pipeline("fs_occlusion"),
+            blur_x: pipeline("fs_blur_x"),
+            blur_y: pipeline("fs_blur_y")
+        }
+    }
+    /// Targets of the depth and normal pre-pass at `scale` of the surface. The pass writing them draws the opaque
+    /// surfaces with single sampled pipelines whose only color target is `NORMAL_FORMAT`
+    pub fn prepass(graph: &mut RenderGraph, scale: f32) -> Prepass {
+        Prepass {
+            normals: graph.create("prepass normals", TextureDesc::new(NORMAL_FORMAT).scaled(scale)),
+            depth: graph.create("prepass depth", TextureDesc::new(DEPTH_FORMAT).scaled(scale))
+        }
+    }
+    /// Adds the passes computing the occlusion of the camera view from `prepass` and returns the texture
+    /// to bind with `Environment::bind_group`. When the occlusion is off it is a white texture and the pre-pass,
+    /// read by nothing else, is skipped by the graph
+    pub fn add_passes<'a>(&'a self, e: &'a Engine, graph: &mut RenderGraph<'a>, prepass: Prepass) -> TextureHandle {
+        let quality = e.graphics_settings().ambient_occlusion;
+        if quality == AmbientOcclusion::Off {
+            return graph.import("unoccluded", &self.unoccluded)
+        }
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&SsaoBinding::new(&e.camera(), &self.settings, quality)));
+        let scale = match graph.desc(prepass.depth).map(|v| v.size) {
+            Some(TextureSize::Surface(scale)) => scale,
+            _ => 1.
+        };
+        let desc = TextureDesc::new(OCCLUSION_FORMAT).scaled(scale * quality.scale());
+        let Prepass { normals, depth } = prepass;
+        let occlusion = graph.create("occlusion", desc);
+        graph.add_pass("ssao")
+            .read(depth)
+            .read(normals)
+            .write(occlusion)
+            .execute(move |ctx| self.draw(e, ctx, &self.occlusion, occlusion, [depth, normals, normals]));
+        let blurred_x = graph.create("occlusion blurred", desc);
+        graph.add_pass("ssao blur x")
+            .read(depth)
+            .read(occlusion)
+            .write(blurred_x)
+            .execute(move |ctx| self.draw(e, ctx, &self.blur_x, blurred_x, [depth, normals, occlusion]));
+        let blurred = graph.create("occlusion blurred", desc);
+        graph.add_pass("ssao blur y")
+            .read(depth)
+            .read(blurred_x)
+            .write(blurred)
+            .execute(move |ctx| self.draw(e, ctx, &self.blur_y, blurred, [depth, normals, blurred_x]));
+        blurred
+    }
+    /// Draws `pipeline` into `target` with the depth, normal and occlusion textures of `sources`
+    fn draw(&self, e: &Engine, ctx: &mut PassContext, pipeline: &RenderPipeline, target: TextureHandle, sources: [TextureHandle;3]) {
+        let [depth, normals, occlusion] = sources;
+        let bind_group = e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("SSAO"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: self.buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(ctx.view(depth)) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(ctx.view(normals)) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::TextureView(ctx.view(occlusion)) }
+            ]
+        });
+        let mut render_pass = ctx.render_pass(&[target], None);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1)
+    }
+}
@@ -0,0 +1,438 @@
+use std::{path::Path, sync::Arc};
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, TextureFormat};
+use math::{Aabb, Frustum, Vec3};
+
+use crate::{Engine, Mesh, Material, Shader, Bvh, decode, vertex::pnu::Vertex};
+
+/// Layers blended by a terrain, at most one per channel of the splat map
+pub const TERRAIN_LAYERS: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainSettings {
+    /// World distance between two samples of the heightmap
+    pub spacing: f32,
+    /// Quads along each side of a chunk at the finest LOD, a power of two
+    pub chunk_size: u32,
+    /// Levels of detail, each one with half the quads of the previous along each side
+    pub lods: u32,
+    /// Distance from the camera up to which chunks use the finest LOD, doubled for each coarser one
+    pub lod_distance: f32,
+    /// Chunks further from the camera are not loaded
+    pub stream_distance: f32,
+    /// Chunk meshes built by each `Terrain::update`, spreading the loading of a zone over several frames
+    pub builds_per_update: usize
+}
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            spacing: 1.,
+            chunk_size: 32,
+            lods: 4,
+            lod_distance: 32.,
+            stream_distance: 512.,
+            builds_per_update: 8
+        }
+    }
+}
+impl TerrainSettings {
+    /// The finest LOD up to `lod_distance`, one coarser each time the distance doubles
+    pub fn lod(&self, distance: f32) -> u32 {
+        let distance = distance / self.lod_distance;
+        if distance <= 1. { return 0 }
+        (distance.log2() as u32 + 1).min(self.lods - 1)
+    }
+}
+
+impl Engine {
+    /// Heightmap compiled from a grayscale image of a folder with `heightmap` set in its settings
+    pub fn load_heightmap(&self, path: impl AsRef<Path>) -> compiler::Heightmap {
+        decode(path)
+    }
+}
+
+/// Heights of a terrain in world space, cheap to clone into the scripts querying them
+#[derive(Clone)]
+pub struct Heightfield {
+    pub heightmap: Arc<compiler::Heightmap>,
+    /// World position of the first sample
+    pub origin: Vec3,
+    /// World distance between two samples
+    pub spacing: f32
+}
+impl Heightfield {
+    pub fn new(heightmap: compiler::Heightmap, origin: Vec3, spacing: f32) -> Self {
+        assert!(heightmap.width > 1 && heightmap.depth > 1, "Heightfield of less than 2x2 samples");
+        Self {
+            heightmap: heightmap.into(),
+            origin,
+            spacing
+        }
+    }
+    pub fn bounds(&self) -> Aabb {
+        let heightmap = &self.heightmap;
+        Aabb::new(
+            self.origin + Vec3::new(0., heightmap.min_height, 0.),
+            self.origin + Vec3::new(
+                (heightmap.width - 1) as f32 * self.spacing,
+                heightmap.max_height,
+                (heightmap.depth - 1) as f32 * self.spacing
+            )
+        )
+    }
+    /// World height of the sample at `x` and `z`, clamped to the edges
+    pub fn sample(&self, x: u32, z: u32) -> f32 {
+        self.origin.y + self.heightmap.height(x, z)
+    }
+    /// Normal at the sample from the slopes to its neighbours
+    pub fn sample_normal(&self, x: u32, z: u32) -> Vec3 {
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.heightmap.width - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(self.heightmap.depth - 1));
+        let dx = (self.sample(right, z) - self.sample(left, z)) / ((right - left) as f32 * self.spacing);
+        let dz = (self.sample(x, front) - self.sample(x, back)) / ((front - back) as f32 * self.spacing);
+        Vec3::new(-dx, 1., -dz).normalized()
+    }
+    /// Height and slopes along x and z of the triangle under `x` and `z`, quads are split along their diagonal
+    /// from the first sample like the chunk meshes
+    fn triangle(&self, x: f32, z: f32) -> Option<(f32, f32, f32)> {
+        let (gx, gz) = ((x - self.origin.x) / self.spacing, (z - self.origin.z) / self.spacing);
+        let (last_x, last_z) = ((self.heightmap.width - 1) as f32, (self.heightmap.depth - 1) as f32);
+        if !(0. ..=last_x).contains(&gx) || !(0. ..=last_z).contains(&gz) { return None }
+        let (x0, z0) = (gx.floor().min(last_x - 1.) as u32, gz.floor().min(last_z - 1.) as u32);
+        let (fx, fz) = (gx - x0 as f32, gz - z0 as f32);
+        let (h00, h11) = (self.sample(x0, z0), self.sample(x0 + 1, z0 + 1));
+        let (dx, dz) = if fx >= fz {
+            let h10 = self.sample(x0 + 1, z0);
+            (h10 - h00, h11 - h10)
+        } else {
+            let h01 = self.sample(x0, z0 + 1);
+            (h11 - h01, h01 - h00)
+        };
+        Some((h00 + dx * fx + dz * fz, dx / self.spacing, dz / self.spacing))
+    }
+    /// World height of the ground at `x` and `z`, `None` outside of the terrain
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.triangle(x, z).map(|(height, _, _)| height)
+    }
+    /// Normal of the triangle under `x` and `z`
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        self.triangle(x, z).map(|(_, dx, dz)| Vec3::new(-dx, 1., -dz).normalized())
+    }
+    /// Distance along `direction` at which a ray from `origin` hits the ground, marched by half a sample
+    /// and refined by bisection. The march stops where the ray leaves the bounds of the terrain
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let direction = direction.normalized();
+        let above = |t: f32| {
+            let p = origin + direction * t;
+            self.height_at(p.x, p.z).map(|height| p.y - height)
+        };
+        let (start, exit) = self.bounds().ray_span(origin, direction)?;
+        let end = max_distance.min(exit);
+        let (mut previous, mut t) = (start, start);
+        while t <= end {
+            if above(t).is_some_and(|v| v <= 0.) {
+                let (mut low, mut high) = (previous, t);
+                for _ in 0..16 {
+                    let middle = (low + high) * 0.5;
+                    if above(middle).is_some_and(|v| v > 0.) { low = middle } else { high = middle }
+                }
+                return Some(high)
+            }
+            if t == end { break }
+            previous = t;
+            t = (t + self.spacing * 0.5).min(end)
+        }
+        None
+    }
+    /// Mesh of the chunk of `size` quads at `chunk` along x and z, at the LOD `lods[0]` with its left, right,
+    /// back and front edges stitched to the following ones
+    pub fn chunk_mesh(&self, size: u32, chunk: [u32;2], lods: [u32;5]) -> compiler::Mesh {
+        let (step, quads) = (1 << lods[0], size >> lods[0]);
+        let (start_x, start_z) = (chunk[0] * size, chunk[1] * size);
+        let (last_x, last_z) = (self.heightmap.width - 1, self.heightmap.depth - 1);
+        let mut mesh = compiler::Mesh::default();
+        for j in 0..=quads {
+            for i in 0..=quads {
+                let (x, z) = ((start_x + i * step).min(last_x), (start_z + j * step).min(last_z));
+                // Edge vertices between two of a coarser neighbour are moved onto the line joining them,
+                // the corners are on every LOD
+                let edge = match (i, j) {
+                    (0, _) => Some((lods[1], z, last_z)),
+                    (i, _) if i == quads => Some((lods[2], z, last_z)),
+                    (_, 0) => Some((lods[3], x, last_x)),
+                    (_, j) if j == quads => Some((lods[4], x, last_x)),
+                    _ => None
+                };
+                let height = match edge {
+                    Some((lod, along, last)) if lod > lods[0] && along % (1 << lod) != 0 => {
+                        let a = along >> lod << lod;
+                        let b = (a + (1 << lod)).min(last);
+                        let sample = |v| if i == 0 || i == quads { self.sample(x, v) } else { self.sample(v, z) };
+                        let (ha, hb) = (sample(a), sample(b));
+                        ha + (hb - ha) * (along - a) as f32 / (b - a) as f32
+                    }
+                    _ => self.sample(x, z)
+                };
+                let origin = self.origin;
+                mesh.positions.push([origin.x + x as f32 * self.spacing, height, origin.z + z as f32 * self.spacing]);
+                mesh.normals.push(self.sample_normal(x, z).into());
+                mesh.uvs.push([x as f32 / last_x as f32, z as f32 / last_z as f32])
+            }
+        }
+        let row = quads + 1;
+        for j in 0..quads {
+            for i in 0..quads {
+                let v = i + j * row;
+                mesh.indices.extend([v, v + row, v + row + 1, v, v + row + 1, v + 1])
+            }
+        }
+        mesh
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TerrainFactors {
+    /// Roughness of each layer
+    pub roughness: [f32;4],
+    /// World size covered by the layer textures before they repeat
+    pub layer_size: f32,
+    pub layers: u32,
+    pub _padding: [u32;2]
+}
+
+/// Splat map stretched over the whole terrain whose red, green, blue and alpha channels weigh the base colors
+/// of the layers of a texture array, repeated every `layer_size`
+pub struct TerrainMaterial {
+    pub factors: TerrainFactors,
+    buffer: Buffer,
+    bind_group: BindGroup
+}
+impl TerrainMaterial {
+    /// `layers` are base colors of the same size, the splat weights are normalized so any sum of them is fine
+    pub fn new(e: &Engine, splat: compiler::Image, layers: Vec<compiler::Image>, roughness: [f32;4], layer_size: f32) -> Self {
+        assert!((1..=TERRAIN_LAYERS).contains(&layers.len()), "Terrain with {} layers", layers.len());
+        let factors = TerrainFactors {
+            roughness,
+            layer_size,
+            layers: layers.len() as u32,
+            _padding: Default::default()
+        };
+        let splat = e.texture_from_image(splat, TextureFormat::Rgba8Unorm);
+        let layers = e.texture_array_from_images(layers, TextureFormat::Rgba8UnormSrgb);
+        let sampler = |address_mode| e.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let (layer_sampler, splat_sampler) = (sampler(wgpu::AddressMode::Repeat), sampler(wgpu::AddressMode::ClampToEdge));
+        let buffer = e.new_buffer(bytemuck::bytes_of(&factors), BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        Self {
+            factors,
+            bind_group: e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Terrain material"),
+                layout: &Self::bgl(&e.device),
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&splat.view) },
+                    wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&layers.view) },
+                    wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(&layer_sampler) },
+                    wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::Sampler(&splat_sampler) }
+                ]
+            }),
+            buffer
+        }
+    }
+    /// Factors uniform, splat map, layers array, and the repeating and clamping samplers
+    pub fn bgl(device: &Device) -> BindGroupLayout {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true }
+            },
+            count: None
+        };
+        let sampler = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Terrain material"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                texture(1, wgpu::TextureViewDimension::D2),
+                texture(2, wgpu::TextureViewDimension::D2Array),
+                sampler(3),
+                sampler(4)
+            ]
+        })
+    }
+    pub fn set_factors(&mut self, e: &Engine, factors: TerrainFactors) {
+        self.factors = factors;
+        e.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&factors))
+    }
+}
+impl Material for TerrainMaterial {
+    const BGI: u32 = 1;
+    fn bind_group(&self) -> &BindGroup { &self.bind_group }
+}
+
+struct TerrainChunk {
+    /// LOD of the mesh followed by those its left, right, back and front edges are stitched to
+    lods: [u32;5],
+    mesh: Mesh<Vertex>
+}
+
+/// Ground of a heightmap cut in square chunks. The chunks around the camera are loaded with a LOD decreasing
+/// with the distance, their edges snapped onto those of coarser neighbours so no cracks open between them,
+/// and unloaded once the camera is away
+pub struct Terrain {
+    pub settings: TerrainSettings,
+    pub heightfield: Heightfield,
+    pub material: TerrainMaterial,
+    /// Chunks along x and z
+    chunks: [u32;2],
+    /// Bounds of every chunk, loaded or not
+    bvh: Bvh,
+    /// Chunks in rows along z, x varying fastest
+    loaded: Vec<Option<TerrainChunk>>
+}
+impl Terrain {
+    /// Heightmaps of a multiple of `chunk_size` quads along each side fill their last chunks, the others
+    /// end in narrower ones
+    pub fn new(heightfield: Heightfield, material: TerrainMaterial, settings: TerrainSettings) -> Self {
+        assert!(settings.lods > 0 && settings.chunk_size.is_power_of_two() && settings.chunk_size >= 1 << (settings.lods - 1),
+            "Terrain chunks of {} quads with {} LODs", settings.chunk_size, settings.lods);
+        let heightmap = &heightfield.heightmap;
+        let size = settings.chunk_size;
+        let chunks = [(heightmap.width - 1).div_ceil(size), (heightmap.depth - 1).div_ceil(size)];
+        let bounds = (0..chunks[0] * chunks[1])
+            .map(|i| {
+                let (x, z) = (i % chunks[0] * size, i / chunks[0] * size);
+                let (end_x, end_z) = ((x + size).min(heightmap.width - 1), (z + size).min(heightmap.depth - 1));
+                let (mut min, mut max) = (f32::INFINITY, f32::NEG_INFINITY);
+                for z in z..=end_z {
+                    for x in x..=end_x {
+                        let height = heightfield.sample(x, z);
+                        min = min.min(height);
+                        max = max.max(height)
+                    }
+                }
+                let position = |x: u32, height: f32, z: u32| heightfield.origin.with_y(0.) +
+                    Vec3::new(x as f32 * heightfield.spacing, height, z as f32 * heightfield.spacing);
+                Aabb::new(position(x, min, z), position(end_x, max, end_z))
+            })
+            .collect::<Vec<_>>();
+        Self {
+            settings,
+            material,
+            loaded: bounds.iter().map(|_| None).collect(),
+            bvh: Bvh::new(bounds),
+            heightfield,
+            chunks
+        }
+    }
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.heightfield.height_at(x, z)
+    }
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        self.heightfield.normal_at(x, z)
+    }
+    /// Number of chunks with a mesh
+    pub fn loaded(&self) -> usize {
+        self.loaded.iter().filter(|v| v.is_some()).count()
+    }
+    /// LOD of the mesh of a chunk, `None` when it is not loaded
+    pub fn chunk_lod(&self, chunk: usize) -> Option<u32> {
+        self.loaded[chunk].as_ref().map(|v| v.lods[0])
+    }
+    fn distance(&self, chunk: usize, eye: Vec3) -> f32 {
+        let bounds = self.bvh.bounds(chunk);
+        eye.max_element_wise(bounds.min).min_element_wise(bounds.max).distance(eye)
+    }
+    /// LOD of a chunk seen from `eye`
+    pub fn lod(&self, chunk: usize, eye: Vec3) -> u32 {
+        self.settings.lod(self.distance(chunk, eye))
+    }
+    /// Loads the chunks within `stream_distance` of `eye` and rebuilds those whose LOD or whose coarser neighbours
+    /// changed, the nearest first. Chunks are kept up to a chunk further, not to reload them back and forth at the limit
+    pub fn update(&mut self, e: &Engine, eye: Vec3) {
+        let range = self.settings.stream_distance;
+        let chunk_size = self.settings.chunk_size as f32 * self.heightfield.spacing;
+        for chunk in 0..self.loaded.len() {
+            if self.loaded[chunk].is_some() && self.distance(chunk, eye) > range + chunk_size {
+                self.loaded[chunk] = None
+            }
+        }
+        let [chunks_x, chunks_z] = self.chunks;
+        let (x, z) = ((eye.x - self.heightfield.origin.x) / chunk_size, (eye.z - self.heightfield.origin.z) / chunk_size);
+        let cells = (range / chunk_size).ceil() + 1.;
+        let span = |center: f32, chunks: u32| {
+            let clamp = |v: f32| v.clamp(0., (chunks - 1) as f32) as u32;
+            clamp(center - cells)..=clamp(center + cells)
+        };
+        let mut builds = Vec::new();
+        for cz in span(z, chunks_z) {
+            for cx in span(x, chunks_x) {
+                let chunk = (cx + cz * chunks_x) as usize;
+                let distance = self.distance(chunk, eye);
+                if distance > range { continue }
+                let lod = self.lod(chunk, eye);
+                // Finer neighbours snap onto this chunk, its mesh does not depend on them
+                let neighbour = |dx: i32, dz: i32| {
+                    let (nx, nz) = (cx as i32 + dx, cz as i32 + dz);
+                    if nx < 0 || nz < 0 || nx >= chunks_x as i32 || nz >= chunks_z as i32 { return lod }
+                    self.lod((nx + nz * chunks_x as i32) as usize, eye).max(lod)
+                };
+                let lods = [lod, neighbour(-1, 0), neighbour(1, 0), neighbour(0, -1), neighbour(0, 1)];
+                if self.loaded[chunk].as_ref().map(|v| v.lods) != Some(lods) {
+                    builds.push((distance, chunk, lods))
+                }
+            }
+        }
+        builds.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, chunk, lods) in builds.into_iter().take(self.settings.builds_per_update) {
+            self.loaded[chunk] = Some(TerrainChunk { lods, mesh: e.create_mesh(&self.chunk_mesh(chunk, lods)) })
+        }
+    }
+    /// Mesh of a chunk at the LOD `lods[0]` with its left, right, back and front edges stitched to the following ones
+    pub fn chunk_mesh(&self, chunk: usize, lods: [u32;5]) -> compiler::Mesh {
+        let chunk = chunk as u32;
+        self.heightfield.chunk_mesh(self.settings.chunk_size, [chunk % self.chunks[0], chunk / self.chunks[0]], lods)
+    }
+    /// Sorted indices of the loaded chunks intersecting at least one of `frustums`
+    pub fn cull(&self, frustums: &[Frustum]) -> Vec<usize> {
+        let mut visible = self.bvh.cull(frustums);
+        visible.retain(|chunk| self.loaded[*chunk].is_some());
+        visible
+    }
+}
+
+pub trait TerrainRenderer: Shader<Material = TerrainMaterial, Vertex = Vertex> {
+    /// Draws the `chunks` of `terrain` returned by `Terrain::cull`
+    fn render_terrain<'r, 's: 'r>(&'s self, render_pass: &mut wgpu::RenderPass<'r>, terrain: &'s Terrain, chunks: &[usize]) where Self: Sized {
+        render_pass.set_pipeline(self.pipeline());
+        terrain.material.set(render_pass);
+        for chunk in chunks.iter().filter_map(|chunk| terrain.loaded[*chunk].as_ref()) {
+            render_pass.set_vertex_buffer(0, chunk.mesh.vertices_buffer.slice(..));
+            render_pass.draw(0..chunk.mesh.vertices_len, 0..1)
+        }
+    }
+}
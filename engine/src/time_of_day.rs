@@ -0,0 +1,180 @@
+use std::f32::consts::PI;
+use math::{Vec3, Quaternion};
+
+use crate::{Engine, DirectionalLight, Sky, Atmosphere};
+
+pub const SECONDS_PER_DAY: f64 = 86400.;
+
+/// In-game time of the world, advanced by the engine every frame. A client keeps it in step with the server
+/// through `Engine::synchronize_game_time`, small differences are absorbed smoothly instead of jumping
+#[derive(Clone, Copy, Debug)]
+pub struct GameClock {
+    /// In-game seconds since the world started
+    time: f64,
+    /// In-game seconds per real second
+    pub time_scale: f64,
+    pub paused: bool,
+    /// Difference with the server left to absorb
+    correction: f64,
+    /// Real seconds left to absorb it in
+    correction_time: f64
+}
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            time: 12. * 3600.,
+            // A day lasts 24 real minutes
+            time_scale: 60.,
+            paused: false,
+            correction: 0.,
+            correction_time: 0.
+        }
+    }
+}
+impl GameClock {
+    /// Real seconds a correction is absorbed over, at a constant rate
+    pub const SYNC_SECONDS: f64 = 2.;
+    /// Differences above it, in in-game seconds, are applied at once
+    pub const SNAP_SECONDS: f64 = 3600.;
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    /// Days elapsed since the world started
+    pub fn day(&self) -> u64 {
+        (self.time / SECONDS_PER_DAY).floor().max(0.) as u64
+    }
+    /// Hours since midnight, from 0 to 24
+    pub fn hours(&self) -> f32 {
+        (self.time.rem_euclid(SECONDS_PER_DAY) / 3600.) as f32
+    }
+    /// Jumps to `time` without smoothing
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+        self.correction = 0.;
+        self.correction_time = 0.
+    }
+    /// Moves towards `time`, the server time sent `latency` real seconds ago
+    pub fn synchronize(&mut self, time: f64, latency: f64) {
+        let time = time + if self.paused { 0. } else { latency * self.time_scale };
+        let difference = time - self.time;
+        if difference.abs() > Self::SNAP_SECONDS {
+            self.set_time(time)
+        } else {
+            self.correction = difference;
+            self.correction_time = Self::SYNC_SECONDS
+        }
+    }
+    /// Advances by `delta` real seconds
+    pub fn advance(&mut self, delta: f64) {
+        if !self.paused {
+            self.time += delta * self.time_scale
+        }
+        if self.correction_time > 0. {
+            let absorbed = self.correction * (delta / self.correction_time).min(1.);
+            self.time += absorbed;
+            self.correction -= absorbed;
+            self.correction_time -= delta
+        }
+    }
+}
+
+/// Sun and moon of a `GameClock` hour, with the light they give
+#[derive(Clone, Copy, Debug)]
+pub struct DayLighting {
+    /// Directions sunlight and moonlight travel to
+    pub sun_direction: Vec3,
+    pub moon_direction: Vec3,
+    /// The directional light follows the sun by day and the moon by night
+    pub light_direction: Vec3,
+    pub light_color: [f32;3],
+    pub light_intensity: f32,
+    /// Scale of the environment lighting, from `TimeOfDay::night_ambient` to 1
+    pub ambient: f32
+}
+
+/// Path of the sun and moon over a day, the sun rises at 6 and sets at 18
+#[derive(Clone, Copy, Debug)]
+pub struct TimeOfDay {
+    /// Angle of the sun at noon from the zenith, in radians
+    pub tilt: f32,
+    /// Rotation around the vertical axis of the sunrise direction, +X when 0
+    pub azimuth: f32,
+    pub sun_intensity: f32,
+    pub moon_color: [f32;3],
+    pub moon_intensity: f32,
+    /// Brightness of the moonlit sky relative to daylight
+    pub moonlit_sky: f32,
+    pub night_ambient: f32
+}
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            tilt: 0.5,
+            azimuth: 0.,
+            sun_intensity: 1.,
+            moon_color: [0.6, 0.7, 1.],
+            moon_intensity: 0.1,
+            moonlit_sky: 0.02,
+            night_ambient: 0.05
+        }
+    }
+}
+impl TimeOfDay {
+    pub fn sun_direction(&self, hours: f32) -> Vec3 {
+        let angle = (hours - 6.) / 24. * 2. * PI;
+        let (sin, cos) = angle.sin_cos();
+        let to_sun = Vec3::new(cos, sin * self.tilt.cos(), sin * self.tilt.sin()).rotate_y(self.azimuth);
+        to_sun * -1.
+    }
+    /// Lighting at `hours`, the sun color is the sunlight left after crossing `atmosphere`
+    pub fn lighting(&self, hours: f32, atmosphere: &Atmosphere) -> DayLighting {
+        let sun_direction = self.sun_direction(hours);
+        let moon_direction = sun_direction * -1.;
+        let sun_height = -sun_direction.y;
+        // Both lights are off when the sun is just below the horizon, where the directional light switches
+        let day = ((sun_height + 0.05) / 0.15).clamp(0., 1.);
+        let night = ((-sun_height - 0.05) / 0.15).clamp(0., 1.);
+        let (light_direction, light_color, light_intensity) = if day >= night {
+            let color = atmosphere.transmittance(sun_direction * -1.);
+            // Near the horizon the transmittance stays reddish while the fade dims it
+            let color = color * (1. / color.x.max(color.y).max(color.z).max(0.0001));
+            (sun_direction, color.into(), self.sun_intensity * day)
+        } else {
+            (moon_direction, self.moon_color, self.moon_intensity * night)
+        };
+        DayLighting {
+            sun_direction,
+            moon_direction,
+            light_direction,
+            light_color,
+            light_intensity,
+            ambient: self.night_ambient + (1. - self.night_ambient) * day
+        }
+    }
+    /// Moves `light` and the bodies of `sky` to the current `Engine::game_clock` hour,
+    /// before `DirectionalLight::update` and `Sky::update`. Returns the lighting for the environment and fog
+    pub fn apply(&self, e: &Engine, light: &mut DirectionalLight, sky: &mut Sky) -> DayLighting {
+        let lighting = self.lighting(e.game_clock().hours(), &sky.atmosphere);
+        light.direction = Quaternion::from_to(Vec3::new(0., 0., 1.), lighting.light_direction);
+        light.color = lighting.light_color;
+        light.intensity = lighting.light_intensity;
+        sky.sun_direction = lighting.sun_direction;
+        sky.moon_direction = lighting.moon_direction;
+        sky.moon_intensity = self.moonlit_sky;
+        lighting
+    }
+}
+
+impl Engine {
+    pub fn game_clock(&self) -> GameClock {
+        *self.game_clock.lock().unwrap()
+    }
+    pub fn set_game_clock(&self, clock: GameClock) {
+        *self.game_clock.lock().unwrap() = clock
+    }
+    /// Moves the game time towards `time` as received from the server, sent `latency` real seconds ago
+    pub fn synchronize_game_time(&self, time: f64, latency: f64) {
+        self.game_clock.lock().unwrap().synchronize(time, latency)
+    }
+}
@@ -0,0 +1,223 @@
+use wgpu::{BindGroupLayout, ColorTargetState, RenderPass, RenderPipeline, TextureFormat};
+use math::Vec3;
+
+use crate::{Engine, Camera, RenderGraph, TextureHandle, TextureDesc, PassBuilder};
+
+/// Weighted color sum of the order independent transparency, see `engine/oit`
+pub const OIT_ACCUM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// Product of the transparent surfaces transmittance
+pub const OIT_REVEALAGE_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+/// Color targets of the `shader!` pipelines declared with `blend oit`
+pub const OIT_TARGETS: [Option<ColorTargetState>;2] = [
+    Some(ColorTargetState {
+        format: OIT_ACCUM_FORMAT,
+        blend: Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add
+            }
+        }),
+        write_mask: wgpu::ColorWrites::ALL
+    }),
+    Some(ColorTargetState {
+        format: OIT_REVEALAGE_FORMAT,
+        blend: Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                operation: wgpu::BlendOperation::Add
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                operation: wgpu::BlendOperation::Add
+            }
+        }),
+        write_mask: wgpu::ColorWrites::ALL
+    })
+];
+
+/// How the alpha of a material is used, renderers only draw the surfaces of the modes they are made for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Cutout below the material alpha cutoff, with alpha to coverage when multisampled
+    Mask,
+    /// Blended over what is behind, drawn after the opaque surfaces
+    Blend
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transparency {
+    /// Draws sorted back to front, exact for surfaces that do not intersect
+    #[default]
+    Sorted,
+    /// Weighted blended accumulation, independent of draw order but approximate where layers are opaque
+    OrderIndependent
+}
+
+type Draw<'a> = Box<dyn FnOnce(&mut RenderPass<'a>) + 'a>;
+
+/// Transparent draws of a frame, rendered back to front from the camera they were queued for
+pub struct TransparentQueue<'a> {
+    position: Vec3,
+    forward: Vec3,
+    /// Farthest first, draws at the same depth keep the order they were queued in
+    draws: Vec<(f32, Draw<'a>)>
+}
+impl<'a> TransparentQueue<'a> {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            position: camera.position,
+            forward: camera.forward(),
+            draws: Vec::new()
+        }
+    }
+    /// View depth of `position`, what transparent draws are sorted by
+    pub fn depth(&self, position: Vec3) -> f32 {
+        (position - self.position).dot(self.forward)
+    }
+    pub fn push(&mut self, position: Vec3, draw: impl FnOnce(&mut RenderPass<'a>) + 'a) {
+        let depth = self.depth(position);
+        let i = self.draws.partition_point(|(v, _)| *v >= depth);
+        self.draws.insert(i, (depth, Box::new(draw)))
+    }
+    /// View depths of the queued draws, in the order they are rendered
+    pub fn depths(&self) -> Vec<f32> {
+        self.draws.iter().map(|(depth, _)| *depth).collect()
+    }
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+    pub fn render(self, render_pass: &mut RenderPass<'a>) {
+        for (_, draw) in self.draws {
+            draw(render_pass)
+        }
+    }
+}
+
+/// Weighted blended order independent transparency: surfaces accumulate into two targets that are composited over
+/// the scene color, the pipelines drawing them are declared with `blend oit` and return `oit_output`
+pub struct Oit {
+    bgl: BindGroupLayout,
+    composite: RenderPipeline
+}
+impl Oit {
+    pub fn new(e: &Engine) -> Self {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true }
+            },
+            count: None
+        };
+        let bgl = e.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("OIT"),
+            entries: &[texture(0), texture(1)]
+        });
+        let shader = e.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("OIT"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/oit_composite.wgsl").into())
+        });
+        let layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("OIT"),
+            bind_group_layouts: &[&bgl],
+            push_constant_ranges: &[]
+        });
+        let composite = e.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("OIT composite"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: crate::HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::COLOR
+                })]
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None
+        });
+        Self { bgl, composite }
+    }
+    /// Accumulation targets of a frame, the pass drawing the transparent surfaces declares them with `OitTargets::write`.
+    /// `samples` and `scale` match the depth texture of that pass
+    pub fn targets(graph: &mut RenderGraph, samples: u32, scale: f32) -> OitTargets {
+        let reveal_clear = wgpu::Color { r: 1., g: 1., b: 1., a: 1. };
+        let accum = graph.create("oit accum", TextureDesc::new(OIT_ACCUM_FORMAT).scaled(scale).samples(samples));
+        let revealage = graph.create("oit revealage", TextureDesc::new(OIT_REVEALAGE_FORMAT).scaled(scale).samples(samples).clear(reveal_clear));
+        let (accum_resolved, revealage_resolved) = if samples > 1 {
+            (
+                graph.create("oit accum", TextureDesc::new(OIT_ACCUM_FORMAT).scaled(scale)),
+                graph.create("oit revealage", TextureDesc::new(OIT_REVEALAGE_FORMAT).scaled(scale).clear(reveal_clear))
+            )
+        } else {
+            (accum, revealage)
+        };
+        OitTargets { accum, revealage, accum_resolved, revealage_resolved }
+    }
+    /// Adds the pass blending the accumulated `targets` over `color`
+    pub fn add_composite<'a>(&'a self, e: &'a Engine, graph: &mut RenderGraph<'a>, targets: OitTargets, color: TextureHandle) {
+        let OitTargets { accum_resolved, revealage_resolved, .. } = targets;
+        graph.add_pass("oit composite")
+            .read(accum_resolved)
+            .read(revealage_resolved)
+            .write(color)
+            .execute(move |ctx| {
+                let bind_group = e.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("OIT"),
+                    layout: &self.bgl,
+                    entries: &[
+                        wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(ctx.view(accum_resolved)) },
+                        wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(ctx.view(revealage_resolved)) }
+                    ]
+                });
+                let mut render_pass = ctx.render_pass(&[color], None);
+                render_pass.set_pipeline(&self.composite);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.draw(0..3, 0..1)
+            });
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OitTargets {
+    accum: TextureHandle,
+    revealage: TextureHandle,
+    accum_resolved: TextureHandle,
+    revealage_resolved: TextureHandle
+}
+impl OitTargets {
+    /// Declares the writes and resolves of the pass drawing the transparent surfaces
+    pub fn write<'g, 'a>(&self, pass: PassBuilder<'g, 'a>) -> PassBuilder<'g, 'a> {
+        pass.write(self.accum)
+            .write(self.revealage)
+            .resolve(self.accum, self.accum_resolved)
+            .resolve(self.revealage, self.revealage_resolved)
+    }
+    /// Color attachments of that pass, in the order of `OIT_TARGETS`
+    pub fn colors(&self) -> [TextureHandle;2] {
+        [self.accum, self.revealage]
+    }
+}
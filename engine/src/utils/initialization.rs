@@ -1,11 +1,11 @@
-use wgpu::{Instance, Adapter, Device, Queue, Surface, Features, Dx12Compiler};
+use wgpu::{Adapter, Device, Queue, Dx12Compiler};
 use winit::{event_loop::EventLoop, window::{Window, WindowBuilder}, dpi::{PhysicalSize, PhysicalPosition}};
 
-use crate::Engine;
+use crate::{Engine, AdapterOptions};
 
-pub fn new_instance() -> wgpu::Instance {
+pub fn new_instance(backends: wgpu::Backends) -> wgpu::Instance {
     wgpu::Instance::new(wgpu::InstanceDescriptor {
-        backends: wgpu::Backends::VULKAN,
+        backends,
         dx12_shader_compiler: Dx12Compiler::Dxc {
             dxil_path: Some("assets/libs/dxil.dll".into()),
             dxc_path: Some("assets/libs/dxc.exe".into())
@@ -13,49 +13,47 @@ pub fn new_instance() -> wgpu::Instance {
     })
 }
 
-pub fn new_device(adapter: &Adapter) -> (Device, Queue) {
-    let mut features = adapter.features();
-    features.remove(Features::MAPPABLE_PRIMARY_BUFFERS);
-    features.insert(Features::PARTIALLY_BOUND_BINDING_ARRAY);
-    features.insert(Features::TEXTURE_BINDING_ARRAY);
-    features.insert(Features::BUFFER_BINDING_ARRAY);
-    features.insert(Features::STORAGE_RESOURCE_BINDING_ARRAY);
-    println!("Using device features: {features:#?}");
+/// Device with the required features and the optional ones the adapter has, the default limits are lowered
+/// to the adapter ones when it can not reach them
+pub fn new_device(adapter: &Adapter, options: &AdapterOptions) -> Result<(Device, Queue), String> {
+    let features = options.features(adapter);
+    let missing = options.optional_features.difference(features);
+    info!("Using device features: {features:?}");
+    if !missing.is_empty() {
+        info!("Optional device features not supported: {missing:?}")
+    }
+    let limits = if wgpu::Limits::default().check_limits(&adapter.limits()) {
+        wgpu::Limits::default()
+    } else {
+        warn!("The adapter does not reach the default limits, using its own");
+        adapter.limits()
+    };
     pollster::block_on(adapter.request_device(
         &wgpu::DeviceDescriptor {
             features,
-            limits: wgpu::Limits::default(),
+            limits,
             label: None
         },
         None
-    )).unwrap()
+    )).map_err(|e| format!("Could not create the device of {}: {e}", adapter.get_info().name))
 }
 
-pub fn new_adapter(instance: &Instance, surface: &Surface) -> Adapter {
-    pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false
-    })).unwrap();
-    let adapters = instance.enumerate_adapters(wgpu::Backends::all())
-        .filter(|adapter| {
-            let info = adapter.get_info();
-            let supported = adapter.is_surface_supported(surface);
-            info!("{:?}: {} ({}, {}), {:?} ({}), {supported}",
-                info.device_type,
-                info.name, info.vendor, info.device,
-                info.backend, info.driver_info
-            );
-            supported
-        })
-        .collect::<Vec<_>>();
-    adapters.into_iter()
-        .next()
-        .unwrap()
+/// Configuration of the offscreen output of headless engines, it is never applied to a surface
+pub fn headless_config(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![]
+    }
 }
 
 pub fn configure_surface(
     window_size: winit::dpi::PhysicalSize<u32>,
+    present_mode: crate::PresentMode,
     device: &wgpu::Device,
     adapter: &wgpu::Adapter,
     surface: &wgpu::Surface
@@ -66,7 +64,10 @@ pub fn configure_surface(
         format: caps.formats.into_iter().find(|v| v.is_srgb()).unwrap(),
         width: window_size.width,
         height: window_size.height,
-        present_mode: wgpu::PresentMode::AutoVsync,
+        present_mode: match present_mode.wgpu() {
+            wgpu::PresentMode::Mailbox if !caps.present_modes.contains(&wgpu::PresentMode::Mailbox) => wgpu::PresentMode::AutoVsync,
+            mode => mode
+        },
         alpha_mode: caps.alpha_modes.into_iter().next().unwrap(),
         view_formats: vec![]
     };
@@ -74,12 +75,12 @@ pub fn configure_surface(
     config
 }
 
-pub fn new_window(event_loop: &EventLoop<()>) -> Window {
+pub fn new_window(event_loop: &EventLoop<()>, (width, height): (u32, u32)) -> Window {
     let w = WindowBuilder::new()
         .with_title("Nexodia")
         .with_inner_size(PhysicalSize {
-            width: 900,
-            height: 600
+            width,
+            height
         })
         .with_min_inner_size(PhysicalSize {
             width: 100,
@@ -93,9 +94,10 @@ pub fn new_window(event_loop: &EventLoop<()>) -> Window {
 
 impl Engine {
     pub fn center_window(&self) {
-        let ms = self.window.current_monitor().unwrap().size();
-        let ws = self.window.inner_size();
-        self.window.set_outer_position(PhysicalPosition {
+        let Some(window) = &self.window else { return };
+        let ms = window.current_monitor().unwrap().size();
+        let ws = window.inner_size();
+        window.set_outer_position(PhysicalPosition {
             x: (ms.width - ws.width) / 2,
             y: (ms.height - ws.height) / 2
         })
@@ -10,9 +10,16 @@ mod color;  pub use color::*;
 mod id;     pub use id::*;
 
 impl Engine {
+    /// Size of the window, or of the output of headless engines
     pub fn window_size(&self) -> Vec2 {
-        let ws = self.window.inner_size();
-        Vec2::new(ws.width as f32, ws.height as f32)
+        let (width, height) = match &self.window {
+            Some(window) => window.inner_size().into(),
+            None => {
+                let config = self.surface_config.lock().unwrap();
+                (config.width, config.height)
+            }
+        };
+        Vec2::new(width as f32, height as f32)
     }
     pub fn new_buffer(&self, contents: &[u8], usage: BufferUsages) -> Buffer {
         self.device.create_buffer_init(
@@ -24,8 +31,9 @@ impl Engine {
         )
     }
     pub fn center_cursor(&self) {
-        let s = self.window.inner_size();
-        self.window.set_cursor_position(PhysicalPosition {
+        let Some(window) = &self.window else { return };
+        let s = window.inner_size();
+        window.set_cursor_position(PhysicalPosition {
             x: s.width/2,
             y: s.height/2
         }).ok();
@@ -84,7 +84,9 @@ impl Parse for PathsArg {
 pub struct Args {
     pub animations: Paths,
     pub meshes: PathsArg,
-    pub textures: Paths
+    pub textures: Paths,
+    pub models: PathsArg,
+    pub heightmaps: Paths
 }
 impl Parse for Args {
     fn parse(input: ParseStream) -> syn::Result<Self> {
@@ -100,6 +102,18 @@ impl Parse for Args {
             textures: {
                 assert_ident(input, "textures")?;
                 Paths::parse(input)?
+            },
+            models: if input.is_empty() || input.fork().parse::<Ident>()? != "models" {
+                PathsArg(Vec::new())
+            } else {
+                assert_ident(input, "models")?;
+                PathsArg::parse(input)?
+            },
+            heightmaps: if input.is_empty() {
+                Paths(Vec::new())
+            } else {
+                assert_ident(input, "heightmaps")?;
+                Paths::parse(input)?
             }
         })
     }
@@ -110,7 +124,9 @@ pub fn assets(inp: TokenStream) -> TokenStream {
     let Args {
         animations: Paths ( animations ),
         meshes: PathsArg ( meshes ),
-        textures: Paths ( textures )
+        textures: Paths ( textures ),
+        models: PathsArg ( models ),
+        heightmaps: Paths ( heightmaps )
     } = parse_macro_input!(inp as Args);
 
     let animations_paths = animations.iter().map(|path| path.to_path_string());
@@ -123,11 +139,23 @@ pub fn assets(inp: TokenStream) -> TokenStream {
     let textures_paths = textures.iter().map(|path| path.to_path_string());
     let textures_fields = textures.iter().map(|path| path.to_field()).collect::<Vec<_>>();
 
+    // Models may share their file with a mesh, their fields are suffixed to keep both
+    let models_paths = models.iter().map(|path_arg| path_arg.0.to_path_string());
+    let models_fields = models.iter()
+        .map(|path_arg| Ident::new(&format!("{}_model", path_arg.0.to_field()), Span::call_site()))
+        .collect::<Vec<_>>();
+    let models_vertex_type = models.iter().map(|path_arg| &path_arg.1).collect::<Vec<_>>();
+
+    let heightmaps_paths = heightmaps.iter().map(|path| path.to_path_string());
+    let heightmaps_fields = heightmaps.iter().map(|path| path.to_field()).collect::<Vec<_>>();
+
     quote!(
         pub struct Assets {
             #(pub #animations_fields: engine::Animation,)*
             #(pub #meshes_fields: engine::Mesh<#meshes_vertex_type>,)*
             #(pub #textures_fields: engine::Texture,)*
+            #(pub #models_fields: engine::Model<#models_vertex_type>,)*
+            #(pub #heightmaps_fields: engine::compiler::Heightmap,)*
         }
         impl Assets {
             pub fn new(e: &'static engine::Engine) -> Self {
@@ -153,6 +181,20 @@ pub fn assets(inp: TokenStream) -> TokenStream {
                                 .with_extension("bin")
                         ),
                     )*
+                    #(
+                        #models_fields: e.load_model::<#models_vertex_type>(
+                            std::path::Path::new("assets/")
+                                .join(#models_paths)
+                                .with_extension("bin")
+                        ),
+                    )*
+                    #(
+                        #heightmaps_fields: e.load_heightmap(
+                            std::path::Path::new("assets/")
+                                .join(#heightmaps_paths)
+                                .with_extension("bin")
+                        ),
+                    )*
                 }
             }
         }
@@ -25,21 +25,28 @@ impl syn::parse::Parse for Args {
         while !input.is_empty() {
             let _type = input.parse::<Ident>()?;
             
-            let (visibility, arg1) = match input.parse::<Expr>()? {
+            let (visibility, arg1, arg2) = match input.parse::<Expr>()? {
                 Expr::Tuple(v) => {
                     let mut args = v.elems.into_iter();
                     (
                         parse_visibility(&args.next().unwrap()),
+                        args.next(),
                         args.next()
                     )
                 },
                 Expr::Paren(v) => (
                     parse_visibility(&v.expr),
+                    None,
                     None
                 ),
                 _ => panic!("Wrong visibility type")
             };
 
+            let view_dimension = match arg2 {
+                Some(v) => quote!(wgpu::TextureViewDimension::#v),
+                None => quote!(wgpu::TextureViewDimension::D2)
+            };
+
             bgls.push(match _type.to_string().as_str() {
                 "Uniform" => quote!(
                     wgpu::BindGroupLayoutEntry {
@@ -53,13 +60,25 @@ impl syn::parse::Parse for Args {
                         count: None
                     }
                 ),
+                "Storage" => quote!(
+                    wgpu::BindGroupLayoutEntry {
+                        binding: #binding,
+                        visibility: #visibility,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    }
+                ),
                 "TextureView" => quote!(
                     wgpu::BindGroupLayoutEntry {
                         binding: #binding,
                         visibility: #visibility,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
+                            view_dimension: #view_dimension,
                             sample_type: wgpu::TextureSampleType::#arg1
                         },
                         count: None
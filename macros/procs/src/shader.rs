@@ -1,6 +1,7 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse::ParseStream, parse_macro_input};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use syn::{parse::ParseStream, parse_macro_input, LitStr};
 
 use crate::utils::{AnyToString, assert_ident};
 
@@ -10,8 +11,140 @@ pub struct Args {
     pub instance: AnyToString,
     pub vbls: AnyToString,
     pub bgls: AnyToString,
-    pub frag_stage: AnyToString
+    pub frag_stage: AnyToString,
+    pub options: Options
 }
+
+/// Optional pipeline state keys, following `frag_stage` in any order
+#[derive(Default)]
+pub struct Options {
+    pub depth_bias: Option<TokenStream2>,
+    pub slope_bias: Option<TokenStream2>,
+    pub samples: Option<TokenStream2>,
+    pub blend: Option<TokenStream2>,
+    pub cull: Option<TokenStream2>,
+    pub depth_test: Option<TokenStream2>,
+    pub depth_write: Option<TokenStream2>,
+    pub depth_compare: Option<TokenStream2>,
+    pub topology: Option<TokenStream2>,
+    pub polygon: Option<TokenStream2>,
+    pub targets: Option<TokenStream2>,
+    pub color_writes: Option<TokenStream2>,
+    pub vs_entry: Option<LitStr>,
+    pub fs_entry: Option<LitStr>,
+    pub source: Option<LitStr>,
+    pub defines: Option<TokenStream2>,
+    pub alpha_to_coverage: Option<TokenStream2>,
+    /// `blend oit` draws into `engine::OIT_TARGETS`
+    pub oit: bool
+}
+
+fn parse_choice(input: ParseStream, choices: &[(&str, TokenStream2)]) -> syn::Result<TokenStream2> {
+    let ident = input.parse::<Ident>()?;
+    choices.iter()
+        .find(|(name, _)| ident == name)
+        .map(|(_, tokens)| tokens.clone())
+        .ok_or_else(|| syn::Error::new(ident.span(), format!(
+            "expected one of: {}",
+            choices.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+        )))
+}
+
+fn parse_blend(input: ParseStream) -> syn::Result<TokenStream2> {
+    parse_choice(input, &[
+        ("none", quote!(None)),
+        ("alpha", quote!(Some(wgpu::BlendState::ALPHA_BLENDING))),
+        ("premultiplied", quote!(Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING))),
+        ("additive", quote!(Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add
+            }
+        })))
+    ])
+}
+
+fn parse_compare(input: ParseStream) -> syn::Result<TokenStream2> {
+    parse_choice(input, &[
+        ("never", quote!(wgpu::CompareFunction::Never)),
+        ("less", quote!(wgpu::CompareFunction::Less)),
+        ("equal", quote!(wgpu::CompareFunction::Equal)),
+        ("less_equal", quote!(wgpu::CompareFunction::LessEqual)),
+        ("greater", quote!(wgpu::CompareFunction::Greater)),
+        ("not_equal", quote!(wgpu::CompareFunction::NotEqual)),
+        ("greater_equal", quote!(wgpu::CompareFunction::GreaterEqual)),
+        ("always", quote!(wgpu::CompareFunction::Always))
+    ])
+}
+
+impl syn::parse::Parse for Options {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Self::default();
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            let expr = |input: ParseStream| -> syn::Result<TokenStream2> { Ok(input.parse::<AnyToString>()?.0) };
+            let (option, value) = match ident.to_string().as_str() {
+                "depth_bias" => (&mut options.depth_bias, expr(input)?),
+                "slope_bias" => (&mut options.slope_bias, expr(input)?),
+                "samples" => (&mut options.samples, expr(input)?),
+                "depth_test" => (&mut options.depth_test, expr(input)?),
+                "depth_write" => (&mut options.depth_write, expr(input)?),
+                "targets" => (&mut options.targets, expr(input)?),
+                "color_writes" => (&mut options.color_writes, expr(input)?),
+                "defines" => (&mut options.defines, expr(input)?),
+                "alpha_to_coverage" => (&mut options.alpha_to_coverage, expr(input)?),
+                "blend" if input.fork().parse::<Ident>().is_ok_and(|v| v == "oit") => {
+                    input.parse::<Ident>()?;
+                    options.oit = true;
+                    (&mut options.blend, quote!(None))
+                }
+                "blend" => (&mut options.blend, parse_blend(input)?),
+                "depth_compare" => (&mut options.depth_compare, parse_compare(input)?),
+                "cull" => (&mut options.cull, parse_choice(input, &[
+                    ("none", quote!(None)),
+                    ("back", quote!(Some(wgpu::Face::Back))),
+                    ("front", quote!(Some(wgpu::Face::Front)))
+                ])?),
+                "topology" => (&mut options.topology, parse_choice(input, &[
+                    ("triangle_list", quote!(wgpu::PrimitiveTopology::TriangleList)),
+                    ("triangle_strip", quote!(wgpu::PrimitiveTopology::TriangleStrip)),
+                    ("line_list", quote!(wgpu::PrimitiveTopology::LineList)),
+                    ("line_strip", quote!(wgpu::PrimitiveTopology::LineStrip)),
+                    ("point_list", quote!(wgpu::PrimitiveTopology::PointList))
+                ])?),
+                "polygon" => (&mut options.polygon, parse_choice(input, &[
+                    ("fill", quote!(wgpu::PolygonMode::Fill)),
+                    ("line", quote!(wgpu::PolygonMode::Line)),
+                    ("point", quote!(wgpu::PolygonMode::Point))
+                ])?),
+                "vs_entry" => {
+                    options.vs_entry = Some(input.parse()?);
+                    continue
+                }
+                "fs_entry" => {
+                    options.fs_entry = Some(input.parse()?);
+                    continue
+                }
+                "source" => {
+                    options.source = Some(input.parse()?);
+                    continue
+                }
+                v => return Err(syn::Error::new(ident.span(), format!("unknown shader key: '{v}'")))
+            };
+            if option.replace(value).is_some() {
+                return Err(syn::Error::new(ident.span(), format!("shader key '{ident}' set twice")))
+            }
+        }
+        Ok(options)
+    }
+}
+
 impl syn::parse::Parse for Args {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         assert_ident(input, "material")?;
@@ -38,7 +171,8 @@ impl syn::parse::Parse for Args {
             instance,
             vbls,
             bgls,
-            frag_stage
+            frag_stage,
+            options: input.parse()?
         })
     }
 }
@@ -51,76 +185,121 @@ pub fn shader(inp: TokenStream) -> TokenStream {
         vbls,
         bgls,
         frag_stage,
+        options
     } = parse_macro_input!(inp as Args);
+    let depth_bias = options.depth_bias.unwrap_or(quote!(0));
+    let slope_bias = options.slope_bias.unwrap_or(quote!(0.));
+    let samples = options.samples.unwrap_or(quote!(e.sample_count()));
+    let blend = options.blend.unwrap_or(quote!(None));
+    let cull = options.cull.unwrap_or(quote!(Some(wgpu::Face::Back)));
+    let depth_test = options.depth_test.unwrap_or(quote!(true));
+    let depth_write = options.depth_write.unwrap_or(quote!(true));
+    let depth_compare = options.depth_compare.unwrap_or(quote!(wgpu::CompareFunction::Less));
+    // Indexed strips restart on the largest index of the `u32` indices of the meshes
+    let strip_index_format = match options.topology.as_ref().map(|v| v.to_string()) {
+        Some(v) if v.ends_with("Strip") => quote!(Some(wgpu::IndexFormat::Uint32)),
+        _ => quote!(None)
+    };
+    let topology = options.topology.unwrap_or(quote!(wgpu::PrimitiveTopology::TriangleList));
+    let polygon = options.polygon.map(|v| quote!(e.polygon_mode(module_path!(), #v))).unwrap_or(quote!(wgpu::PolygonMode::Fill));
+    let targets = options.targets.unwrap_or(quote!([engine::HDR_FORMAT]));
+    let color_writes = options.color_writes.unwrap_or(quote!(wgpu::ColorWrites::COLOR));
+    let vs_entry = options.vs_entry.map(|v| quote!(#v)).unwrap_or(quote!("vs_main"));
+    let fs_entry = options.fs_entry.map(|v| quote!(#v)).unwrap_or(quote!("fs_main"));
+    let source = options.source.map(|v| quote!(#v)).unwrap_or(quote!("./shader.wgsl"));
+    let defines = options.defines.unwrap_or(quote!([]));
+    let alpha_to_coverage = options.alpha_to_coverage.unwrap_or(quote!(false));
+    let targets = if options.oit {
+        quote!(engine::OIT_TARGETS)
+    } else {
+        quote!(#targets.map(|format: wgpu::TextureFormat| Some(wgpu::ColorTargetState {
+            format,
+            blend: #blend,
+            write_mask: #color_writes
+        })))
+    };
     quote!(
         use wgpu::{ShaderModuleDescriptor, RenderPipeline, VertexBufferLayout, BindGroupLayout};
         use engine::{Vertex};
-        pub struct Shader(wgpu::RenderPipeline);
+        pub struct Shader(std::sync::Arc<wgpu::RenderPipeline>);
         impl engine::Shader for Shader {
             type Material = #material;
             type Vertex = #vertex;
             type Instance = #instance;
             fn pipeline(&self) -> &wgpu::RenderPipeline { &self.0 }
             fn new(e: &'static engine::Engine) -> Self {
-                let shader = e.device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some(module_path!()),
-                    source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(
-                        include_str!("./shader.wgsl")
-                            .replace("#MAX_JOINTS", &engine::MAX_JOINTS.to_string())
-                    ))
-                });
-                let render_pipeline_layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some(module_path!()),
-                    bind_group_layouts: &#bgls,
-                    push_constant_ranges: &[]
-                });
-                let targets = &[Some(wgpu::ColorTargetState {
-                    format: e.surface_config.lock().unwrap().format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::COLOR
-                })];
-                Self (
+                let source = include_str!(#source);
+                let defines = #defines;
+                let buffers = #vbls;
+                let targets = #targets;
+                let depth_test: bool = #depth_test;
+                let samples: u32 = #samples;
+                let frag_stage: bool = #frag_stage;
+                let primitive = wgpu::PrimitiveState {
+                    topology: #topology,
+                    strip_index_format: #strip_index_format,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: #cull,
+                    polygon_mode: #polygon,
+                    unclipped_depth: false,
+                    conservative: false
+                };
+                let depth_stencil = wgpu::DepthStencilState {
+                    format: engine::DEPTH_FORMAT,
+                    depth_write_enabled: depth_test && #depth_write,
+                    depth_compare: if depth_test { #depth_compare } else { wgpu::CompareFunction::Always },
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: #depth_bias,
+                        slope_scale: #slope_bias,
+                        clamp: 0.
+                    }
+                };
+                let multisample = wgpu::MultisampleState {
+                    count: samples,
+                    mask: !0,
+                    // Alpha to coverage needs several samples, single sampled pipelines keep their discard
+                    alpha_to_coverage_enabled: #alpha_to_coverage && samples > 1
+                };
+                // Layouts are compared by the types and expressions they come from
+                let state = format!(
+                    "{} {} {} {} {} {frag_stage} {buffers:?} {targets:?} {primitive:?} {depth_stencil:?} {multisample:?}",
+                    std::any::type_name::<Self::Material>(),
+                    std::any::type_name::<Self::Instance>(),
+                    stringify!(#bgls),
+                    #vs_entry,
+                    #fs_entry
+                );
+                Self(e.render_pipeline(source, &defines, state, || {
+                    let shader = e.shader_module(module_path!(), source, &defines);
+                    let render_pipeline_layout = e.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some(module_path!()),
+                        bind_group_layouts: &#bgls,
+                        push_constant_ranges: &[]
+                    });
                     e.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                         label: Some(module_path!()),
                         layout: Some(&render_pipeline_layout),
                         vertex: wgpu::VertexState {
                             module: &shader,
-                            entry_point: "vs_main",
-                            buffers: &#vbls
+                            entry_point: #vs_entry,
+                            buffers: &buffers
                         },
-                        fragment: if #frag_stage {
+                        fragment: if frag_stage {
                             Some(wgpu::FragmentState {
                                 module: &shader,
-                                entry_point: "fs_main",
-                                targets
+                                entry_point: #fs_entry,
+                                targets: &targets
                             })
                         } else {
                             None
                         },
-                        primitive: wgpu::PrimitiveState {
-                            topology: wgpu::PrimitiveTopology::TriangleList,
-                            strip_index_format: None,
-                            front_face: wgpu::FrontFace::Ccw,
-                            cull_mode: Some(wgpu::Face::Back),
-                            polygon_mode: wgpu::PolygonMode::Fill,
-                            unclipped_depth: false,
-                            conservative: false
-                        },
-                        depth_stencil: Some(wgpu::DepthStencilState {
-                            format: engine::DEPTH_FORMAT,
-                            depth_write_enabled: true,
-                            depth_compare: wgpu::CompareFunction::Less,
-                            stencil: wgpu::StencilState::default(),
-                            bias: wgpu::DepthBiasState::default()
-                        }),
-                        multisample: wgpu::MultisampleState {
-                            count: 1,
-                            mask: !0,
-                            alpha_to_coverage_enabled: false
-                        },
+                        primitive,
+                        depth_stencil: Some(depth_stencil),
+                        multisample,
                         multiview: None
                     })
-                )
+                }))
             }
         }
     ).into()
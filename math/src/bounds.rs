@@ -0,0 +1,106 @@
+use crate::{Vec3, Vec4, Mat4x4};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3
+}
+impl Aabb {
+    /// Contains nothing, the identity of `union`
+    pub const EMPTY: Self = Self {
+        min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY)
+    };
+    #[inline(always)]
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+    pub fn around(center: Vec3, half_extents: Vec3) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |aabb, point| Self::new(aabb.min.min_element_wise(point), aabb.max.max_element_wise(point)))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+    pub fn union(self, other: Self) -> Self {
+        Self::new(self.min.min_element_wise(other.min), self.max.max_element_wise(other.max))
+    }
+    pub fn corners(&self) -> [Vec3;8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z), Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z), Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z)
+        ]
+    }
+    /// Box around the 8 transformed corners
+    pub fn transformed(&self, transform: Mat4x4) -> Self {
+        Self::from_points(self.corners().map(|corner| (transform * corner.extend(1.)).truncate()))
+    }
+    /// Distance along `direction` at which a ray from `origin` enters the box, 0 when it starts inside
+    pub fn ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        self.ray_span(origin, direction).map(|(near, _)| near)
+    }
+    /// Distances along `direction` at which a ray from `origin` enters and leaves the box
+    pub fn ray_span(&self, origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        let (mut near, mut far) = (0f32, f32::INFINITY);
+        for (o, d, min, max) in [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z)
+        ] {
+            if d == 0. {
+                if o < min || o > max { return None }
+                continue
+            }
+            let (a, b) = ((min - o) / d, (max - o) / d);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        (near <= far).then_some((near, far))
+    }
+}
+
+/// Inward facing planes of a view volume, `(normal, distance)` with points inside where `normal.p + distance >= 0`
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    pub planes: [Vec4;6]
+}
+impl Frustum {
+    /// Planes of a projection * view matrix, the near plane assumes clip depths down to -w so it also holds
+    /// for the [0, 1] depth range
+    pub fn from_matrix(m: Mat4x4) -> Self {
+        let row = |i: usize| {
+            let get = |v: Vec4| [v.x, v.y, v.z, v.w][i];
+            Vec4::new(get(m.x), get(m.y), get(m.z), get(m.w))
+        };
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let normalize = |p: Vec4| p * (1. / p.truncate().length());
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z].map(normalize)
+        }
+    }
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(point) + plane.w >= 0.)
+    }
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+    /// Conservative, boxes outside of the frustum but across two of its planes near a corner are kept
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let (center, extents) = (aabb.center(), aabb.half_extents());
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let radius = extents.x * normal.x.abs() + extents.y * normal.y.abs() + extents.z * normal.z.abs();
+            normal.dot(center) + plane.w >= -radius
+        })
+    }
+}
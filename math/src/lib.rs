@@ -5,4 +5,5 @@ mod mat4x4;          pub use mat4x4::*;
 mod mat3x3;          pub use mat3x3::*;
 mod quaternion;      pub use quaternion::*;
 mod transform;       pub use transform::*;
-mod stransform;      pub use stransform::*;
\ No newline at end of file
+mod stransform;      pub use stransform::*;
+mod bounds;          pub use bounds::*;
\ No newline at end of file
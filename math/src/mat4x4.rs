@@ -21,10 +21,15 @@ impl Mat4x4 {
     pub const fn new(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Self {
         Self { x, y, z, w }
     }
+    /// World up, or the z axis when `dir` is too close to vertical to build a view basis from it
+    #[inline(always)]
+    pub fn view_up(dir: Vec3) -> Vec3 {
+        if dir.normalized().y.abs() > 0.999 { Vec3::new(0., 0., 1.) } else { Vec3::new(0., 1., 0.) }
+    }
     #[inline(always)]
     pub fn look_to(eye: Vec3, dir: Vec3) -> Self {
         let f = dir.normalized();
-        let s = f.cross(Vec3::new(0., 1., 0.)).normalized();
+        let s = f.cross(Self::view_up(f)).normalized();
         let u = s.cross(f);
         Self {
             x: Vec4::new(s.x, u.x, -f.x, 0.),
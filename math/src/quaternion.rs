@@ -3,7 +3,7 @@ use bincode::{Decode, Encode};
 
 use crate::{Vec3, Mat4x4, Mat3x3, Vec4};
 
-#[derive(Copy, Clone, Encode, Decode)]
+#[derive(Debug, Copy, Clone, Encode, Decode)]
 pub struct Quaternion {
     pub v: Vec3,
     pub s: f32
@@ -109,6 +109,25 @@ impl Quaternion {
         Self::from_vs(axis * s, c)
     }
     #[inline(always)]
+    pub fn from_to(from: Vec3, to: Vec3) -> Self {
+        let from = from.normalized();
+        let to = to.normalized();
+        let dot = from.dot(to);
+        if dot < -0.999999 {
+            let mut axis = Vec3::new(1., 0., 0.).cross(from);
+            if axis.length() < 0.000001 {
+                axis = Vec3::new(0., 1., 0.).cross(from)
+            }
+            Self::from_axis_angle(axis.normalized(), PI)
+        } else {
+            Self::from_vs(from.cross(to), 1. + dot).normalised()
+        }
+    }
+    #[inline(always)]
+    pub fn angle(self) -> f32 {
+        2. * self.s.abs().min(1.).acos()
+    }
+    #[inline(always)]
     pub fn from_angle_x(angle: f32) -> Self {
         let (s, c) = (angle * 0.5).sin_cos();
         Self::from_vs(Vec3::new(s, 0., 0.), c)
@@ -3,7 +3,7 @@ use bincode::{Encode, Decode};
 
 use crate::{Vec3, Quaternion, Mat4x4, Vec4};
 
-#[derive(Copy, Clone, Encode, Decode)]
+#[derive(Debug, Copy, Clone, Encode, Decode)]
 pub struct SimpleTransform {
     pub translation: Vec3,
     pub rotation: Quaternion
@@ -27,6 +27,11 @@ impl SimpleTransform {
         self.rotation = self.rotation.nlerp(other.rotation, amount)
     }
     #[inline(always)]
+    pub fn inverse(self) -> Self {
+        let rotation = self.rotation.conjugate();
+        Self::new(-(rotation * self.translation), rotation)
+    }
+    #[inline(always)]
     pub fn apply_translation_rotation(self, other: Vec3) -> Vec3 {
         self.translation + (self.rotation * other)
     }
@@ -28,7 +28,11 @@ macro_rules! vec {
             }
             #[inline(always)]
             pub fn distance(self, other: Self) -> f32 {
-                self.dot(other).sqrt()
+                strip_plus!($(+((self.$field-other.$field)*(self.$field-other.$field)))+).sqrt()
+            }
+            #[inline(always)]
+            pub fn length(self) -> f32 {
+                self.dot(self).sqrt()
             }
             #[inline(always)]
             pub fn mul_element_wise(self, other: Self) -> Self {
@@ -37,6 +41,18 @@ macro_rules! vec {
                 ),* }
             }
             #[inline(always)]
+            pub fn min_element_wise(self, other: Self) -> Self {
+                Self { $(
+                    $field: self.$field.min(other.$field)
+                ),* }
+            }
+            #[inline(always)]
+            pub fn max_element_wise(self, other: Self) -> Self {
+                Self { $(
+                    $field: self.$field.max(other.$field)
+                ),* }
+            }
+            #[inline(always)]
             pub fn sum(self) -> f32 {
                 strip_plus!($(+(self.$field))+)
             }
@@ -1,5 +1,5 @@
 use std::{f32::consts::PI, sync::Arc};
-use engine::{Script, Engine, utils::Id, Animator, Light, Quaternion, Vec3, Mesh};
+use engine::{Script, Engine, utils::Id, Animator, Quaternion, Vec3, Mesh, Heightfield};
 
 use crate::{objects::CameraValues, shaders::character, objects::Character, scenes::main::Assets};
 
@@ -7,42 +7,50 @@ pub struct MainCharacter {
     e: &'static Engine,
     assets: Arc<Assets>,
     camera_values: CameraValues,
+    /// Ground the character walks on
+    heightfield: Heightfield,
     pub animator: Animator
 }
 impl<'s> Script<'s> for MainCharacter {
     type Params = (
         Arc<Assets>,
-        Mesh<<character::main::Shader as engine::Shader>::Vertex>,
-        &'s Light,
-        CameraValues
+        Mesh<engine::vertex::pnj::Vertex>,
+        CameraValues,
+        Heightfield
     );
     type Return = Character;
     const NAME: &'static str = "MainCharacter";
     fn new(
         e: &'static Engine,
         _id: Id,
-        (assets, mesh, light, camera_values): Self::Params
+        (assets, mesh, camera_values, heightfield): Self::Params
     ) -> (Self, Self::Return) {
-        let animator = e.animator(&mesh, assets.male_animations_idle.clone());
-        let object = e.create_object(
-            character::main::Material::new(e, &animator, light, "#d69f7e"),
-            mesh.clone()
-        );
-        let object_light = e.create_object(
-            character::dir_light::Material::new(e, &animator, light),
-            mesh
-        );
+        let animator = e.animator(&mesh, assets.male_animations_idle.clone())
+            .expect("No room left in the skinning pool for the main character");
+        let main = character::main::Material::new(e, &animator, "#d69f7e");
+        let dir_light = character::dir_light::Material::new(e, &animator);
+        let character = if e.graphics_settings().compute_skinning {
+            let skinned = e.skinned_mesh(&mesh, &animator);
+            Character::ComputeSkinned {
+                main: e.create_object(main, skinned.mesh.clone()),
+                dir_light: e.create_object(dir_light, skinned.mesh.clone()),
+                skinned
+            }
+        } else {
+            Character::VertexSkinned {
+                main: e.create_object(main, mesh.clone()),
+                dir_light: e.create_object(dir_light, mesh)
+            }
+        };
         (
             Self {
                 e,
                 assets,
                 camera_values,
+                heightfield,
                 animator
             },
-            Character {
-                main: object,
-                dir_light: object_light
-            }
+            character
         )
     }
     fn update(&mut self) {
@@ -67,6 +75,10 @@ impl<'s> Script<'s> for MainCharacter {
         } else {
             self.animator.set_animation(self.assets.male_animations_idle.clone())
         }
+        let translation = &mut self.animator.transform.translation;
+        if let Some(height) = self.heightfield.height_at(translation.x, translation.z) {
+            translation.y = height
+        }
         self.animator.update(self.e);
         *self.camera_values.target.lock().unwrap() = self.animator.position();
     }
@@ -1,8 +1,46 @@
-use engine::Object;
+use engine::{Object, SkinnedMesh, Engine, ObjectRenderer};
+use wgpu::{CommandEncoder, RenderPass};
+
+use crate::shaders::character::{self, Shaders};
 
 mod main;  pub use main::*;
 
-pub struct Character {
-    pub main: Object<crate::shaders::character::main::Shader>,
-    pub dir_light: Object<crate::shaders::character::dir_light::Shader>
-}
\ No newline at end of file
+/// Objects of a character, skinned by the vertex shader of each pass or once per frame by `engine::ComputeSkinning`
+/// when `GraphicsSettings::compute_skinning` is on
+pub enum Character {
+    VertexSkinned {
+        main: Object<character::main::Shader>,
+        dir_light: Object<character::dir_light::Shader>
+    },
+    ComputeSkinned {
+        skinned: SkinnedMesh,
+        main: Object<character::compute_main::Shader>,
+        dir_light: Object<character::compute_dir_light::Shader>
+    }
+}
+impl Character {
+    /// Records the compute skinning of this frame, nothing for vertex skinned characters
+    pub fn skin(&self, e: &Engine, encoder: &mut CommandEncoder) {
+        if let Self::ComputeSkinned { skinned, .. } = self {
+            skinned.skin(e, encoder)
+        }
+    }
+    pub fn render_shadow<'r, 's: 'r>(&'s self, shaders: &'s Shaders, render_pass: &mut RenderPass<'r>) {
+        match self {
+            Self::VertexSkinned { dir_light, .. } => shaders.dir_light.render_object(render_pass, dir_light),
+            Self::ComputeSkinned { dir_light, .. } => shaders.compute_dir_light.render_object(render_pass, dir_light)
+        }
+    }
+    pub fn render_prepass<'r, 's: 'r>(&'s self, shaders: &'s Shaders, render_pass: &mut RenderPass<'r>) {
+        match self {
+            Self::VertexSkinned { main, .. } => shaders.prepass.render_object(render_pass, main),
+            Self::ComputeSkinned { main, .. } => shaders.compute_prepass.render_object(render_pass, main)
+        }
+    }
+    pub fn render<'r, 's: 'r>(&'s self, shaders: &'s Shaders, render_pass: &mut RenderPass<'r>) {
+        match self {
+            Self::VertexSkinned { main, .. } => shaders.main.render_object(render_pass, main),
+            Self::ComputeSkinned { main, .. } => shaders.compute_main.render_object(render_pass, main)
+        }
+    }
+}
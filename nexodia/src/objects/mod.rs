@@ -1,3 +1,2 @@
 mod character;  pub use character::*;
 mod third_p_c;  pub use third_p_c::*;
-mod basic;      pub use basic::*;
\ No newline at end of file
@@ -1,9 +1,11 @@
 use std::{f32::consts::FRAC_PI_2, sync::{Mutex, Arc}};
-use engine::{Script, Engine, CameraBinding, Vec3, Mat4x4, Vec2, Quaternion, utils::Id};
+use engine::{Script, Engine, Camera, Vec3, Vec2, Quaternion, utils::Id};
 
 use crate::utils::Lerp;
 
 const CAM_MAX_ANG: f32 = FRAC_PI_2 - 0.1;
+/// Vertical field of view, about 70 degrees
+const CAM_FOV: f32 = 1.22;
 
 #[derive(Default, Clone)]
 pub struct CameraValues {
@@ -25,10 +27,12 @@ impl<'s> Script<'s> for ThirdPersonCamera {
     fn new(e: &'static Engine, _id: Id, _params: Self::Params) -> (Self, Self::Return) {
         e.center_window();
         e.center_cursor();
-        e.window.set_cursor_grab(winit::window::CursorGrabMode::Confined).ok();
-        e.window.set_ime_allowed(true);
-        e.window.set_cursor_visible(false);
-        e.window.focus_window();
+        if let Some(window) = &e.window {
+            window.set_cursor_grab(winit::window::CursorGrabMode::Confined).ok();
+            window.set_ime_allowed(true);
+            window.set_cursor_visible(false);
+            window.focus_window();
+        }
         let values = CameraValues::default();
         (
             Self {
@@ -60,13 +64,15 @@ impl<'s> Script<'s> for ThirdPersonCamera {
 
         let position = target + rotation * Vec3::new(0., 0., *self.distance);
 
-        let ws = self.e.window.inner_size();
-        let aspect = ws.width as f32 / ws.height as f32;
-        let proj = Mat4x4::perspective(aspect, aspect, 0.01, 100.);
-        let view = Mat4x4::look_at(position, target);
-        self.e.update_camera_buffer(CameraBinding {
-            matrix: (proj * view).into(),
-            position: position.extend(1.).into()
+        let ws = self.e.window_size();
+        let aspect = ws.x / ws.y;
+        self.e.update_camera(Camera {
+            position,
+            target,
+            fov: CAM_FOV,
+            aspect,
+            near: 0.01,
+            far: 100.
         })
     }
 }
\ No newline at end of file
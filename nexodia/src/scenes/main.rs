@@ -1,12 +1,21 @@
 use std::sync::Arc;
 use winit::event::VirtualKeyCode;
-use engine::{Engine, Script, Quaternion, Light, ObjectRenderer, ScriptInstance, utils::{Id, new_render_pass}};
+use engine::{
+    Engine, Script, Quaternion, DirectionalLight, ScriptInstance, AnimationBank, CrowdAgent, Instances,
+    InstancesRenderer, SimpleTransform, Vec3, LightClusters, PointLight, SpotLight, Environment, Model, ModelRenderer,
+    RenderGraph, TextureDesc, DEPTH_FORMAT, HDR_FORMAT, PostProcess, Oit, Transparency, TransparentQueue, compiler,
+    Bvh, Aabb, Mat4x4, Batcher, BatchRenderer, ShadowQuality, WindowMode, Sky, Fog, TimeOfDay, Ssao, IrradianceVolume,
+    Terrain, TerrainMaterial, TerrainRenderer, TerrainSettings, Heightfield, utils::Id
+};
 
 use crate::{
-    objects::{BasicObject, Character, ThirdPersonCamera, MainCharacter, CameraValues},
-    shaders::Shaders
+    objects::{Character, ThirdPersonCamera, MainCharacter, CameraValues},
+    shaders::{Shaders, crowd, standard}
 };
 
+/// Base colors of the grass, dirt, rock and snow layers of the terrain
+const TERRAIN_LAYERS: [[u8;3];4] = [[74, 104, 46], [112, 86, 58], [116, 112, 106], [236, 240, 246]];
+
 assets!(
     animations [
         male/animations/idle,
@@ -16,21 +25,63 @@ assets!(
         male/animations/walk_left
     ]
     meshes [
-        male/base/base  > engine::vertex::pnj::Vertex,
-        geometries/cube > engine::vertex::pu::Vertex
+        male/base/base  > engine::vertex::pnj::Vertex
     ]
     textures [
         textures/grass
     ]
+    models [
+        geometries/cube > engine::vertex::pnu::Vertex
+    ]
+    heightmaps [
+        terrain/heightmap
+    ]
 );
 
 pub struct Scene {
     e: &'static Engine,
     _camera: ScriptInstance<CameraValues>,
     shaders: Shaders,
-    scenary: Vec<BasicObject>,
-    dir_light: Light,
-    main_char: ScriptInstance<Character>
+    /// Samples per pixel `shaders` were created with
+    samples: u32,
+    /// Quality `dir_light` was created with
+    shadow_quality: ShadowQuality,
+    /// Whether the characters were created compute skinned
+    compute_skinning: bool,
+    /// F11 was down last update, it toggles fullscreen when pressed
+    fullscreen_key: bool,
+    scenary: Vec<Model<engine::vertex::pnu::Vertex>>,
+    scenary_bvh: Bvh,
+    /// Scenary visible from the camera, refreshed by `update` with the batches drawn from it
+    visible_scenary: Vec<usize>,
+    opaque_batches: Batcher<standard::main::Shader>,
+    cutout_batches: Batcher<standard::cutout::Shader>,
+    /// Shadow casters of each cascade
+    caster_batches: Vec<Batcher<standard::dir_light::Shader>>,
+    terrain: Terrain,
+    /// Terrain chunks visible from the camera and casting shadows in each cascade, refreshed with the batches
+    visible_terrain: Vec<usize>,
+    terrain_casters: Vec<Vec<usize>>,
+    transparency: Transparency,
+    oit: Oit,
+    dir_light: DirectionalLight,
+    environment: Environment,
+    /// Baked over the scenary around the spawn
+    probes: IrradianceVolume,
+    ssao: Ssao,
+    sky: Sky,
+    time_of_day: TimeOfDay,
+    /// Fog in daylight, darkened at night
+    fog: Fog,
+    post: PostProcess,
+    lights: LightClusters,
+    main_char: ScriptInstance<Character>,
+    crowd_bank: AnimationBank,
+    crowd_agents: Vec<CrowdAgent>,
+    /// Bounds of an agent around its transform, wide enough for the animated limbs
+    crowd_bounds: Aabb,
+    crowd: Instances<crowd::main::Shader>,
+    crowd_light: Instances<crowd::dir_light::Shader>
 }
 impl<'s> Script<'s> for Scene {
     type Params = ();
@@ -38,57 +89,333 @@ impl<'s> Script<'s> for Scene {
     const NAME: &'static str = "MainScene";
     fn new(e: &'static Engine, _id: Id, _params: Self::Params) -> (Self, Self::Return) {
         let camera = e.new_script::<ThirdPersonCamera>(());
-        let dir_light = Light::new(e, Quaternion::from_angle_x(0.), (1024, 1024));
+        let settings = e.graphics_settings();
+        let dir_light = DirectionalLight::new(
+            e,
+            Quaternion::from_to(Vec3::new(0., 0., 1.), Vec3::new(-1., -1., -1.)),
+            settings.shadow_quality.resolution(),
+            settings.shadow_quality.cascades()
+        );
+
+        let environment = Environment::gradient(e, "#6b9bd8", "#d6e4f0", "#4a4036");
+        let sky = Sky::procedural(e, Default::default());
+        let fog = Fog {
+            height_falloff: 0.3,
+            start: 10.,
+            ..Fog::new("#b7c8d8", 0.01)
+        };
+
+        let mut lights = LightClusters::new(e, &dir_light);
+        for i in 0..4 {
+            lights.push(PointLight::new(Vec3::new(i as f32 * 2. - 3., 2., 4.), "#ffb46b", 4., 6.))
+        }
+        lights.push(SpotLight::new(Vec3::new(0., 4., 0.), Vec3::new(0., -1., 0.), "#ffffff", 8., 8., 0.5));
 
         let assets = Arc::new(Assets::new(e));
+        let terrain = terrain(e, assets.terrain_heightmap.clone());
         
         let main_char = e.new_script::<MainCharacter>((
             assets.clone(),
             assets.male_base_base.clone(),
-            &dir_light,
-            camera.0.clone()
+            camera.0.clone(),
+            terrain.heightfield.clone()
         ));
+
+        let crowd_bank = AnimationBank::new(
+            e,
+            assets.male_base_base.skeleton.as_ref().unwrap(),
+            &[assets.male_animations_idle.clone(), assets.male_animations_walk_forward.clone()]
+        );
+        let crowd_agents = (0..16)
+            .map(|i| {
+                let (x, z) = ((i % 4) as f32 - 1.5, (i / 4) as f32 + 2.);
+                let y = terrain.height_at(x, z).unwrap_or_default();
+                let mut agent = CrowdAgent::new(
+                    SimpleTransform::new(Vec3::new(x, y, z), Quaternion::default()),
+                    i % crowd_bank.clips.len()
+                );
+                agent.time = i as f32 * 7.;
+                agent
+            })
+            .collect::<Vec<_>>();
+        let instances = crowd_agents.iter().map(|agent| crowd_bank.instance(agent)).collect::<Vec<_>>();
+        let crowd = e.create_instances::<crowd::main::Shader>(
+            assets.male_base_base.clone(),
+            crowd::main::Material::new(e, &crowd_bank, "#9e7ed6"),
+            Some(instances.clone())
+        );
+        let crowd_light = e.create_instances::<crowd::dir_light::Shader>(
+            assets.male_base_base.clone(),
+            crowd::dir_light::Material::new(e, &crowd_bank),
+            Some(instances)
+        );
+        let bounds = assets.male_base_base.bounds;
+        let crowd_bounds = Aabb::around(bounds.center(), bounds.half_extents() * 1.5);
+
+        let mut cube = assets.geometries_cube_model.clone();
+        cube.materials = cube.materials.iter()
+            .map(|material| material.with_base_color(e, assets.textures_grass.clone()).into())
+            .collect();
+        let scenary = vec![cube];
+        let scenary_bvh = Bvh::new(scenary.iter().map(|model| model.mesh.bounds).collect());
+        let caster_batches = dir_light.cascades.iter().map(|_| e.create_batcher()).collect();
+        // Rays hitting the scenary or the grass see the environment reflected by its base color, the sky blurred by the
+        // irradiance harmonics otherwise. The grass of `TERRAIN_LAYERS` in linear space
+        let grass = Vec3::new(0.07, 0.14, 0.03);
+        let probes = IrradianceVolume::bake(e, Aabb::new(Vec3::new(-6., 0., -6.), Vec3::new(6., 3., 6.)), [6, 3, 6], |origin, direction| {
+            let scenary = scenary_bvh.raycast(origin, direction).map(|(i, distance)| {
+                let [r, g, b, _] = scenary[i].materials[0].factors.base_color;
+                (distance, Vec3::new(r, g, b))
+            });
+            let ground = terrain.heightfield.raycast(origin, direction, 16.).map(|distance| (distance, grass));
+            match [scenary, ground].into_iter().flatten().min_by(|a, b| a.0.total_cmp(&b.0)) {
+                Some((_, base_color)) => environment.irradiance(direction * -1.).mul_element_wise(base_color),
+                None => environment.irradiance(direction)
+            }
+        });
         
         (
             Self {
                 e,
                 _camera: camera,
+                samples: e.sample_count(),
+                shadow_quality: settings.shadow_quality,
+                compute_skinning: settings.compute_skinning,
+                fullscreen_key: false,
                 shaders: Shaders::new(e),
-                scenary: vec![
-                    BasicObject::new(e, &dir_light, assets.textures_grass.clone(), assets.geometries_cube.clone())
-                ],
+                scenary,
+                scenary_bvh,
+                visible_scenary: Vec::new(),
+                opaque_batches: e.create_batcher(),
+                cutout_batches: e.create_batcher(),
+                terrain_casters: dir_light.cascades.iter().map(|_| Vec::new()).collect(),
+                caster_batches,
+                terrain,
+                visible_terrain: Vec::new(),
+                transparency: Transparency::Sorted,
+                oit: Oit::new(e),
                 dir_light,
-                main_char
+                environment,
+                probes,
+                ssao: Ssao::new(e),
+                sky,
+                time_of_day: Default::default(),
+                fog,
+                post: PostProcess::new(e),
+                lights,
+                main_char,
+                crowd_bank,
+                crowd_agents,
+                crowd_bounds,
+                crowd,
+                crowd_light
             },
             ()
         )
     }
+    fn update(&mut self) {
+        let fullscreen_key = self.e.pressed_keys[VirtualKeyCode::F11];
+        if fullscreen_key && !self.fullscreen_key {
+            let mut settings = self.e.graphics_settings();
+            settings.window_mode = match settings.window_mode {
+                WindowMode::Windowed => WindowMode::Borderless,
+                _ => WindowMode::Windowed
+            };
+            self.e.set_graphics_settings(settings)
+        }
+        self.fullscreen_key = fullscreen_key;
+
+        let delta_time = self.e.time.delta();
+        for (i, agent) in self.crowd_agents.iter_mut().enumerate() {
+            agent.update(&self.crowd_bank.clips, delta_time);
+            let instance = self.crowd_bank.instance(agent);
+            self.crowd[i] = instance;
+            self.crowd_light[i] = instance
+        }
+        let lighting = self.time_of_day.apply(self.e, &mut self.dir_light, &mut self.sky);
+        self.environment.set_intensity(self.e, lighting.ambient);
+        self.probes.intensity = lighting.ambient;
+        self.probes.enabled = self.e.graphics_settings().light_probes;
+        self.probes.update(self.e);
+        self.e.set_fog(Fog {
+            color: self.fog.color.map(|v| v * lighting.ambient),
+            inscattering: lighting.light_color.map(|v| v * lighting.light_intensity * 0.8),
+            light_direction: lighting.light_direction,
+            ..self.fog
+        });
+        self.dir_light.update(self.e);
+        let bounds = self.crowd_bounds;
+        let instance_bounds = |instance: &engine::AnimatedInstance| bounds.transformed(Mat4x4::from(instance.transform));
+        self.crowd.cull(&[self.e.camera().frustum()], instance_bounds);
+        let cascades = self.dir_light.cascades.iter().map(|cascade| cascade.frustum()).collect::<Vec<_>>();
+        self.crowd_light.cull(&cascades, instance_bounds);
+        self.crowd.update(self.e);
+        self.crowd_light.update(self.e);
+        self.terrain.update(self.e, self.e.camera().position);
+        self.update_batches();
+        self.lights.update(self.e)
+    }
+    fn settings_changed(&mut self) {
+        let settings = self.e.graphics_settings();
+        if settings.shadow_quality != self.shadow_quality || settings.compute_skinning != self.compute_skinning {
+            // The shadow map is bound with the light clusters and the characters are skinned by other shaders,
+            // the scene is created again
+            return self.e.set_scene::<Scene>(())
+        }
+        self.samples = self.e.sample_count();
+        self.shaders = Shaders::new(self.e);
+        self.sky.settings_changed(self.e)
+    }
     fn render(&mut self) {
         if self.e.pressed_keys[VirtualKeyCode::Escape] { self.e.exit() }
-        self.e.render(move |encoder| {
-            {
-                let mut render_pass = new_render_pass(
-                    encoder,
-                    None,
-                    Some(&self.dir_light.depth_texture.view)
-                );
-                self.shaders.character.dir_light.render_object(&mut render_pass, &self.main_char.0.dir_light);
-                for object in self.scenary.iter() {
-                    self.shaders.basic.dir_light.render_object(&mut render_pass, &object.dir_light)
+        self.sky.update(self.e);
+        let s = &*self;
+        let render_scale = s.e.render_scale();
+        let mut graph = RenderGraph::new();
+        let shadow_map = graph.import("shadow map", &s.dir_light.depth_texture.view);
+        let color = graph.create("scene color", TextureDesc::new(HDR_FORMAT).scaled(render_scale).samples(s.samples));
+        let depth = graph.create("depth", TextureDesc::new(DEPTH_FORMAT).scaled(render_scale).samples(s.samples));
+        let hdr = if s.samples > 1 { graph.create("hdr", TextureDesc::new(HDR_FORMAT).scaled(render_scale)) } else { color };
+        graph.add_pass("skinning").execute(|ctx| s.main_char.0.skin(s.e, ctx.encoder));
+        // The main character follows the camera and is never culled
+        graph.add_pass("shadows")
+            .write(shadow_map)
+            .execute(|ctx| for (cascade, casters) in s.caster_batches.iter().enumerate() {
+                let mut render_pass = s.dir_light.cascade_pass(ctx.encoder, cascade);
+                s.main_char.0.render_shadow(&s.shaders.character, &mut render_pass);
+                s.shaders.crowd.dir_light.render_instances(&mut render_pass, &s.crowd_light);
+                s.shaders.terrain.dir_light.render_terrain(&mut render_pass, &s.terrain, &s.terrain_casters[cascade]);
+                s.shaders.standard.dir_light.render_batches(&mut render_pass, casters)
+            });
+        let prepass = Ssao::prepass(&mut graph, render_scale);
+        graph.add_pass("prepass")
+            .write(prepass.normals)
+            .write(prepass.depth)
+            .execute(move |ctx| {
+                let mut render_pass = ctx.render_pass(&[prepass.normals], Some(prepass.depth));
+                render_pass.set_bind_group(0, &s.e.camera_buffer.bind_group, &[]);
+                s.main_char.0.render_prepass(&s.shaders.character, &mut render_pass);
+                s.shaders.crowd.prepass.render_instances(&mut render_pass, &s.crowd);
+                s.shaders.terrain.prepass.render_terrain(&mut render_pass, &s.terrain, &s.visible_terrain);
+                s.shaders.standard.prepass.render_batches(&mut render_pass, &s.opaque_batches);
+                s.shaders.standard.prepass.render_batches(&mut render_pass, &s.cutout_batches)
+            });
+        let occlusion = s.ssao.add_passes(s.e, &mut graph, prepass);
+        graph.add_pass("main")
+            .read(shadow_map)
+            .read(occlusion)
+            .write(color)
+            .write(depth)
+            .resolve(color, hdr)
+            .execute(move |ctx| {
+                let environment = s.environment.bind_group(s.e, &s.probes, ctx.view(occlusion));
+                let mut render_pass = ctx.render_pass(&[color], Some(depth));
+                render_pass.set_bind_group(0, &s.e.camera_buffer.bind_group, &[]);
+                render_pass.set_bind_group(2, &s.lights.bind_group, &[]);
+                render_pass.set_bind_group(3, &environment, &[]);
+                s.main_char.0.render(&s.shaders.character, &mut render_pass);
+                s.shaders.crowd.main.render_instances(&mut render_pass, &s.crowd);
+                s.shaders.terrain.main.render_terrain(&mut render_pass, &s.terrain, &s.visible_terrain);
+                s.shaders.standard.main.render_batches(&mut render_pass, &s.opaque_batches);
+                s.shaders.standard.cutout.render_batches(&mut render_pass, &s.cutout_batches);
+                s.sky.render(&mut render_pass);
+                if s.transparency == Transparency::Sorted {
+                    let mut queue = TransparentQueue::new(&s.e.camera());
+                    for i in s.visible_scenary.iter().copied() {
+                        s.shaders.standard.transparent.queue_model(&mut queue, &s.scenary[i])
+                    }
+                    queue.render(&mut render_pass)
                 }
+            });
+        if s.transparency == Transparency::OrderIndependent {
+            let targets = Oit::targets(&mut graph, s.samples, render_scale);
+            targets.write(graph.add_pass("transparent"))
+                .read(occlusion)
+                .write(depth)
+                .execute(move |ctx| {
+                    let environment = s.environment.bind_group(s.e, &s.probes, ctx.view(occlusion));
+                    let mut render_pass = ctx.render_pass(&targets.colors(), Some(depth));
+                    render_pass.set_bind_group(0, &s.e.camera_buffer.bind_group, &[]);
+                    render_pass.set_bind_group(2, &s.lights.bind_group, &[]);
+                    render_pass.set_bind_group(3, &environment, &[]);
+                    for i in s.visible_scenary.iter().copied() {
+                        s.shaders.standard.oit.render_model(&mut render_pass, &s.scenary[i])
+                    }
+                });
+            s.oit.add_composite(s.e, &mut graph, targets, hdr);
+        }
+        s.post.add_passes(s.e, &mut graph, hdr);
+        self.e.render_graph(graph);
+    }
+}
+impl Scene {
+    /// Culls the scenary and the terrain, and batches the visible opaque and cutout surfaces and the casters of each cascade
+    fn update_batches(&mut self) {
+        self.visible_scenary = self.scenary_bvh.cull(&[self.e.camera().frustum()]);
+        self.visible_terrain = self.terrain.cull(&[self.e.camera().frustum()]);
+        self.opaque_batches.clear();
+        self.cutout_batches.clear();
+        for i in self.visible_scenary.iter().copied() {
+            self.opaque_batches.push_model(&self.scenary[i], ());
+            self.cutout_batches.push_model(&self.scenary[i], ())
+        }
+        self.opaque_batches.update(self.e);
+        self.cutout_batches.update(self.e);
+        let cascades = self.dir_light.cascades.iter().zip(self.terrain_casters.iter_mut());
+        for ((cascade, terrain), casters) in cascades.zip(self.caster_batches.iter_mut()) {
+            *terrain = self.terrain.cull(&[cascade.frustum()]);
+            casters.clear();
+            for i in self.scenary_bvh.cull(&[cascade.frustum()]) {
+                casters.push_model(&self.scenary[i], ())
             }
-            let depth_texture = self.e.depth_texture.lock().unwrap();
-            let output_texture = self.e.output_texture.lock().unwrap();
-            let mut render_pass = new_render_pass(
-                encoder,
-                Some(&output_texture.view),
-                Some(&depth_texture.view)
-            );
-            render_pass.set_bind_group(0, &self.e.camera_buffer.bind_group, &[]);
-            self.shaders.character.main.render_object(&mut render_pass, &self.main_char.0.main);
-            for object in self.scenary.iter() {
-                self.shaders.basic.main.render_object(&mut render_pass, &object.main)
-            }
-        });
+            casters.update(self.e)
+        }
     }
+}
+
+/// Ground of the `terrain/heightmap` asset, hills around a flat clearing at the spawn, splatted with grass,
+/// dirt on the gentle slopes, rock on the steep ones and snow on the tops
+fn terrain(e: &Engine, heightmap: compiler::Heightmap) -> Terrain {
+    let (width, depth) = (heightmap.width, heightmap.depth);
+    let spacing = 1.;
+    let origin = Vec3::new(-0.5 * (width - 1) as f32, 0., -0.5 * (depth - 1) as f32) * spacing;
+    let heightfield = Heightfield::new(heightmap, origin, spacing);
+    let splat = (0..width * depth)
+        .flat_map(|i| {
+            let (x, z) = (i % width, i / width);
+            let (height, slope) = (heightfield.sample(x, z), 1. - heightfield.sample_normal(x, z).y);
+            let rock = (slope * 8. - 1.).clamp(0., 1.);
+            let snow = ((height - 14.) / 4.).clamp(0., 1.) * (1. - rock);
+            let dirt = (slope * 24. - 0.5).clamp(0., 1.) * (1. - rock) * (1. - snow);
+            let grass = (1. - rock - snow - dirt).max(0.);
+            [grass, dirt, rock, snow].map(|v| (v * 255.) as u8)
+        })
+        .collect();
+    // Layers of 16x16 texels with a little noise repeated every 4 meters
+    let layers = TERRAIN_LAYERS.iter()
+        .map(|color| compiler::Image {
+            width: 16,
+            height: 16,
+            pixels: compiler::Pixels::ARGB((0..256u32)
+                .flat_map(|i| {
+                    let noise = (i.wrapping_mul(2654435761) >> 27) as f32 / 31. * 0.3 + 0.85;
+                    [color[0], color[1], color[2]].map(|v| (v as f32 * noise).min(255.) as u8).into_iter().chain([255])
+                })
+                .collect())
+        })
+        .collect();
+    let material = TerrainMaterial::new(
+        e,
+        compiler::Image { width, height: depth, pixels: compiler::Pixels::ARGB(splat) },
+        layers,
+        [0.9, 0.95, 0.7, 0.4],
+        4.
+    );
+    Terrain::new(heightfield, material, TerrainSettings {
+        chunk_size: 32,
+        lods: 4,
+        lod_distance: 24.,
+        stream_distance: 160.,
+        ..Default::default()
+    })
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+use engine::DirectionalLight;
+
+shader!(
+    material    super::dir_light::Material
+    vertex      engine::vertex::pn::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&DirectionalLight::cascade_bgl(&e.device), &super::dir_light::bind_group_layouts(&e.device)]
+    frag_stage  false
+    depth_bias  2
+    slope_bias  2.
+    samples     1
+    source      "../main/shader.wgsl"
+    defines     ["COMPUTE_SKINNED", "DEPTH_ONLY"]
+);
+impl engine::ObjectRenderer for Shader {}
@@ -0,0 +1,16 @@
+shader!(
+    material    super::main::Material
+    vertex      engine::vertex::pn::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &super::main::bind_group_layouts(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
+    frag_stage  true
+    source      "../main/shader.wgsl"
+    defines     ["COMPUTE_SKINNED"]
+);
+impl engine::ObjectRenderer for Shader {}
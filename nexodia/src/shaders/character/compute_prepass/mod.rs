@@ -0,0 +1,13 @@
+shader!(
+    material    super::main::Material
+    vertex      engine::vertex::pn::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&e.camera_buffer.bgl, &super::main::bind_group_layouts(&e.device)]
+    frag_stage  true
+    samples     1
+    targets     [engine::NORMAL_FORMAT]
+    source      "../main/shader.wgsl"
+    defines     ["COMPUTE_SKINNED", "PREPASS"]
+);
+impl engine::ObjectRenderer for Shader {}
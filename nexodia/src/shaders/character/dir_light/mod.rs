@@ -1,19 +1,19 @@
-use engine::{Engine, Animator, Light};
+use engine::{Engine, Animator, DirectionalLight};
 
 bind_group_layouts!(
     Uniform(VERTEX)
-    Uniform(VERTEX)
+    Storage(VERTEX)
 );
 
 basic_material!(
-    (e: &Engine, animator: &Animator, light: &Light) {
+    (e: &Engine, animator: &Animator) {
         create_bind_group!(
             bind_group_layouts(&e.device)
             animator.buffer.as_entire_binding()
-            light.buffer.as_entire_binding()
+            e.skinning_pool.buffer.as_entire_binding()
         )
     }
-    bind_group_index 0
+    bind_group_index 1
 );
 
 shader!(
@@ -21,7 +21,12 @@ shader!(
     vertex      engine::vertex::pnj::Vertex
     instance    ()
     vbls        [Self::Vertex::LAYOUT]
-    bgls        [&bind_group_layouts(&e.device)]
+    bgls        [&DirectionalLight::cascade_bgl(&e.device), &bind_group_layouts(&e.device)]
     frag_stage  false
+    depth_bias  2
+    slope_bias  2.
+    samples     1
+    source      "../main/shader.wgsl"
+    defines     ["DEPTH_ONLY"]
 );
-impl engine::ObjectRenderer for Shader {}
\ No newline at end of file
+impl engine::ObjectRenderer for Shader {}
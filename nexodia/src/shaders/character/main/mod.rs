@@ -1,12 +1,10 @@
-use engine::{Engine, Animator, Light, utils::ToColor};
+use engine::{Engine, Animator, utils::ToColor};
 use wgpu::BufferUsages;
 
 bind_group_layouts!(
     Uniform(FRAGMENT)
-    Uniform(VERTEX_FRAGMENT)
-    Uniform(FRAGMENT)
-    TextureView(FRAGMENT, Depth)
-    TextureSampler(FRAGMENT, Filtering)
+    Uniform(VERTEX)
+    Storage(VERTEX)
 );
 
 #[repr(C)]
@@ -15,7 +13,7 @@ pub struct MaterialBinding {
     pub color: [f32;4]
 }
 basic_material!(
-    (e: &Engine, animator: &Animator, light: &Light, color: impl ToColor) {
+    (e: &Engine, animator: &Animator, color: impl ToColor) {
         create_bind_group!(
             bind_group_layouts(&e.device)
             e.new_buffer(
@@ -25,9 +23,7 @@ basic_material!(
                 BufferUsages::UNIFORM
             ).as_entire_binding()
             animator.buffer.as_entire_binding()
-            light.buffer.as_entire_binding()
-            wgpu::BindingResource::TextureView(&light.depth_texture.view)
-            wgpu::BindingResource::Sampler(&light.depth_texture.sampler)
+            e.skinning_pool.buffer.as_entire_binding()
         )
     }
     bind_group_index 1
@@ -38,7 +34,12 @@ shader!(
     vertex      engine::vertex::pnj::Vertex
     instance    ()
     vbls        [Self::Vertex::LAYOUT]
-    bgls        [&e.camera_buffer.bgl, &bind_group_layouts(&e.device)]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &bind_group_layouts(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
     frag_stage  true
 );
-impl engine::ObjectRenderer for Shader {}
\ No newline at end of file
+impl engine::ObjectRenderer for Shader {}
@@ -1,17 +1,33 @@
+// Skinned by the vertex shaders from the joints of `engine::Animator`, or by `engine::ComputeSkinning` into the vertices
+// of an `engine::SkinnedMesh` with `COMPUTE_SKINNED`. `DEPTH_ONLY` renders the shadow cascades, `PREPASS` the normals of
+// `engine::Ssao`
+#ifdef COMPUTE_SKINNED
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>
+};
+#else
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) joints: vec4<u32>,
     @location(3) weights: vec4<f32>
 };
+#endif
 
-// Camera
-    struct Camera {
-        @location(0) perspective: mat4x4<f32>,
-        @location(1) position: vec4<f32>
-    };
-    @group(0) @binding(0)
-    var<uniform> camera: Camera;
+#ifdef DEPTH_ONLY
+#include <engine/cascade>
+#define ANIMATOR_BINDING 0
+#define JOINTS_BINDING 1
+#else
+#ifdef PREPASS
+#include <engine/camera>
+#else
+// Lights and environment
+    #include <engine/lighting>
+#endif
+#define ANIMATOR_BINDING 1
+#define JOINTS_BINDING 2
 
 // Material
     struct Material {
@@ -20,71 +36,66 @@ struct Vertex {
     @group(1) @binding(0)
     var<uniform> material: Material;
 
-    // Animator
-        struct Animator {
-            @location(0) joint: array<mat4x4<f32>, #MAX_JOINTS>
-        };
-        @group(1) @binding(1)
-        var<uniform> animator: Animator;
-
-    // Light
-        struct Light {
-            @location(0) perspective: mat4x4<f32>,
-            @location(1) direction: vec4<f32>
-        };
-        @group(1) @binding(2)
-        var<uniform> light: Light;
-        @group(1) @binding(3)
-        var light_shadow_texture: texture_depth_2d;
-        @group(1)@binding(4)
-        var light_shadow_sampler: sampler;
-
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) position: vec4<f32>,
-    @location(1) normal: vec3<f32>,
-    @location(2) @interpolate(flat) joints: vec4<u32>,
-    @location(3) @interpolate(flat) weights: vec4<f32>
+    @location(1) normal: vec3<f32>
 };
+#endif
+
+#ifdef COMPUTE_SKINNED
+fn skin(vertex: Vertex) -> mat4x4<f32> {
+    return mat4x4<f32>(
+        vec4<f32>(1., 0., 0., 0.),
+        vec4<f32>(0., 1., 0., 0.),
+        vec4<f32>(0., 0., 1., 0.),
+        vec4<f32>(0., 0., 0., 1.)
+    );
+}
+#else
+// Animator
+    struct Animator {
+        offset: u32,
+        joints: u32
+    };
+    @group(1) @binding(ANIMATOR_BINDING)
+    var<uniform> animator: Animator;
+    @group(1) @binding(JOINTS_BINDING)
+    var<storage, read> joints: array<mat4x4<f32>>;
+
+fn skin(vertex: Vertex) -> mat4x4<f32> {
+    return
+        joints[animator.offset + vertex.joints[0]] * vertex.weights[0] +
+        joints[animator.offset + vertex.joints[1]] * vertex.weights[1] +
+        joints[animator.offset + vertex.joints[2]] * vertex.weights[2] +
+        joints[animator.offset + vertex.joints[3]] * vertex.weights[3];
+}
+#endif
 
+#ifdef DEPTH_ONLY
+@vertex
+fn vs_main(vertex: Vertex) -> @builtin(position) vec4<f32> {
+    return cascade.perspective * skin(vertex) * vec4<f32>(vertex.position, 1.);
+}
+#else
 @vertex
 fn vs_main(vertex: Vertex) -> VertexOutput {
     var vout: VertexOutput;
-    let v4 = vec4<f32>(vertex.position, 1.);
-    vout.position = (
-        ((animator.joint[vertex.joints[0]] * v4) * vertex.weights[0]) +
-        ((animator.joint[vertex.joints[1]] * v4) * vertex.weights[1]) +
-        ((animator.joint[vertex.joints[2]] * v4) * vertex.weights[2]) +
-        ((animator.joint[vertex.joints[3]] * v4) * vertex.weights[3])
-    );
+    let transform = skin(vertex);
+    vout.position = transform * vec4<f32>(vertex.position, 1.);
     vout.clip_position = camera.perspective * vout.position;
-    vout.normal = vertex.normal;
-    vout.joints = vertex.joints;
-    vout.weights = vertex.weights;
+    vout.normal = normalize((transform * vec4<f32>(vertex.normal, 0.)).xyz);
     return vout;
 }
 
-fn mat4x4_to_mat3x3(in: mat4x4<f32>) -> mat3x3<f32> {
-    return mat3x3<f32>(in.x.xyz, in.y.xyz, in.z.xyz);
-}
-
 @fragment
 fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
-    let position = light.perspective * vin.position;
-    let light_texture_depth = textureSample(light_shadow_texture, light_shadow_sampler, vec2<f32>(
-        position.x * 0.5 + 0.5,
-        position.y * -0.5 + 0.5
-    ));
-    var light_shadow = 1.0;
-    if (light_texture_depth < position.z - 0.01) {
-        light_shadow = 0.5;
-    }
-    let normal = (
-        ((mat4x4_to_mat3x3(animator.joint[vin.joints[0]]) * vin.normal) * vin.weights[0]) +
-        ((mat4x4_to_mat3x3(animator.joint[vin.joints[1]]) * vin.normal) * vin.weights[1]) +
-        ((mat4x4_to_mat3x3(animator.joint[vin.joints[2]]) * vin.normal) * vin.weights[2]) +
-        ((mat4x4_to_mat3x3(animator.joint[vin.joints[3]]) * vin.normal) * vin.weights[3])
-    );
-    let normal_shadow = dot(normal, light.direction.xyz) * 0.5 + 0.5;
-    return vec4<f32>(material.color.xyz * light_shadow, 1.);
-}
\ No newline at end of file
+#ifdef PREPASS
+    return vec4<f32>(normalize(vin.normal), 1.);
+#else
+    // Matte skin and cloth
+    let color = lighting(vin.position.xyz, normalize(vin.normal), material.color.rgb, 0., 0.8, 1., camera.position.xyz);
+    return vec4<f32>(apply_fog(color, vin.position.xyz), 1.);
+#endif
+}
+#endif
@@ -2,6 +2,10 @@ use engine::Shader;
 join_modules!(
     Shaders {
         main: Shader
+        prepass: Shader
         dir_light: Shader
+        compute_main: Shader
+        compute_prepass: Shader
+        compute_dir_light: Shader
     }
-);
\ No newline at end of file
+);
@@ -0,0 +1,13 @@
+shader!(
+    material    super::main::Material
+    vertex      engine::vertex::pnj::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&e.camera_buffer.bgl, &super::main::bind_group_layouts(&e.device)]
+    frag_stage  true
+    samples     1
+    targets     [engine::NORMAL_FORMAT]
+    source      "../main/shader.wgsl"
+    defines     ["PREPASS"]
+);
+impl engine::ObjectRenderer for Shader {}
@@ -0,0 +1,30 @@
+use engine::{Engine, AnimationBank, DirectionalLight};
+
+bind_group_layouts!(
+    Storage(VERTEX)
+);
+
+basic_material!(
+    (e: &Engine, bank: &AnimationBank) {
+        create_bind_group!(
+            bind_group_layouts(&e.device)
+            bank.buffer.as_entire_binding()
+        )
+    }
+    bind_group_index 1
+);
+
+shader!(
+    material    Material
+    vertex      engine::vertex::pnj::Vertex
+    instance    engine::AnimatedInstance
+    vbls        [Self::Vertex::LAYOUT, engine::AnimatedInstance::LAYOUT]
+    bgls        [&DirectionalLight::cascade_bgl(&e.device), &bind_group_layouts(&e.device)]
+    frag_stage  false
+    depth_bias  2
+    slope_bias  2.
+    samples     1
+    source      "../main/shader.wgsl"
+    defines     ["DEPTH_ONLY"]
+);
+impl engine::InstancesRenderer for Shader {}
@@ -0,0 +1,43 @@
+use engine::{Engine, AnimationBank, utils::ToColor};
+use wgpu::BufferUsages;
+
+bind_group_layouts!(
+    Uniform(FRAGMENT)
+    Storage(VERTEX)
+);
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialBinding {
+    pub color: [f32;4]
+}
+basic_material!(
+    (e: &Engine, bank: &AnimationBank, color: impl ToColor) {
+        create_bind_group!(
+            bind_group_layouts(&e.device)
+            e.new_buffer(
+                bytemuck::bytes_of(&MaterialBinding {
+                    color: color.to_color().into()
+                }),
+                BufferUsages::UNIFORM
+            ).as_entire_binding()
+            bank.buffer.as_entire_binding()
+        )
+    }
+    bind_group_index 1
+);
+
+shader!(
+    material    Material
+    vertex      engine::vertex::pnj::Vertex
+    instance    engine::AnimatedInstance
+    vbls        [Self::Vertex::LAYOUT, engine::AnimatedInstance::LAYOUT]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &bind_group_layouts(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
+    frag_stage  true
+);
+impl engine::InstancesRenderer for Shader {}
@@ -0,0 +1,88 @@
+// Instances skinned from `engine::AnimationBank`, `DEPTH_ONLY` renders the shadow cascades, `PREPASS` the normals of `engine::Ssao`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) joints: vec4<u32>,
+    @location(3) weights: vec4<f32>
+};
+struct Instance {
+    @location(4) transform_0: vec4<f32>,
+    @location(5) transform_1: vec4<f32>,
+    @location(6) transform_2: vec4<f32>,
+    @location(7) transform_3: vec4<f32>,
+    @location(8) frames: vec2<u32>,
+    @location(9) blend: f32
+};
+
+#ifdef DEPTH_ONLY
+#include <engine/cascade>
+
+@group(1) @binding(0)
+var<storage, read> bank: array<mat4x4<f32>>;
+#else
+#ifdef PREPASS
+#include <engine/camera>
+#else
+// Lights and environment
+    #include <engine/lighting>
+#endif
+
+// Material
+    struct Material {
+        @location(0) color: vec4<f32>
+    };
+    @group(1) @binding(0)
+    var<uniform> material: Material;
+
+    // Animation bank
+        @group(1) @binding(1)
+        var<storage, read> bank: array<mat4x4<f32>>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec4<f32>,
+    @location(1) normal: vec3<f32>
+};
+#endif
+
+fn joint(instance: Instance, i: u32) -> mat4x4<f32> {
+    return bank[instance.frames.x + i] * (1. - instance.blend) + bank[instance.frames.y + i] * instance.blend;
+}
+
+fn skin(vertex: Vertex, instance: Instance) -> mat4x4<f32> {
+    let transform = mat4x4<f32>(instance.transform_0, instance.transform_1, instance.transform_2, instance.transform_3);
+    return transform * (
+        joint(instance, vertex.joints[0]) * vertex.weights[0] +
+        joint(instance, vertex.joints[1]) * vertex.weights[1] +
+        joint(instance, vertex.joints[2]) * vertex.weights[2] +
+        joint(instance, vertex.joints[3]) * vertex.weights[3]
+    );
+}
+
+#ifdef DEPTH_ONLY
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> @builtin(position) vec4<f32> {
+    return cascade.perspective * skin(vertex, instance) * vec4<f32>(vertex.position, 1.);
+}
+#else
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> VertexOutput {
+    var vout: VertexOutput;
+    let transform = skin(vertex, instance);
+    vout.position = transform * vec4<f32>(vertex.position, 1.);
+    vout.clip_position = camera.perspective * vout.position;
+    vout.normal = normalize((transform * vec4<f32>(vertex.normal, 0.)).xyz);
+    return vout;
+}
+
+@fragment
+fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
+#ifdef PREPASS
+    return vec4<f32>(normalize(vin.normal), 1.);
+#else
+    // Matte skin and cloth
+    let color = lighting(vin.position.xyz, normalize(vin.normal), material.color.rgb, 0., 0.8, 1., camera.position.xyz);
+    return vec4<f32>(apply_fog(color, vin.position.xyz), 1.);
+#endif
+}
+#endif
@@ -2,6 +2,7 @@ use engine::Shader;
 join_modules!(
     Shaders {
         main: Shader
+        prepass: Shader
         dir_light: Shader
     }
-);
\ No newline at end of file
+);
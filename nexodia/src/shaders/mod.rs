@@ -1,6 +1,8 @@
 join_modules!(
     Shaders {
-        basic: Shaders
         character: Shaders
+        crowd: Shaders
+        standard: Shaders
+        terrain: Shaders
     }
 );
\ No newline at end of file
@@ -0,0 +1,20 @@
+use engine::DirectionalLight;
+
+shader!(
+    material    engine::PbrMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&DirectionalLight::cascade_bgl(&e.device), &engine::PbrMaterial::bgl(&e.device)]
+    frag_stage  true
+    targets     []
+    depth_bias  2
+    slope_bias  2.
+    samples     1
+    source      "../main/shader.wgsl"
+    defines     ["DEPTH_ONLY"]
+);
+impl engine::ModelRenderer for Shader {
+    const ALPHA_MODES: &'static [engine::AlphaMode] = &[engine::AlphaMode::Opaque, engine::AlphaMode::Mask];
+}
+impl engine::BatchRenderer for Shader {}
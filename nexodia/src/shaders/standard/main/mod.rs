@@ -0,0 +1,15 @@
+shader!(
+    material    engine::PbrMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &engine::PbrMaterial::bgl(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
+    frag_stage  true
+);
+impl engine::ModelRenderer for Shader {}
+impl engine::BatchRenderer for Shader {}
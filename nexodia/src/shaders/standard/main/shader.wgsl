@@ -0,0 +1,74 @@
+// `DEPTH_ONLY` renders the shadow cascades, `OIT` the order independent transparency, `PREPASS` the normals
+// of `engine::Ssao`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>
+};
+
+#ifdef DEPTH_ONLY
+#include <engine/cascade>
+#include <engine/material>
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+};
+
+@vertex
+fn vs_main(vertex: Vertex) -> VertexOutput {
+    var vout: VertexOutput;
+    vout.clip_position = cascade.perspective * vec4<f32>(vertex.position, 1.);
+    vout.uv = vertex.uv;
+    return vout;
+}
+
+// Cutout of the masked materials, the opaque ones have no alpha cutoff and never discard
+@fragment
+fn fs_main(vin: VertexOutput) {
+    let alpha = material.base_color.a * textureSample(material_base_color, material_sampler, vin.uv).a;
+    if (alpha < material.alpha_cutoff) {
+        discard;
+    }
+}
+#else
+#include <engine/camera>
+
+// Material, lights and environment
+    #include <engine/pbr>
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>
+};
+
+@vertex
+fn vs_main(vertex: Vertex) -> VertexOutput {
+    var vout: VertexOutput;
+    vout.position = vertex.position;
+    vout.clip_position = camera.perspective * vec4<f32>(vertex.position, 1.);
+    vout.normal = vertex.normal;
+    vout.uv = vertex.uv;
+    return vout;
+}
+
+#ifdef OIT
+#include <engine/oit>
+
+@fragment
+fn fs_main(vin: VertexOutput) -> OitOutput {
+    return oit_output(pbr(vin.position, vin.normal, vin.uv, camera.position.xyz), vin.clip_position.z);
+}
+#else
+@fragment
+fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
+#ifdef PREPASS
+    return vec4<f32>(pbr_prepass(vin.position, vin.normal, vin.uv), 1.);
+#else
+    return pbr(vin.position, vin.normal, vin.uv, camera.position.xyz);
+#endif
+}
+#endif
+#endif
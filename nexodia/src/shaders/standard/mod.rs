@@ -0,0 +1,11 @@
+use engine::Shader;
+join_modules!(
+    Shaders {
+        main: Shader
+        cutout: Shader
+        transparent: Shader
+        oit: Shader
+        prepass: Shader
+        dir_light: Shader
+    }
+);
@@ -0,0 +1,21 @@
+shader!(
+    material    engine::PbrMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &engine::PbrMaterial::bgl(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
+    frag_stage  true
+    cull        none
+    blend       oit
+    depth_write false
+    source      "../main/shader.wgsl"
+    defines     ["OIT", "TRANSPARENT"]
+);
+impl engine::ModelRenderer for Shader {
+    const ALPHA_MODES: &'static [engine::AlphaMode] = &[engine::AlphaMode::Blend];
+}
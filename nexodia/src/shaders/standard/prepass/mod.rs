@@ -0,0 +1,17 @@
+shader!(
+    material    engine::PbrMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&e.camera_buffer.bgl, &engine::PbrMaterial::bgl(&e.device)]
+    frag_stage  true
+    cull        none
+    samples     1
+    targets     [engine::NORMAL_FORMAT]
+    source      "../main/shader.wgsl"
+    defines     ["PREPASS"]
+);
+impl engine::ModelRenderer for Shader {
+    const ALPHA_MODES: &'static [engine::AlphaMode] = &[engine::AlphaMode::Opaque, engine::AlphaMode::Mask];
+}
+impl engine::BatchRenderer for Shader {}
@@ -0,0 +1,16 @@
+use engine::DirectionalLight;
+
+shader!(
+    material    engine::TerrainMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&DirectionalLight::cascade_bgl(&e.device), &engine::TerrainMaterial::bgl(&e.device)]
+    frag_stage  false
+    depth_bias  2
+    slope_bias  2.
+    samples     1
+    source      "../main/shader.wgsl"
+    defines     ["DEPTH_ONLY"]
+);
+impl engine::TerrainRenderer for Shader {}
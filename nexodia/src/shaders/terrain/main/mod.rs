@@ -0,0 +1,14 @@
+shader!(
+    material    engine::TerrainMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [
+        &e.camera_buffer.bgl,
+        &engine::TerrainMaterial::bgl(&e.device),
+        &engine::LightClusters::bgl(&e.device),
+        &engine::Environment::bgl(&e.device)
+    ]
+    frag_stage  true
+);
+impl engine::TerrainRenderer for Shader {}
@@ -0,0 +1,88 @@
+// Layers of `engine::TerrainMaterial` blended by its splat map, `DEPTH_ONLY` renders the shadow cascades and
+// `PREPASS` the normals of `engine::Ssao`
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>
+};
+
+#ifdef DEPTH_ONLY
+#include <engine/cascade>
+
+@vertex
+fn vs_main(vertex: Vertex) -> @builtin(position) vec4<f32> {
+    return cascade.perspective * vec4<f32>(vertex.position, 1.);
+}
+#else
+#include <engine/camera>
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    // Over the whole terrain
+    @location(2) uv: vec2<f32>
+};
+
+@vertex
+fn vs_main(vertex: Vertex) -> VertexOutput {
+    var vout: VertexOutput;
+    vout.position = vertex.position;
+    vout.clip_position = camera.perspective * vec4<f32>(vertex.position, 1.);
+    vout.normal = vertex.normal;
+    vout.uv = vertex.uv;
+    return vout;
+}
+
+#ifdef PREPASS
+@fragment
+fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(vin.normal), 1.);
+}
+#else
+// Lights and environment
+    #include <engine/lighting>
+
+struct TerrainFactors {
+    roughness: vec4<f32>,
+    layer_size: f32,
+    layers: u32
+};
+@group(1) @binding(0)
+var<uniform> terrain: TerrainFactors;
+@group(1) @binding(1)
+var terrain_splat: texture_2d<f32>;
+@group(1) @binding(2)
+var terrain_layers: texture_2d_array<f32>;
+@group(1) @binding(3)
+var terrain_layer_sampler: sampler;
+@group(1) @binding(4)
+var terrain_splat_sampler: sampler;
+
+@fragment
+fn fs_main(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let splat = textureSample(terrain_splat, terrain_splat_sampler, vin.uv);
+    let uv = vin.position.xz / terrain.layer_size;
+    var base_color = vec3<f32>(0.);
+    var roughness = 0.;
+    var weights = 0.;
+    for (var i = 0u; i < terrain.layers; i++) {
+        let weight = splat[i];
+        base_color += textureSample(terrain_layers, terrain_layer_sampler, uv, i32(i)).rgb * weight;
+        roughness += terrain.roughness[i] * weight;
+        weights += weight;
+    }
+    let total = max(weights, 0.0001);
+    let color = lighting(
+        vin.position,
+        normalize(vin.normal),
+        base_color / total,
+        0.,
+        clamp(roughness / total, 0.03, 1.),
+        1.,
+        camera.position.xyz
+    );
+    return vec4<f32>(apply_fog(color, vin.position), 1.);
+}
+#endif
+#endif
@@ -0,0 +1,8 @@
+use engine::Shader;
+join_modules!(
+    Shaders {
+        main: Shader
+        prepass: Shader
+        dir_light: Shader
+    }
+);
@@ -0,0 +1,13 @@
+shader!(
+    material    engine::TerrainMaterial
+    vertex      engine::vertex::pnu::Vertex
+    instance    ()
+    vbls        [Self::Vertex::LAYOUT]
+    bgls        [&e.camera_buffer.bgl, &engine::TerrainMaterial::bgl(&e.device)]
+    frag_stage  true
+    samples     1
+    targets     [engine::NORMAL_FORMAT]
+    source      "../main/shader.wgsl"
+    defines     ["PREPASS"]
+);
+impl engine::TerrainRenderer for Shader {}
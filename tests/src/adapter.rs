@@ -0,0 +1,45 @@
+use engine::AdapterOptions;
+use wgpu::{AdapterInfo, Backend, Backends, DeviceType, Features};
+
+fn info(name: &str, backend: Backend, device_type: DeviceType) -> AdapterInfo {
+    AdapterInfo {
+        name: name.into(),
+        vendor: 0,
+        device: 0,
+        device_type,
+        driver: String::new(),
+        driver_info: String::new(),
+        backend
+    }
+}
+
+#[test]
+fn rank() {
+    let options = AdapterOptions {
+        backends: vec![Backends::VULKAN, Backends::GL],
+        name: None,
+        ..Default::default()
+    };
+    let vulkan_cpu = info("llvmpipe", Backend::Vulkan, DeviceType::Cpu);
+    let vulkan_gpu = info("Radeon", Backend::Vulkan, DeviceType::DiscreteGpu);
+    let gl_gpu = info("Radeon", Backend::Gl, DeviceType::DiscreteGpu);
+    // Backend order first, then device type
+    assert!(options.rank(&vulkan_gpu) < options.rank(&vulkan_cpu));
+    assert!(options.rank(&vulkan_cpu) < options.rank(&gl_gpu));
+    // A matching name wins over both
+    let named = AdapterOptions { name: Some("LLVM".into()), ..options };
+    assert!(named.rank(&vulkan_cpu) < named.rank(&vulkan_gpu));
+}
+
+#[test]
+fn reject() {
+    let options = AdapterOptions {
+        required_features: Features::MULTI_DRAW_INDIRECT,
+        ..AdapterOptions::software()
+    };
+    let cpu = info("llvmpipe", Backend::Gl, DeviceType::Cpu);
+    assert_eq!(options.reject(&cpu, Features::MULTI_DRAW_INDIRECT, true), None);
+    assert!(options.reject(&cpu, Features::MULTI_DRAW_INDIRECT, false).is_some());
+    assert!(options.reject(&cpu, Features::empty(), true).is_some());
+    assert!(options.reject(&info("Radeon", Backend::Vulkan, DeviceType::DiscreteGpu), Features::all(), true).is_some());
+}
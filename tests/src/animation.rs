@@ -0,0 +1,83 @@
+use std::{sync::Arc, collections::HashMap};
+use engine::{
+    compiler::{Skeleton, SkeletonJoint},
+    Animation, AnimationFrame, AnimationEvent, AnimationJoint, Retarget, SimpleTransform, Vec3, Quaternion, Mat4x4
+};
+
+fn animation(frames: usize, events: &[(&str, f32)]) -> Animation {
+    Animation {
+        id: 0,
+        frames: vec![AnimationFrame::default(); frames].into(),
+        events: events.iter()
+            .map(|(name, frame)| AnimationEvent { name: name.to_string(), frame: *frame })
+            .collect::<Vec<_>>()
+            .into(),
+        joints: Default::default(),
+        keep_root_translation_axis: Default::default()
+    }
+}
+
+fn names(events: Vec<&AnimationEvent>) -> Vec<&str> {
+    events.into_iter().map(|event| event.name.as_str()).collect()
+}
+
+#[test]
+fn events() {
+    let a = animation(30, &[("start", 0.), ("left", 10.), ("right", 25.)]);
+    assert!(names(a.events_between(0., 1.)) == ["start"]);
+    assert!(names(a.events_between(1., 9.)).is_empty());
+    assert!(names(a.events_between(9., 1.5)) == ["left"]);
+    assert!(names(a.events_between(20., 15.)) == ["right", "start"]);
+    assert!(names(a.events_between(5., 60.)) == ["left", "right", "start", "left", "right", "start"]);
+    assert!(names(a.events_between(5., 0.)).is_empty());
+    assert!(names(a.events_between(35., 1.)).is_empty());
+    assert!(names(a.events_between(35., 6.)) == ["left"]);
+}
+
+
+fn rest(spacing: f32, i: usize) -> SimpleTransform {
+    SimpleTransform::new(Vec3::new(0., spacing * (i + 1) as f32, 0.), Default::default())
+}
+
+#[test]
+fn retarget() {
+    let source = (0..3)
+        .map(|i| AnimationJoint { name: format!("src{i}"), humanoid: Some(format!("bone{i}")), rest: rest(1., i) })
+        .collect::<Vec<_>>();
+    let target = Arc::new(Skeleton {
+        joints: (0..3).rev().map(|i| SkeletonJoint {
+            name: format!("dst{i}"),
+            humanoid: Some(format!("bone{i}")),
+            parents: (3 - i as u16..3).collect(),
+            ibm: Mat4x4::IDENTITY,
+            rest: rest(2., i)
+        }).collect()
+    });
+
+    let rotation = Quaternion::from_angle_z(std::f32::consts::FRAC_PI_2);
+    let mut frame = AnimationFrame {
+        root: Default::default(),
+        joints: source.iter().map(|joint| joint.rest).collect()
+    };
+    frame.joints[0].translation.x += 0.5;
+    for i in 0..3 {
+        frame.joints[i] = SimpleTransform::new(
+            frame.joints[0].translation + rotation * Vec3::new(0., i as f32, 0.),
+            rotation
+        )
+    }
+
+    let mapping = (0..3).map(|i| (format!("src{i}"), format!("dst{i}"))).collect::<HashMap<_, _>>();
+    for retarget in [
+        Retarget::new(&source, target.clone(), &mapping),
+        Retarget::humanoid(&source, target.clone())
+    ] {
+        let res = retarget.frame(&frame);
+        let expected = [Vec3::new(-3., 2., 0.), Vec3::new(-1., 2., 0.), Vec3::new(1., 2., 0.)];
+        for (joint, expected) in res.joints.iter().zip(expected) {
+            assert!((joint.translation - expected).length() < 0.001, "{:?} != {expected:?}", joint.translation);
+        }
+        let forward = res.joints[0].rotation * Vec3::new(0., 1., 0.);
+        assert!((forward - Vec3::new(-1., 0., 0.)).length() < 0.001);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use engine::{DrawPath, PbrMaterial, AnimatedInstance, compiler};
+use wgpu::Features;
+
+/// Instanced renderer of the batches, its pipeline is never created
+mod instanced {
+    shader!(
+        material    engine::PbrMaterial
+        vertex      engine::vertex::pnu::Vertex
+        instance    engine::AnimatedInstance
+        vbls        [Self::Vertex::LAYOUT, engine::AnimatedInstance::LAYOUT]
+        bgls        []
+        frag_stage  true
+        source      "shaders/model.wgsl"
+    );
+}
+
+#[test]
+fn draw_path() {
+    let multi_draw = Features::MULTI_DRAW_INDIRECT | Features::INDIRECT_FIRST_INSTANCE;
+    assert_eq!(DrawPath::new(multi_draw, true), DrawPath::MultiDrawIndirect);
+    assert_eq!(DrawPath::new(Features::INDIRECT_FIRST_INSTANCE, true), DrawPath::Indirect);
+    // Instanced draws need their first instance, without it they fall back to CPU draws
+    assert_eq!(DrawPath::new(Features::MULTI_DRAW_INDIRECT, true), DrawPath::Direct);
+    assert_eq!(DrawPath::new(Features::MULTI_DRAW_INDIRECT, false), DrawPath::MultiDrawIndirect);
+    assert_eq!(DrawPath::new(Features::empty(), false), DrawPath::Indirect);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn grouping() {
+    let e = crate::headless::engine(8, 4);
+    let mesh = || e.create_mesh::<engine::vertex::pnu::Vertex>(&compiler::Mesh {
+        positions: vec![[0.;3];12],
+        normals: vec![[0., 1., 0.];12],
+        uvs: vec![[0.;2];12],
+        indices: (0..12).collect(),
+        ..Default::default()
+    });
+    let (a, b) = (mesh(), mesh());
+    let (red, blue) = (Arc::new(PbrMaterial::new(e, Default::default())), Arc::new(PbrMaterial::new(e, Default::default())));
+    let instance = |id| AnimatedInstance { frames: [id, 0], ..Default::default() };
+
+    let mut batcher = e.create_batcher::<instanced::Shader>();
+    let draws = [(&b, &red, 0..3), (&a, &red, 3..6), (&b, &blue, 6..12), (&b, &red, 9..12), (&a, &red, 0..6)];
+    for (id, (mesh, material, vertices)) in draws.iter().cloned().enumerate() {
+        batcher.push(mesh, vertices, material, instance(id as u32))
+    }
+    batcher.update(e);
+    // Batches in the order their first draw was pushed, with their draws in push order
+    assert_eq!(batcher.batches(), [0..2, 2..4, 4..5]);
+    let ids = batcher.instances().iter().map(|v| v.frames[0]).collect::<Vec<_>>();
+    assert_eq!(ids, [0, 3, 1, 4, 2]);
+    let args = batcher.args().iter()
+        .map(|v| (v.base_vertex, v.vertex_count, v.base_instance, v.instance_count))
+        .collect::<Vec<_>>();
+    assert_eq!(args, [(0, 3, 0, 1), (9, 3, 1, 1), (3, 3, 2, 1), (0, 6, 3, 1), (6, 6, 4, 1)]);
+
+    // Pushed again, the same draws keep the same order
+    batcher.clear();
+    for (id, (mesh, material, vertices)) in draws.iter().cloned().enumerate() {
+        batcher.push(mesh, vertices, material, instance(id as u32))
+    }
+    batcher.update(e);
+    assert_eq!(batcher.instances().iter().map(|v| v.frames[0]).collect::<Vec<_>>(), ids);
+    assert_eq!(batcher.draw_count(), 5);
+    assert_eq!(batcher.batch_count(), 3)
+}
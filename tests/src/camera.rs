@@ -0,0 +1,41 @@
+use engine::{Camera, Vec3};
+
+#[test]
+fn frustum_corners() {
+    let camera = Camera {
+        position: Vec3::new(0., 0., 0.),
+        target: Vec3::new(0., 0., -1.),
+        fov: std::f32::consts::FRAC_PI_2,
+        aspect: 2.,
+        near: 0.1,
+        far: 100.
+    };
+    let corners = camera.frustum_corners(1., 10.);
+    for (i, distance) in [1., 10.].into_iter().enumerate() {
+        for corner in &corners[i * 4..i * 4 + 4] {
+            assert!((corner.z + distance).abs() < 0.0001, "{corner:?}");
+            assert!((corner.y.abs() - distance).abs() < 0.0001, "{corner:?}");
+            assert!((corner.x.abs() - distance * 2.).abs() < 0.0001, "{corner:?}")
+        }
+    }
+}
+
+#[test]
+fn vertical_view() {
+    for y in [-1., 1.] {
+        let camera = Camera {
+            position: Vec3::new(0., 0., 0.),
+            target: Vec3::new(0., y, 0.),
+            ..Default::default()
+        };
+        let corners = camera.frustum_corners(1., 10.);
+        for (i, distance) in [1., 10.].into_iter().enumerate() {
+            for corner in &corners[i * 4..i * 4 + 4] {
+                assert!(corner.x.is_finite() && corner.y.is_finite() && corner.z.is_finite(), "{corner:?}");
+                assert!((corner.y - distance * y).abs() < 0.0001, "{corner:?}")
+            }
+        }
+        let view = camera.projection() * camera.view();
+        assert!(<[[f32;4];4]>::from(view).iter().flatten().all(|v| v.is_finite()))
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::{Path, PathBuf};
+use engine::compiler::{humanoid_path, read_humanoid, Asset, Heightmap, Settings};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(name)
+}
+
+#[test]
+fn humanoid_sidecar() {
+    let mesh = fixture("character.glb");
+    assert_eq!(humanoid_path(&mesh), fixture("character.humanoid.json"));
+    assert_eq!(humanoid_path(&fixture("character.fbx")), humanoid_path(&mesh));
+
+    let humanoid = read_humanoid(&mesh);
+    assert_eq!(humanoid.len(), 3);
+    assert_eq!(humanoid["J_Bip_C_Hips"], "hips");
+    assert_eq!(humanoid["J_Bip_L_UpperLeg"], "leftUpperLeg");
+    assert!(read_humanoid(&fixture("missing.glb")).is_empty())
+}
+
+#[test]
+fn heightmap_asset() {
+    assert!(Settings::default().heightmap_height > 0.);
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/terrain");
+    let mut settings = Settings::default();
+    settings.merge(&dir);
+    assert!(settings.heightmap);
+    let heightmap = Heightmap::compile(&dir.join("heightmap.png"), &settings);
+    assert_eq!((heightmap.width, heightmap.depth), (257, 257));
+    // Flat clearing at the center, hills around it up to `heightmap_height`
+    assert!(heightmap.height(128, 128).abs() < 0.01);
+    let highest = heightmap.samples.iter().copied().max().unwrap();
+    assert!(highest > u16::MAX / 2, "{highest}");
+    assert_eq!(heightmap.max_height, settings.heightmap_height);
+}
@@ -0,0 +1,75 @@
+use engine::{
+    compiler::{Skeleton, SkeletonJoint},
+    Animation, AnimationFrame, AnimationClips, CrowdAgent, SimpleTransform, Vec3, Mat4x4
+};
+
+fn skeleton(joints: usize) -> Skeleton {
+    Skeleton {
+        joints: (0..joints).map(|i| SkeletonJoint {
+            name: format!("joint{i}"),
+            humanoid: None,
+            parents: Vec::new(),
+            ibm: Mat4x4::IDENTITY,
+            rest: Default::default()
+        }).collect()
+    }
+}
+
+/// Clip whose joint `j` is at `(frame + offset, j, 0)`
+fn clip(frames: usize, joints: usize, offset: f32) -> Animation {
+    Animation::new(
+        (0..frames).map(|frame| AnimationFrame {
+            root: Default::default(),
+            joints: (0..joints)
+                .map(|j| SimpleTransform::new(Vec3::new(frame as f32 + offset, j as f32, 0.), Default::default()))
+                .collect()
+        }).collect(),
+        Vec::new(),
+        Vec::new()
+    )
+}
+
+fn translation(matrix: [[f32;4];4]) -> [f32;3] {
+    [matrix[3][0], matrix[3][1], matrix[3][2]]
+}
+
+#[test]
+fn bank_sampling() {
+    let (clips, matrices) = AnimationClips::bake(&skeleton(2), &[clip(3, 2, 0.), clip(2, 2, 10.)]);
+    assert_eq!(clips.len(), 2);
+    assert_eq!((clips.frames(0), clips.frames(1)), (3, 2));
+    assert_eq!(matrices.len(), (3 + 2) * 2);
+
+    let mut agent = CrowdAgent::new(SimpleTransform::new(Vec3::new(4., 0., 0.), Default::default()), 1);
+    agent.time = 1.25;
+    let instance = clips.instance(&agent);
+    // Second frame of the second clip, blended with its first one as the clip loops
+    assert_eq!(instance.frames, [6 + 2, 6]);
+    assert_eq!(instance.blend, 0.25);
+    assert_eq!(translation(matrices[instance.frames[0] as usize + 1]), [11., 1., 0.]);
+    assert_eq!(translation(matrices[instance.frames[1] as usize]), [10., 0., 0.]);
+    assert_eq!(translation(instance.transform), [4., 0., 0.]);
+
+    agent.set_clip(0);
+    let instance = clips.instance(&agent);
+    assert_eq!((instance.frames, instance.blend), ([0, 2], 0.));
+}
+
+#[test]
+fn agent_advance() {
+    let (clips, _) = AnimationClips::bake(&skeleton(1), &[clip(3, 1, 0.), clip(2, 1, 0.)]);
+    let mut agent = CrowdAgent::new(Default::default(), 0);
+    agent.speed = 10.;
+    agent.update(&clips, 0.15);
+    assert!((agent.time - 1.5).abs() < 1e-5);
+    // Loops over the 3 frames of the clip
+    agent.update(&clips, 0.2);
+    assert!((agent.time - 0.5).abs() < 1e-5);
+
+    agent.set_clip(0);
+    assert!((agent.time - 0.5).abs() < 1e-5);
+    agent.set_clip(1);
+    assert_eq!((agent.clip, agent.time), (1, 0.));
+    agent.update(&clips, 0.25);
+    assert!((agent.time - 0.5).abs() < 1e-5)
+}
@@ -0,0 +1,47 @@
+use engine::{Aabb, Bvh, Camera, Vec3};
+
+#[test]
+fn frustum_aabb() {
+    let camera = Camera {
+        position: Vec3::new(0., 0., 5.),
+        ..Default::default()
+    };
+    let frustum = camera.frustum();
+    assert!(frustum.intersects_aabb(&Aabb::around(Vec3::default(), Vec3::new(1., 1., 1.))));
+    // Behind the camera, beyond the far plane and outside the 90° field of view
+    assert!(!frustum.intersects_aabb(&Aabb::around(Vec3::new(0., 0., 10.), Vec3::new(1., 1., 1.))));
+    assert!(!frustum.intersects_aabb(&Aabb::around(Vec3::new(0., 0., -200.), Vec3::new(1., 1., 1.))));
+    assert!(!frustum.intersects_aabb(&Aabb::around(Vec3::new(20., 0., 0.), Vec3::new(1., 1., 1.))));
+    // Straddling a plane is kept
+    assert!(frustum.intersects_aabb(&Aabb::around(Vec3::new(6., 0., 0.), Vec3::new(2., 1., 1.))));
+}
+
+#[test]
+fn bvh_cull() {
+    let camera = Camera {
+        position: Vec3::new(0., 0., 5.),
+        ..Default::default()
+    };
+    let bounds = (0..20)
+        .map(|i| Aabb::around(Vec3::new(i as f32 * 4. - 40., 0., 0.), Vec3::new(0.5, 0.5, 0.5)))
+        .collect::<Vec<_>>();
+    let bvh = Bvh::new(bounds.clone());
+    assert_eq!(bvh.len(), 20);
+    let expected = (0..20).filter(|&i| camera.frustum().intersects_aabb(&bounds[i])).collect::<Vec<_>>();
+    assert!(!expected.is_empty() && expected.len() < 20);
+    assert_eq!(bvh.cull(&[camera.frustum()]), expected);
+    assert!(Bvh::new(Vec::new()).cull(&[camera.frustum()]).is_empty());
+}
+
+#[test]
+fn bvh_raycast() {
+    let bounds = (0..20)
+        .map(|i| Aabb::around(Vec3::new(i as f32 * 4. - 40., 0., 0.), Vec3::new(0.5, 0.5, 0.5)))
+        .collect::<Vec<_>>();
+    let bvh = Bvh::new(bounds.clone());
+    // The closest box along the ray wins, a ray starting inside a box hits it at 0
+    assert_eq!(bvh.raycast(Vec3::new(-50., 0., 0.), Vec3::new(1., 0., 0.)), Some((0, 9.5)));
+    assert_eq!(bvh.raycast(Vec3::new(0., 0., 0.), Vec3::new(-1., 0., 0.)), Some((10, 0.)));
+    assert_eq!(bvh.raycast(Vec3::new(0., 2., 0.), Vec3::new(1., 0., 0.)), None);
+    assert_eq!(bounds[0].ray(Vec3::new(-50., 0., 0.), Vec3::new(-1., 0., 0.)), None);
+}
@@ -0,0 +1,49 @@
+use engine::{irradiance_sh, prefilter_radiance};
+
+#[test]
+fn irradiance() {
+    let (width, height) = (64, 32);
+    // A uniform white sky reflects exactly the diffuse color in every direction
+    let sh = irradiance_sh(width, height, &vec![[1.;3]; (width * height) as usize]);
+    assert!((sh[0][0] * 0.282095 - 1.).abs() < 0.01, "{:?}", sh[0]);
+    for c in &sh[1..] {
+        assert!(c[0].abs() < 0.01, "{c:?}")
+    }
+
+    // Light only from above, surfaces facing up receive it all and the ones facing down none
+    let pixels = (0..width * height)
+        .map(|i| if i / width < height / 2 { [1.;3] } else { [0.;3] })
+        .collect::<Vec<_>>();
+    let sh = irradiance_sh(width, height, &pixels);
+    let even = sh[0][0] * 0.282095 - sh[6][0] * 0.315392 - sh[8][0] * 0.546274;
+    let (up, down) = (even + sh[1][0] * 0.488603, even - sh[1][0] * 0.488603);
+    assert!((up - 1.).abs() < 0.1, "{up}");
+    assert!(down.abs() < 0.1, "{down}")
+}
+
+#[test]
+fn prefiltered_radiance() {
+    let (width, height) = (64, 32);
+    // A uniform sky stays uniform at every roughness
+    let levels = prefilter_radiance(width, height, vec![[0.5;3]; (width * height) as usize]);
+    assert_eq!(levels.len(), 6);
+    assert_eq!((levels[5].1, levels[5].2), (2, 1));
+    for (level, _, _) in levels.iter() {
+        assert!(level.iter().all(|v| (v[0] - 0.5).abs() < 0.001), "{level:?}")
+    }
+
+    // Light only from above, the first level keeps the sharp horizon and the roughest levels blur it
+    let pixels = (0..width * height)
+        .map(|i| if i / width < height / 2 { [1.;3] } else { [0.;3] })
+        .collect::<Vec<_>>();
+    let levels = prefilter_radiance(width, height, pixels.clone());
+    assert_eq!(levels[0].0, pixels);
+    let (level, w, h) = &levels[3];
+    let above = level[(h / 2 - 1) as usize * *w as usize];
+    let below = level[(h / 2) as usize * *w as usize];
+    assert!(above[0] < 1. && above[0] > 0.5, "{above:?}");
+    assert!(below[0] > 0. && below[0] < 0.5, "{below:?}");
+    // Close to the poles a smooth level stays fully lit and unlit
+    let (level, _, _) = &levels[1];
+    assert!((level[0][0] - 1.).abs() < 0.01 && level[level.len() - 1][0] < 0.01, "{:?} {:?}", level[0], level[level.len() - 1])
+}
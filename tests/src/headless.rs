@@ -0,0 +1,24 @@
+use engine::{Engine, RenderGraph, AdapterOptions, compiler::Pixels};
+
+/// Engine drawing to an offscreen target on the software adapter. GPU tests fail when there is none,
+/// building the tests without the default `gpu` feature ignores them instead
+pub fn engine(width: u32, height: u32) -> &'static Engine {
+    Engine::headless(width, height, AdapterOptions::software())
+        .unwrap_or_else(|e| panic!("{e}\nNo adapter for the GPU tests, run them with --no-default-features to ignore them"))
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn read_output() {
+    let e = crate::headless::engine(8, 4);
+    let mut graph = RenderGraph::new();
+    graph.add_pass("clear")
+        .write(RenderGraph::OUTPUT)
+        .execute(|ctx| drop(ctx.render_pass(&[RenderGraph::OUTPUT], None)));
+    e.render_graph(graph);
+    let image = e.read_output();
+    assert_eq!((image.width, image.height), (8, 4));
+    let Pixels::ARGB(pixels) = image.pixels else { panic!("Output is read back as RGBA") };
+    assert_eq!(pixels.len(), 8 * 4 * 4);
+    assert!(pixels.chunks(4).all(|v| v == [0, 0, 0, 255]));
+}
@@ -0,0 +1,86 @@
+use engine::{
+    compiler::{Skeleton, SkeletonJoint},
+    AnimationFrame, SimpleTransform, Vec3, Quaternion, Mat4x4,
+    IkConstraint, TwoBoneIk, ChainIk, ChainSolver, LookAt
+};
+
+fn chain(length: usize) -> (Skeleton, AnimationFrame) {
+    let skeleton = Skeleton {
+        joints: (0..length).map(|i| SkeletonJoint {
+            name: format!("joint{i}"),
+            humanoid: None,
+            parents: (0..i as u16).rev().collect(),
+            ibm: Mat4x4::IDENTITY,
+            rest: SimpleTransform::new(Vec3::new(0., i as f32, 0.), Default::default())
+        }).collect()
+    };
+    let frame = AnimationFrame {
+        root: Default::default(),
+        joints: (0..length)
+            .map(|i| SimpleTransform::new(Vec3::new(0., i as f32, 0.), Default::default()))
+            .collect()
+    };
+    (skeleton, frame)
+}
+
+fn assert_near(a: Vec3, b: Vec3) {
+    assert!((a - b).length() < 0.01, "{a:?} != {b:?}")
+}
+
+fn assert_bone_lengths(frame: &AnimationFrame) {
+    for joints in frame.joints.windows(2) {
+        assert!(((joints[1].translation - joints[0].translation).length() - 1.).abs() < 0.01)
+    }
+}
+
+#[test]
+fn two_bone() {
+    let (skeleton, mut frame) = chain(3);
+    let target = Vec3::new(1., 0.5, 0.);
+    let ik = TwoBoneIk::new(&skeleton, 2, target, Vec3::new(0., 1., 1.));
+    assert!(ik.root == 0 && ik.mid == 1);
+    IkConstraint::from(ik).solve(&skeleton, &mut frame, Default::default());
+    assert_near(frame.joints[2].translation, target);
+    assert_bone_lengths(&frame);
+    assert!(frame.joints[1].translation.z > 0.);
+}
+
+#[test]
+fn two_bone_world_space() {
+    let (skeleton, mut frame) = chain(3);
+    let transform = SimpleTransform::new(Vec3::new(5., 0., 0.), Quaternion::from_angle_y(1.));
+    let target = Vec3::new(5.5, 1., 0.5);
+    IkConstraint::from(TwoBoneIk::new(&skeleton, 2, target, Vec3::new(5., 1., 2.)))
+        .solve(&skeleton, &mut frame, transform);
+    assert_near(transform * frame.joints[2].translation, target);
+}
+
+#[test]
+fn chains() {
+    for solver in [ChainSolver::Fabrik, ChainSolver::Ccd] {
+        let (skeleton, mut frame) = chain(5);
+        let target = Vec3::new(2., 2., 1.);
+        let mut ik = ChainIk::new(&skeleton, 4, 5, target, solver);
+        ik.iterations = 50;
+        assert!(ik.joints == vec![0, 1, 2, 3, 4]);
+        IkConstraint::from(ik).solve(&skeleton, &mut frame, Default::default());
+        assert_near(frame.joints[4].translation, target);
+        assert_bone_lengths(&frame);
+    }
+}
+
+#[test]
+fn look_at() {
+    let (skeleton, mut frame) = chain(2);
+    let forward = Vec3::new(0., 0., 1.);
+    IkConstraint::from(LookAt::new(0, forward, Vec3::new(3., 0., 0.)))
+        .solve(&skeleton, &mut frame, Default::default());
+    assert_near(frame.joints[0].rotation * forward, Vec3::new(1., 0., 0.));
+    assert_near(frame.joints[1].translation, Vec3::new(0., 1., 0.));
+
+    let (skeleton, mut frame) = chain(2);
+    let mut look_at = LookAt::new(0, forward, Vec3::new(3., 0., 0.));
+    look_at.weight = 0.;
+    IkConstraint::from(look_at).solve(&skeleton, &mut frame, Default::default());
+    assert_near(frame.joints[0].rotation * forward, forward);
+}
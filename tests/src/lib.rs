@@ -1,2 +1,51 @@
+#[macro_use]
+extern crate macros;
+
 #[allow(unused)]
-pub mod math;
\ No newline at end of file
+pub mod math;
+#[allow(unused)]
+pub mod ik;
+#[allow(unused)]
+pub mod animation;
+#[allow(unused)]
+pub mod camera;
+#[allow(unused)]
+pub mod shader;
+#[allow(unused)]
+pub mod lights;
+#[allow(unused)]
+pub mod environment;
+#[allow(unused)]
+pub mod render_graph;
+#[allow(unused)]
+pub mod transparency;
+#[allow(unused)]
+pub mod culling;
+#[allow(unused)]
+pub mod batch;
+#[allow(unused)]
+pub mod headless;
+#[allow(unused)]
+pub mod adapter;
+#[allow(unused)]
+pub mod settings;
+#[allow(unused)]
+pub mod resolution;
+#[allow(unused)]
+pub mod sky;
+#[allow(unused)]
+pub mod time_of_day;
+#[allow(unused)]
+pub mod probes;
+#[allow(unused)]
+pub mod ssao;
+#[allow(unused)]
+pub mod terrain;
+#[allow(unused)]
+pub mod compiler;
+#[allow(unused)]
+pub mod skinning;
+#[allow(unused)]
+pub mod crowd;
+#[allow(unused)]
+pub mod shader_macro;
\ No newline at end of file
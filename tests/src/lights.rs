@@ -0,0 +1,38 @@
+use engine::{Camera, Vec3, ClusterList, PointLight, PunctualLight, CLUSTERS_X, CLUSTERS_Y, CLUSTERS_Z};
+
+#[test]
+fn clusters() {
+    let camera = Camera {
+        position: Vec3::new(0., 0., 0.),
+        target: Vec3::new(0., 0., -1.),
+        fov: std::f32::consts::FRAC_PI_2,
+        aspect: 1.,
+        near: 0.1,
+        far: 100.
+    };
+    let lights: Vec<PunctualLight> = vec![
+        PointLight::new(Vec3::new(0., 0., -10.), "#ffffff", 1., 1.).into(),
+        PointLight::new(Vec3::new(0., 0., 10.), "#ffffff", 1., 1.).into(),
+        PointLight::new(Vec3::new(0., 0., -0.5), "#ffffff", 1., 1.).into()
+    ];
+    let list = ClusterList::build(&camera, &lights);
+    let slice = ((10f32 / camera.near).ln() / (camera.far / camera.near).ln() * CLUSTERS_Z as f32) as u32;
+    let center = ClusterList::cluster(CLUSTERS_X / 2, CLUSTERS_Y / 2, slice);
+    assert!(list.lights(center).contains(&0));
+    // Far from the screen center at the same depth
+    assert!(!list.lights(ClusterList::cluster(0, 0, slice)).contains(&0));
+    // Behind the camera
+    assert!(list.indices.iter().all(|i| *i != 1));
+    // Touching the near plane, covers the whole screen
+    assert!(list.lights(ClusterList::cluster(0, 0, 0)).contains(&2));
+    assert!(list.lights(ClusterList::cluster(CLUSTERS_X - 1, CLUSTERS_Y - 1, 0)).contains(&2));
+    // Clusters follow each other in the flat index list, with their lights in order
+    let mut offset = 0;
+    for [start, count] in list.ranges.iter().copied() {
+        assert_eq!(start, offset);
+        offset += count
+    }
+    assert_eq!(offset as usize, list.indices.len());
+    let both = list.lights(ClusterList::cluster(CLUSTERS_X / 2, CLUSTERS_Y / 2, 0));
+    assert!(both.windows(2).all(|v| v[0] < v[1]))
+}
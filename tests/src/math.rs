@@ -1,5 +1,5 @@
 use cgmath::{Matrix4, SquareMatrix, Vector4, Rotation3, Deg, Rad, Euler, Matrix3, Decomposed, VectorSpace, InnerSpace, Vector3, Ortho};
-use math::{Mat4x4, Vec4, Quaternion, Transform, Vec3};
+use math::{Mat4x4, Vec4, Quaternion, Transform, Vec3, Vec2};
 
 #[test]
 fn math() {
@@ -92,6 +92,16 @@ fn math() {
     ));
 }
 
+#[test]
+fn distance() {
+    let (a, b) = ([1., -2., 0.5], [-3., 4., 2.]);
+    let expected = cgmath::MetricSpace::distance(Vector3::from(a), Vector3::from(b));
+    assert!((Vec3::from(a).distance(Vec3::from(b)) - expected).abs() < 1e-5);
+    assert_eq!(Vec3::from(a).distance(Vec3::from(a)), 0.);
+    assert_eq!(Vec2::new(0., 0.).distance(Vec2::new(3., 4.)), 5.);
+    assert_eq!(Vec4::new(1., 1., 1., 1.).distance(Vec4::new(2., 2., 2., 2.)), 2.);
+}
+
 fn compare_v(a: Vector4<f32>, b: Vec4) -> bool {
     let a: [f32;4] = a.into();
     let b: [f32;4] = b.into();
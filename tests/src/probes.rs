@@ -0,0 +1,40 @@
+use engine::{IrradianceVolume, ProbeGrid, Aabb, Vec3};
+
+#[test]
+fn probe_grid() {
+    let bounds = Aabb::new(Vec3::new(-1., 0., -1.), Vec3::new(1., 2., 1.));
+    // A uniform white sky gives the irradiance of a white lambertian surface reflecting 1 for every normal
+    let sky = ProbeGrid::bake(bounds, [2, 2, 2], |_, _| Vec3::new(1., 1., 1.));
+    assert_eq!(sky.probes.len(), 8);
+    for normal in [Vec3::new(0., 1., 0.), Vec3::new(1., 0., 0.), Vec3::new(0., -1., 0.)] {
+        let irradiance = sky.irradiance(Vec3::new(0.3, 1., -0.2), normal);
+        assert!((irradiance.x - 1.).abs() < 0.05, "{irradiance:?}");
+    }
+    // Under a roof, surfaces facing up get far less than those facing down
+    let roofed = ProbeGrid::bake(bounds, [2, 2, 2], |_, direction| {
+        if direction.y > 0. { Vec3::default() } else { Vec3::new(1., 1., 1.) }
+    });
+    let (up, down) = (roofed.irradiance(bounds.center(), Vec3::new(0., 1., 0.)), roofed.irradiance(bounds.center(), Vec3::new(0., -1., 0.)));
+    assert!(up.x < 0.2 && down.x > 0.8, "{up:?} {down:?}");
+    // Probes sit at the centers of their cells and blend linearly between them
+    assert!(roofed.position([1, 0, 0]).distance(Vec3::new(0.5, 0.5, -0.5)) < 1e-5);
+    let lit = ProbeGrid::bake(bounds, [2, 1, 1], |origin, _| if origin.x > 0. { Vec3::new(1., 1., 1.) } else { Vec3::default() });
+    let normal = Vec3::new(0., 1., 0.);
+    assert!(lit.irradiance(Vec3::new(-0.9, 1., 0.), normal).x < 0.01);
+    assert!((lit.irradiance(Vec3::new(0., 1., 0.), normal).x - 0.5).abs() < 0.03);
+    assert!((lit.irradiance(Vec3::new(0.9, 1., 0.), normal).x - 1.).abs() < 0.05);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn irradiance_volume() {
+    let e = crate::headless::engine(8, 4);
+    let bounds = Aabb::new(Vec3::new(-1., 0., -1.), Vec3::new(1., 2., 1.));
+    let mut sky = IrradianceVolume::bake(e, bounds, [2, 2, 2], |_, _| Vec3::new(1., 1., 1.));
+    assert!(sky.enabled);
+    sky.intensity = 0.5;
+    sky.update(e);
+    let irradiance = sky.irradiance(bounds.center(), Vec3::new(0., 1., 0.));
+    assert!((irradiance.x - 0.5).abs() < 0.03, "{irradiance:?}");
+    assert!(!IrradianceVolume::empty(e).enabled);
+}
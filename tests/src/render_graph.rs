@@ -0,0 +1,84 @@
+use engine::{RenderGraph, TextureDesc, DEPTH_FORMAT};
+use wgpu::TextureFormat;
+
+#[test]
+fn schedule() {
+    let mut graph = RenderGraph::new();
+    let color = TextureDesc::new(TextureFormat::Rgba16Float);
+    let hdr = graph.create("hdr", color);
+    let bloom = graph.create("bloom", color);
+    let depth = graph.create("depth", TextureDesc::new(DEPTH_FORMAT));
+    let unused = graph.create("unused", color);
+
+    // Declared out of order, the tone mapping reads what the passes after it write
+    graph.add_pass("tonemap").read(hdr).read(bloom).write(RenderGraph::OUTPUT);
+    graph.add_pass("bloom").read(hdr).write(bloom);
+    graph.add_pass("main").write(hdr).write(depth);
+    graph.add_pass("debug").read(depth).write(unused);
+    graph.add_pass("compute");
+
+    let schedule = graph.compile();
+    assert_eq!(schedule.order, vec![2, 1, 0, 4]);
+    assert_eq!(schedule.slot(unused), None);
+    assert_eq!(schedule.clears, vec![vec![true, true], vec![true], vec![true], vec![]]);
+    // hdr and bloom are alive at the same time, only the depth needs another description
+    assert_eq!(schedule.textures.len(), 3);
+
+    let mut graph = RenderGraph::new();
+    let a = graph.create("a", color);
+    let b = graph.create("b", color);
+    let c = graph.create("c", color);
+    graph.add_pass("a").write(a);
+    graph.add_pass("b").read(a).write(b);
+    graph.add_pass("c").read(b).write(c);
+    graph.add_pass("output").read(c).write(RenderGraph::OUTPUT);
+    let schedule = graph.compile();
+    // `a` is dead once `b` is written, so `c` reuses its memory
+    assert_eq!(schedule.textures.len(), 2);
+    assert_eq!(schedule.slot(a), schedule.slot(c));
+    assert_ne!(schedule.slot(a), schedule.slot(b));
+}
+
+#[test]
+#[should_panic(expected = "cycle")]
+fn cycle() {
+    let mut graph = RenderGraph::new();
+    let color = TextureDesc::new(TextureFormat::Rgba8Unorm);
+    let a = graph.create("a", color);
+    let b = graph.create("b", color);
+    graph.add_pass("a").read(b).write(a).write(RenderGraph::OUTPUT);
+    graph.add_pass("b").read(a).write(b);
+    graph.compile();
+}
+
+#[test]
+fn mip_chain() {
+    let mut graph = RenderGraph::new();
+    let color = TextureDesc::new(TextureFormat::Rgba16Float);
+    let levels = (0..3).map(|i| graph.create("level", color.scaled(0.5f32.powi(i)))).collect::<Vec<_>>();
+    graph.add_pass("down 0").write(levels[0]);
+    graph.add_pass("down 1").read(levels[0]).write(levels[1]);
+    graph.add_pass("down 2").read(levels[1]).write(levels[2]);
+    // Upsampling adds each level onto the previous one, after it was read by the downsampling
+    graph.add_pass("up 1").read(levels[2]).write(levels[1]);
+    graph.add_pass("up 0").read(levels[1]).write(levels[0]);
+    graph.add_pass("output").read(levels[0]).write(RenderGraph::OUTPUT);
+    let schedule = graph.compile();
+    assert_eq!(schedule.order, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(schedule.clears[3..5], [vec![false], vec![false]]);
+}
+
+#[test]
+fn resolve() {
+    let mut graph = RenderGraph::new();
+    let color = graph.create("color", TextureDesc::new(TextureFormat::Rgba16Float).samples(4));
+    let depth = graph.create("depth", TextureDesc::new(DEPTH_FORMAT).samples(4));
+    let hdr = graph.create("hdr", TextureDesc::new(TextureFormat::Rgba16Float));
+    graph.add_pass("main").write(color).write(depth).resolve(color, hdr);
+    graph.add_pass("output").read(hdr).write(RenderGraph::OUTPUT);
+    let schedule = graph.compile();
+    // Only the resolved texture is read, the multisampled ones live for the main pass alone
+    assert_eq!(schedule.order, vec![0, 1]);
+    assert_eq!(schedule.textures.len(), 3);
+    assert_eq!(schedule.textures[schedule.slot(color).unwrap()].samples, 4);
+}
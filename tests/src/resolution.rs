@@ -0,0 +1,47 @@
+use engine::{DynamicResolution, RenderGraph, TextureDesc, PostProcess, PostSettings, HDR_FORMAT, compiler::Pixels};
+
+#[test]
+fn dynamic_resolution() {
+    let mut dynamic = DynamicResolution::default();
+    // Within the budget the scale holds
+    assert_eq!(dynamic.update(16., 16.), 1.);
+    for _ in 0..100 { dynamic.update(40., 16.); }
+    assert_eq!(dynamic.scale(), dynamic.min_scale);
+    let mut previous = dynamic.scale();
+    for _ in 0..100 {
+        let scale = dynamic.update(4., 16.);
+        assert!(scale >= previous && scale - previous <= DynamicResolution::STEP + 1e-5);
+        previous = scale
+    }
+    assert_eq!(dynamic.scale(), dynamic.max_scale);
+    // A little over or under the budget holds the scale, far over drops it by two steps at most
+    let mut dynamic = DynamicResolution::default();
+    for _ in 0..100 { dynamic.update(16.5, 16.); }
+    assert_eq!(dynamic.scale(), 1.);
+    assert!((dynamic.update(1000., 16.) - (1. - DynamicResolution::STEP * 2.)).abs() < 1e-5);
+    for _ in 0..100 {
+        let scale = dynamic.update(20., 16.);
+        let steps = scale / DynamicResolution::STEP;
+        assert!((steps - steps.round()).abs() < 1e-3, "{scale}");
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn upscale() {
+    let e = crate::headless::engine(8, 4);
+    for sharpen in [None, Some(1.)] {
+        let mut post = PostProcess::new(e);
+        post.settings = PostSettings { bloom: None, vignette: None, sharpen, ..Default::default() };
+        post.update(e);
+        let mut graph = RenderGraph::new();
+        let hdr = graph.create("hdr", TextureDesc::new(HDR_FORMAT).scaled(0.5).clear(wgpu::Color::WHITE));
+        graph.add_pass("clear")
+            .write(hdr)
+            .execute(move |ctx| drop(ctx.render_pass(&[hdr], None)));
+        post.add_passes(e, &mut graph, hdr);
+        e.render_graph(graph);
+        let Pixels::ARGB(pixels) = e.read_output().pixels else { panic!("Output is read back as RGBA") };
+        assert!(pixels.chunks(4).all(|v| v == &pixels[..4] && v[0] > 100));
+    }
+}
@@ -0,0 +1,36 @@
+use engine::{GraphicsSettings, ShadowQuality, WindowMode};
+
+#[test]
+fn graphics_settings() {
+    let path = std::env::temp_dir().join(format!("nexodia-graphics-{}.json", std::process::id()));
+    let settings = GraphicsSettings {
+        window_mode: WindowMode::Borderless,
+        shadow_quality: ShadowQuality::Low,
+        max_fps: 144.,
+        ..Default::default()
+    };
+    settings.save(&path);
+    assert_eq!(GraphicsSettings::load(&path), settings);
+
+    // Missing fields keep their default and broken values are clamped
+    std::fs::write(&path, r#"{ "msaa": 3, "render_scale": 8.0 }"#).unwrap();
+    let loaded = GraphicsSettings::load(&path);
+    assert_eq!((loaded.msaa, loaded.render_scale), (1, 1.));
+    assert_eq!(loaded.shadow_quality, GraphicsSettings::default().shadow_quality);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(GraphicsSettings::load(&path), GraphicsSettings::default());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn applied_settings() {
+    let e = crate::headless::engine(8, 4);
+    // Sample counts the adapter rejects are not kept
+    for msaa in [16, 4, 1] {
+        e.set_graphics_settings(GraphicsSettings { msaa, ..e.graphics_settings() });
+        assert_eq!(e.graphics_settings().msaa, e.sample_count());
+    }
+    assert_eq!(e.sample_count(), 1);
+}
@@ -0,0 +1,20 @@
+use std::borrow::Cow;
+use engine::{preprocess, preprocess_with};
+
+#[test]
+fn includes() {
+    assert!(matches!(preprocess("fn main() {}"), Cow::Borrowed(_)));
+    let source = preprocess("#include <engine/shadow>\n    #include <engine/shadow>\nfn main() {}");
+    assert_eq!(source.matches("fn cascade_shadow").count(), 1);
+    assert!(source.ends_with("fn main() {}\n"));
+}
+
+#[test]
+fn defines() {
+    let source = "#define SIZE 4\n#ifdef SHADOWS\na\n#ifndef SKINNED\nb\n#else\nc\n#endif\n#else\nd\n#endif\nSIZE SIZED";
+    let lines = |defines| preprocess_with(source, defines).lines().filter(|v| !v.is_empty()).collect::<Vec<_>>().join(" ");
+    assert_eq!(lines(&[]), "d 4 SIZED");
+    assert_eq!(lines(&["SHADOWS"]), "a b 4 SIZED");
+    // The caller's value wins over the default of the source
+    assert_eq!(lines(&["SHADOWS", "SKINNED", "SIZE 8"]), "a c 8 SIZED");
+}
@@ -0,0 +1,91 @@
+use engine::{Shader, supported_polygon_mode};
+use wgpu::{Features, PolygonMode};
+
+mod material {
+    use engine::Engine;
+    bind_group_layouts!();
+    basic_material!(
+        (e: &Engine) {
+            create_bind_group!(
+                bind_group_layouts(&e.device)
+            )
+        }
+        bind_group_index 0
+    );
+}
+
+/// Every optional key of `shader!`, with a strip topology and a polygon mode the software adapter may lack
+mod keys {
+    shader!(
+        material        super::material::Material
+        vertex          engine::vertex::p::Vertex
+        instance        ()
+        vbls            [Self::Vertex::LAYOUT]
+        bgls            [&super::material::bind_group_layouts(&e.device)]
+        frag_stage      true
+        depth_bias      1
+        slope_bias      1.
+        samples         1
+        blend           additive
+        cull            front
+        depth_test      true
+        depth_write     false
+        depth_compare   greater_equal
+        topology        triangle_strip
+        polygon         line
+        targets         [wgpu::TextureFormat::Rgba8Unorm, engine::HDR_FORMAT]
+        color_writes    wgpu::ColorWrites::ALL
+        vs_entry        "vs_keys"
+        fs_entry        "fs_keys"
+        source          "shaders/keys.wgsl"
+        defines         ["UNUSED"]
+    );
+}
+
+/// Line strips without a fragment stage nor depth test
+mod lines {
+    shader!(
+        material        super::material::Material
+        vertex          engine::vertex::p::Vertex
+        instance        ()
+        vbls            [Self::Vertex::LAYOUT]
+        bgls            [&super::material::bind_group_layouts(&e.device)]
+        frag_stage      false
+        samples         1
+        cull            none
+        depth_test      false
+        topology        line_strip
+        polygon         point
+        vs_entry        "vs_keys"
+        source          "shaders/keys.wgsl"
+    );
+}
+
+#[test]
+fn polygon_fallback() {
+    for mode in [PolygonMode::Fill, PolygonMode::Line, PolygonMode::Point] {
+        assert_eq!(supported_polygon_mode(Features::all(), mode), mode);
+        assert_eq!(supported_polygon_mode(Features::empty(), mode), PolygonMode::Fill)
+    }
+    assert_eq!(supported_polygon_mode(Features::POLYGON_MODE_LINE, PolygonMode::Point), PolygonMode::Fill);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn pipelines() {
+    let e = crate::headless::engine(8, 4);
+    e.device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let (a, b) = (keys::Shader::new(e), keys::Shader::new(e));
+    let lines = lines::Shader::new(e);
+    if let Some(error) = pollster::block_on(e.device.pop_error_scope()) {
+        panic!("{error}")
+    }
+    // The same permutation and state share a pipeline
+    assert!(std::ptr::eq(a.pipeline(), b.pipeline()));
+    assert!(!std::ptr::eq(a.pipeline(), lines.pipeline()))
+}
+
+#[test]
+fn key_errors() {
+    trybuild::TestCases::new().compile_fail("ui/*.rs")
+}
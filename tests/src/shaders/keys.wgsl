@@ -0,0 +1,9 @@
+@vertex
+fn vs_keys(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 1.);
+}
+
+@fragment
+fn fs_keys() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.);
+}
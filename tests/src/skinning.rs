@@ -0,0 +1,24 @@
+use engine::RangeAllocator;
+
+#[test]
+fn range_allocator() {
+    let mut ranges = RangeAllocator::new(100);
+    let a = ranges.allocate(30).unwrap();
+    let b = ranges.allocate(30).unwrap();
+    let c = ranges.allocate(30).unwrap();
+    assert_eq!((a, b, c), (0, 30, 60));
+    assert_eq!(ranges.allocate(20), None);
+    assert_eq!(ranges.available(), 10);
+
+    ranges.release(a..a + 30);
+    ranges.release(c..c + 30);
+    assert_eq!(ranges.free(), &[0..30, 60..100]);
+    assert_eq!(ranges.allocate(35), Some(60));
+    ranges.release(60..95);
+
+    ranges.release(b..b + 30);
+    assert_eq!(ranges.free().to_vec(), vec![0..100]);
+    assert_eq!(ranges.allocate(100), Some(0));
+    assert_eq!(ranges.available(), 0);
+    assert_eq!(ranges.allocate(1), None)
+}
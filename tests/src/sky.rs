@@ -0,0 +1,56 @@
+use engine::{
+    Atmosphere, Sky, Fog, FogBinding, DirectionalLight, PostProcess, PostSettings, Quaternion,
+    RenderGraph, TextureDesc, Vec3, HDR_FORMAT, DEPTH_FORMAT, compiler::Pixels
+};
+
+#[test]
+fn atmosphere() {
+    let atmosphere = Atmosphere::default();
+    let up = Vec3::new(0., 1., 0.);
+    // Blue overhead with the sun high, reddened towards the sun at sunset
+    let noon = atmosphere.radiance(up, Vec3::new(1., 1., 0.));
+    assert!(noon.z > noon.x && noon.z > 0.);
+    let sun = Vec3::new(1., 0.02, 0.);
+    let sunset = atmosphere.radiance(Vec3::new(1., 0.05, 0.), sun);
+    assert!(sunset.x > sunset.z);
+    // No light reaches the sky once the sun is well below the horizon
+    let night = atmosphere.radiance(up, Vec3::new(1., -0.5, 0.));
+    assert!(night.length() < noon.length() * 1e-3);
+}
+
+#[test]
+fn fog_binding() {
+    let fog = Fog { start: 5., light_direction: Vec3::new(0., -2., 0.), ..Fog::new([0.2, 0.3, 0.4], 0.05) };
+    let binding = FogBinding::from(&fog);
+    assert_eq!(binding.color, [0.2, 0.3, 0.4, 0.05]);
+    assert_eq!(binding.direction, [0., -1., 0., 5.]);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn sky_pass() {
+    let e = crate::headless::engine(8, 8);
+    let light = DirectionalLight::new(e, Quaternion::from_to(Vec3::new(0., 0., 1.), Vec3::new(-1., -1., -1.)), 64, 1);
+    let mut sky = Sky::procedural(e, Atmosphere::default());
+    sky.sun_direction = light.world_direction();
+    sky.update(e);
+    let mut post = PostProcess::new(e);
+    post.settings = PostSettings { bloom: None, vignette: None, fxaa: false, ..Default::default() };
+    post.update(e);
+    let mut graph = RenderGraph::new();
+    let color = graph.create("scene color", TextureDesc::new(HDR_FORMAT));
+    let depth = graph.create("depth", TextureDesc::new(DEPTH_FORMAT));
+    graph.add_pass("main")
+        .write(color)
+        .write(depth)
+        .execute(|ctx| {
+            let mut render_pass = ctx.render_pass(&[color], Some(depth));
+            render_pass.set_bind_group(0, &e.camera_buffer.bind_group, &[]);
+            sky.render(&mut render_pass)
+        });
+    post.add_passes(e, &mut graph, color);
+    e.render_graph(graph);
+    let Pixels::ARGB(pixels) = e.read_output().pixels else { panic!("Output is read back as RGBA") };
+    // The top row looks above the horizon
+    assert!(pixels[..8 * 4].chunks(4).all(|v| v[2] > v[0] && v[2] > 0));
+}
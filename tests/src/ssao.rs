@@ -0,0 +1,23 @@
+use engine::{Ssao, PostProcess, PostSettings, RenderGraph, compiler::Pixels};
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn ssao_pass() {
+    let e = crate::headless::engine(8, 8);
+    let ssao = Ssao::new(e);
+    let mut post = PostProcess::new(e);
+    post.settings = PostSettings { bloom: None, vignette: None, fxaa: false, ..Default::default() };
+    post.update(e);
+    let mut graph = RenderGraph::new();
+    let prepass = Ssao::prepass(&mut graph, 1.);
+    graph.add_pass("prepass")
+        .write(prepass.normals)
+        .write(prepass.depth)
+        .execute(move |ctx| drop(ctx.render_pass(&[prepass.normals], Some(prepass.depth))));
+    let occlusion = ssao.add_passes(e, &mut graph, prepass);
+    post.add_passes(e, &mut graph, occlusion);
+    e.render_graph(graph);
+    let Pixels::ARGB(pixels) = e.read_output().pixels else { panic!("Output is read back as RGBA") };
+    // Nothing was drawn, so nothing is occluded
+    assert!(pixels.chunks(4).all(|v| v == &pixels[..4] && v[0] > 100));
+}
@@ -0,0 +1,105 @@
+use engine::{Heightfield, Terrain, TerrainMaterial, TerrainSettings, Vec3, compiler, mip_chain};
+
+fn heightfield(samples: u32, spacing: f32, origin: Vec3, height: impl Fn(f32, f32) -> f32) -> Heightfield {
+    let heights = (0..samples * samples)
+        .map(|i| height((i % samples) as f32 * spacing, (i / samples) as f32 * spacing))
+        .collect::<Vec<_>>();
+    Heightfield::new(compiler::Heightmap::from_heights(samples, samples, &heights), origin, spacing)
+}
+
+#[test]
+fn heightfield_queries() {
+    let origin = Vec3::new(-10., 1., 5.);
+    let plane = heightfield(65, 2., origin, |x, z| x * 0.5 + z * 0.25);
+    let expected = |x: f32, z: f32| 1. + (x - origin.x) * 0.5 + (z - origin.z) * 0.25;
+    for (x, z) in [(-10., 5.), (0.3, 7.9), (117., 133.), (54., 69.)] {
+        let height = plane.height_at(x, z).unwrap();
+        assert!((height - expected(x, z)).abs() < 0.01, "{height} at {x} {z}");
+    }
+    assert_eq!(plane.height_at(-10.5, 6.), None);
+    assert_eq!(plane.height_at(0., 134.), None);
+    let normal = plane.normal_at(20., 30.).unwrap();
+    assert!(normal.distance(Vec3::new(-0.5, 1., -0.25).normalized()) < 0.001, "{normal:?}");
+    // Straight down onto the ground, and over all of it without touching it
+    let distance = plane.raycast(Vec3::new(20., 100., 30.), Vec3::new(0., -1., 0.), 200.).unwrap();
+    assert!((distance - (100. - expected(20., 30.))).abs() < 0.01, "{distance}");
+    assert_eq!(plane.raycast(Vec3::new(-20., 100., 30.), Vec3::new(1., 0., 0.), 200.), None);
+    // Unbounded rays stop where they leave the terrain
+    let distance = plane.raycast(Vec3::new(20., 100., 30.), Vec3::new(0., -1., 0.), f32::INFINITY).unwrap();
+    assert!((distance - (100. - expected(20., 30.))).abs() < 0.01, "{distance}");
+    assert_eq!(plane.raycast(Vec3::new(20., 100., 30.), Vec3::new(1., 1., 0.), f32::INFINITY), None);
+}
+
+#[test]
+fn terrain_lods() {
+    let settings = TerrainSettings { lods: 3, lod_distance: 16., ..Default::default() };
+    // The finest LOD up to the distance, a coarser one each time it doubles up to the last
+    for (distance, lod) in [(0., 0), (16., 0), (17., 1), (31., 1), (32., 2), (1000., 2)] {
+        assert_eq!(settings.lod(distance), lod, "{distance}");
+    }
+    let field = heightfield(129, 1., Vec3::default(), |x, z| (x * 0.7).sin() * (z * 0.9).cos() * 3.);
+    // Each LOD halves the quads along the sides of a chunk
+    let mesh = field.chunk_mesh(16, [2, 3], [1;5]);
+    assert_eq!((mesh.positions.len(), mesh.indices.len()), (81, 8 * 8 * 6));
+    assert_eq!(mesh.positions[0], [32., field.height_at(32., 48.).unwrap(), 48.]);
+
+    // The edge of a chunk stitched to a coarser neighbour lies on the edge of the neighbour
+    let edge = |mesh: compiler::Mesh| {
+        let mut edge = mesh.positions.into_iter().filter(|v| v[0] == 16.).collect::<Vec<_>>();
+        edge.sort_by(|a, b| a[2].total_cmp(&b[2]));
+        edge.dedup();
+        edge
+    };
+    let coarse = edge(field.chunk_mesh(16, [1, 0], [2;5]));
+    let off_coarse = |fine: &[[f32;3]]| fine.iter().any(|v| {
+        let segment = coarse.windows(2).find(|s| s[0][2] <= v[2] && v[2] <= s[1][2]).unwrap();
+        let t = (v[2] - segment[0][2]) / (segment[1][2] - segment[0][2]);
+        (segment[0][1] + (segment[1][1] - segment[0][1]) * t - v[1]).abs() > 0.001
+    });
+    assert!(!off_coarse(&edge(field.chunk_mesh(16, [0, 0], [0, 0, 2, 0, 0]))));
+    assert!(off_coarse(&edge(field.chunk_mesh(16, [0, 0], [0;5]))));
+}
+
+#[test]
+fn layer_mips() {
+    // Black and white columns average to mid gray, brighter in sRGB where they are averaged as linear light
+    let pixels = (0..8).flat_map(|i| if i % 2 == 0 { [0, 0, 0, 255] } else { [255;4] }).collect::<Vec<u8>>();
+    let sizes = |chain: &[(Vec<u8>, u32, u32)]| chain.iter().map(|(pixels, w, h)| (pixels.len(), *w, *h)).collect::<Vec<_>>();
+    let unorm = mip_chain(4, 2, pixels.clone(), false);
+    assert_eq!(sizes(&unorm), [(32, 4, 2), (8, 2, 1), (4, 1, 1)]);
+    assert_eq!(unorm[1].0, [128, 128, 128, 255, 128, 128, 128, 255]);
+    let srgb = mip_chain(4, 2, pixels, true);
+    assert_eq!(srgb[2].0, [188, 188, 188, 255]);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn terrain_streaming() {
+    let e = crate::headless::engine(8, 4);
+    let white = || compiler::Image { width: 1, height: 1, pixels: compiler::Pixels::ARGB(vec![255;4]) };
+    let mut terrain = Terrain::new(
+        heightfield(129, 1., Vec3::default(), |x, z| (x * 0.7).sin() * (z * 0.9).cos() * 3.),
+        TerrainMaterial::new(e, white(), vec![white()], [1.;4], 1.),
+        TerrainSettings {
+            chunk_size: 16,
+            lods: 3,
+            lod_distance: 16.,
+            stream_distance: 48.,
+            builds_per_update: 4,
+            ..Default::default()
+        }
+    );
+    // 8x8 chunks, the nearest are built first within the budget of each update
+    terrain.update(e, Vec3::new(1., 0., 1.));
+    assert_eq!(terrain.loaded(), 4);
+    for _ in 0..32 { terrain.update(e, Vec3::new(1., 0., 1.)) }
+    assert!(terrain.loaded() > 4 && terrain.loaded() < 64, "{}", terrain.loaded());
+    assert_eq!(terrain.chunk_lod(0), Some(0));
+    assert_eq!(terrain.chunk_lod(2), Some(1));
+    assert_eq!(terrain.chunk_lod(3), Some(2));
+    assert_eq!(terrain.chunk_lod(63), None);
+    for _ in 0..32 { terrain.update(e, Vec3::new(127., 0., 127.)) }
+    assert_eq!(terrain.chunk_lod(0), None);
+    assert_eq!(terrain.chunk_lod(63), Some(0));
+
+}
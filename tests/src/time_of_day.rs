@@ -0,0 +1,37 @@
+use engine::{GameClock, TimeOfDay, Atmosphere};
+
+#[test]
+fn game_clock() {
+    let mut clock = GameClock::default();
+    clock.time_scale = 60.;
+    clock.set_time(86400. + 6. * 3600.);
+    assert_eq!((clock.day(), clock.hours()), (1, 6.));
+    clock.advance(60.);
+    assert_eq!(clock.hours(), 7.);
+
+    // Small differences with the server are absorbed evenly over `SYNC_SECONDS`, large ones applied at once
+    let server = clock.time() + 120.;
+    clock.synchronize(server, 0.);
+    clock.advance(GameClock::SYNC_SECONDS / 2.);
+    assert!((clock.time() - (server - 60. + GameClock::SYNC_SECONDS / 2. * 60.)).abs() < 1e-6);
+    clock.advance(GameClock::SYNC_SECONDS);
+    assert!((clock.time() - (server + GameClock::SYNC_SECONDS * 1.5 * 60.)).abs() < 1e-6);
+    clock.synchronize(clock.time() + 7200., 1.);
+    assert!((clock.time() - (server + GameClock::SYNC_SECONDS * 1.5 * 60. + 7260.)).abs() < 1e-6);
+}
+
+#[test]
+fn day_lighting() {
+    let time_of_day = TimeOfDay::default();
+    let atmosphere = Atmosphere::default();
+    let noon = time_of_day.lighting(12., &atmosphere);
+    assert!(noon.sun_direction.y < 0. && noon.light_direction.y < 0.);
+    assert_eq!((noon.light_intensity, noon.ambient), (time_of_day.sun_intensity, 1.));
+
+    let sunset = time_of_day.lighting(17.9, &atmosphere);
+    assert!(sunset.light_color[0] > sunset.light_color[2] && sunset.light_intensity < noon.light_intensity);
+
+    let midnight = time_of_day.lighting(0., &atmosphere);
+    assert!(midnight.sun_direction.y > 0. && midnight.light_direction.y < 0.);
+    assert_eq!((midnight.light_color, midnight.ambient), (time_of_day.moon_color, time_of_day.night_ambient));
+}
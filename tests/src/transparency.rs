@@ -0,0 +1,89 @@
+use engine::{Camera, TransparentQueue, Vec3, Shader, ModelRenderer, AlphaMode, compiler};
+
+/// Renderers of the blended surfaces and of the opaque and masked ones, over a position only shader
+mod blend {
+    shader!(
+        material    engine::PbrMaterial
+        vertex      engine::vertex::pnu::Vertex
+        instance    ()
+        vbls        [Self::Vertex::LAYOUT]
+        bgls        []
+        frag_stage  true
+        samples     1
+        source      "shaders/model.wgsl"
+    );
+    impl engine::ModelRenderer for Shader {
+        const ALPHA_MODES: &'static [engine::AlphaMode] = &[engine::AlphaMode::Blend];
+    }
+}
+mod surface {
+    shader!(
+        material    engine::PbrMaterial
+        vertex      engine::vertex::pnu::Vertex
+        instance    ()
+        vbls        [Self::Vertex::LAYOUT]
+        bgls        []
+        frag_stage  true
+        samples     1
+        source      "shaders/model.wgsl"
+    );
+    impl engine::ModelRenderer for Shader {
+        const ALPHA_MODES: &'static [engine::AlphaMode] = &[engine::AlphaMode::Opaque, engine::AlphaMode::Mask];
+    }
+}
+
+fn camera() -> Camera {
+    Camera {
+        position: Vec3::new(0., 1., 5.),
+        target: Vec3::new(0., 1., 0.),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn view_depth() {
+    let queue = TransparentQueue::new(&camera());
+    assert!(queue.is_empty());
+    assert_eq!(queue.depth(Vec3::new(0., 1., 0.)), 5.);
+    // Sideways offsets do not change the order, only the distance along the view
+    assert_eq!(queue.depth(Vec3::new(3., -2., 2.)), 3.);
+    assert_eq!(queue.depth(Vec3::new(0., 1., 7.)), -2.);
+}
+
+#[test]
+fn back_to_front() {
+    let mut queue = TransparentQueue::new(&camera());
+    for z in [0., 3., -4., 1., 3.] {
+        queue.push(Vec3::new(0., 0., z), |_| {})
+    }
+    assert_eq!(queue.len(), 5);
+    assert_eq!(queue.depths(), [9., 5., 4., 2., 2.]);
+}
+
+#[test]
+#[cfg_attr(not(feature = "gpu"), ignore = "needs a GPU adapter")]
+fn alpha_modes() {
+    let e = crate::headless::engine(8, 4);
+    // One quad per alpha mode, further from the camera each
+    let quad = |z: f32| [[-1., 0., z], [1., 0., z], [1., 2., z], [-1., 2., z]];
+    let material = |alpha_cutoff: Option<f32>, alpha_blend: bool| compiler::Material { alpha_cutoff, alpha_blend, ..Default::default() };
+    let model = e.create_model::<engine::vertex::pnu::Vertex>(compiler::Mesh {
+        positions: [quad(0.), quad(-1.), quad(-2.)].concat(),
+        normals: vec![[0., 0., 1.];12],
+        uvs: vec![[0., 0.];12],
+        indices: (0..3).flat_map(|i| [0, 1, 2, 0, 2, 3].map(|v| v + i * 4)).collect(),
+        materials: vec![material(None, false), material(Some(0.5), false), material(None, true)],
+        submeshes: (0..3).map(|i| compiler::Submesh { material: Some(i), start: i * 6, len: 6 }).collect(),
+        ..Default::default()
+    });
+    let modes = model.materials.iter().map(|material| material.alpha_mode).collect::<Vec<_>>();
+    assert_eq!(modes, [AlphaMode::Opaque, AlphaMode::Mask, AlphaMode::Blend]);
+
+    let (blend, surface) = (blend::Shader::new(e), surface::Shader::new(e));
+    let mut queue = TransparentQueue::new(&camera());
+    blend.queue_model(&mut queue, &model);
+    assert_eq!(queue.depths(), [7.]);
+    let mut queue = TransparentQueue::new(&camera());
+    surface.queue_model(&mut queue, &model);
+    assert_eq!(queue.depths(), [6., 5.]);
+}
@@ -0,0 +1,15 @@
+#[macro_use]
+extern crate macros;
+
+shader!(
+    material        engine::PbrMaterial
+    vertex          engine::vertex::p::Vertex
+    instance        ()
+    vbls            [Self::Vertex::LAYOUT]
+    bgls            []
+    frag_stage      true
+    cull            back
+    cull            none
+);
+
+fn main() {}
@@ -0,0 +1,14 @@
+#[macro_use]
+extern crate macros;
+
+shader!(
+    material        engine::PbrMaterial
+    vertex          engine::vertex::p::Vertex
+    instance        ()
+    vbls            [Self::Vertex::LAYOUT]
+    bgls            []
+    frag_stage      true
+    depth_clamp     true
+);
+
+fn main() {}